@@ -1,12 +1,19 @@
 use leptos::*;
 use leptos_meta::*;
 use leptos_router::*;
-use time::*;
 
 use crate::frontend::*;
 use overlay::*;
+use pages::agenda::*;
+use pages::categories::*;
+use pages::jobs::*;
 use pages::month::*;
 use pages::day::*;
+use pages::login::*;
+use pages::tasks::*;
+use pages::user::*;
+use pages::week::*;
+use pages::year::*;
 
 #[component]
 pub fn App(cx: Scope) -> impl IntoView {
@@ -28,8 +35,21 @@ pub fn App(cx: Scope) -> impl IntoView {
 			<main>
 				<Overlay>
 					<Routes>
-						<Route path="/month" view=|cx| view! { cx, <MonthView year=2023 month={Month::January}/> }/>
-						<Route path="/day" view=|cx| view!{cx, <DayView date={Date::from_calendar_date(2022, Month::December, 1).unwrap()} />}/>
+						<Route path="/year" view=|cx| view! { cx, <YearPage/> }/>
+						<Route path="/year/:year" view=|cx| view! { cx, <YearPage/> }/>
+						<Route path="/month" view=|cx| view! { cx, <MonthPage/> }/>
+						<Route path="/month/:year/:month" view=|cx| view! { cx, <MonthPage/> }/>
+						<Route path="/week" view=|cx| view! { cx, <WeekPage/> }/>
+						<Route path="/week/:year/:month/:day" view=|cx| view! { cx, <WeekPage/> }/>
+						<Route path="/day" view=|cx| view! { cx, <DayPage/> }/>
+						<Route path="/day/:year/:month/:day" view=|cx| view! { cx, <DayPage/> }/>
+						<Route path="/agenda" view=|cx| view! { cx, <AgendaView/> }/>
+						<Route path="/tasks" view=|cx| view! { cx, <TasksPage/> }/>
+						<Route path="/categories" view=|cx| view! { cx, <CategoriesPage/> }/>
+						<Route path="/login" view=|cx| view! { cx, <LoginPage/> }/>
+						<Route path="/signup" view=|cx| view! { cx, <SignupPage/> }/>
+						<Route path="/user" view=|cx| view! { cx, <UserPage/> }/>
+						<Route path="/admin/jobs" view=|cx| view! { cx, <JobsPage/> }/>
 					</Routes>
 				</Overlay>
 			</main>
@@ -0,0 +1,194 @@
+use std::future::Future;
+use std::pin::Pin;
+
+use actix_web::cookie::{time::Duration as CookieDuration, Cookie, SameSite};
+use actix_web::dev::Payload;
+use actix_web::{http::header, post, web, FromRequest, HttpRequest, HttpResponse};
+use chrono::{Duration, Utc};
+use serde::Deserialize;
+use surrealdb::sql::Uuid;
+
+use crate::backend::database::{db_error::DBerror, store::Store};
+use crate::backend::password::{hash_token, random_token, verify_dummy};
+use crate::common::model::Session;
+
+/// Name of the cookie holding the session token.
+pub const SESSION_COOKIE: &str = "gressus_session";
+
+/// How long a session stays valid after logging in.
+pub const SESSION_DAYS: i64 = 30;
+
+/// Passwords shorter than this are refused at signup.
+pub const MIN_PASSWORD_LENGTH: usize = 8;
+
+/// The user whose session cookie came with the request.
+///
+/// Use this as a handler argument, or `current_user` in server functions, instead of taking a user
+/// id from the client. Extraction fails with `DBerror::SessionNotFound` (401) when the cookie is
+/// missing, unknown or expired.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CurrentUser {
+    pub uuid: Uuid,
+    /// Hash of the session token, to end this session on logout
+    pub session: String,
+}
+
+impl CurrentUser {
+    ///looks up the session belonging to the request's cookie
+    pub async fn from_http_request(req: &HttpRequest) -> Result<Self, DBerror> {
+        let db = req.app_data::<web::Data<dyn Store>>()
+            .ok_or(DBerror::InvalidConfig("no store registered with the app".to_string()))?;
+        let token = req.cookie(SESSION_COOKIE).ok_or(DBerror::SessionNotFound)?;
+        let session = db.get_session(&hash_token(token.value())).await?;
+        if session.expires_at <= Utc::now() {
+            db.delete_session(&session.token_hash).await?;
+            return Err(DBerror::SessionNotFound);
+        }
+        Ok(CurrentUser { uuid: session.user, session: session.token_hash })
+    }
+}
+
+impl FromRequest for CurrentUser {
+    type Error = DBerror;
+    type Future = Pin<Box<dyn Future<Output = Result<Self, Self::Error>>>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let req = req.clone();
+        Box::pin(async move { CurrentUser::from_http_request(&req).await })
+    }
+}
+
+///whether a user may see the admin pages, which the comma-separated user names in the `ADMIN_USERS`
+///environment variable may
+pub fn is_admin(name: &str) -> bool {
+    std::env::var("ADMIN_USERS").is_ok_and(|x| x.split(',').any(|x| x.trim() == name))
+}
+
+fn request(cx: leptos::Scope) -> Result<HttpRequest, DBerror> {
+    leptos::use_context::<HttpRequest>(cx)
+        .ok_or(DBerror::InvalidConfig("server function called without a request".to_string()))
+}
+
+///the logged-in user inside a server function, which gets the request from the leptos context
+pub async fn current_user(cx: leptos::Scope) -> Result<CurrentUser, DBerror> {
+    CurrentUser::from_http_request(&request(cx)?).await
+}
+
+///the store registered with the app, inside a server function
+pub fn store(cx: leptos::Scope) -> Result<web::Data<dyn Store>, DBerror> {
+    request(cx)?.app_data::<web::Data<dyn Store>>()
+        .cloned()
+        .ok_or(DBerror::InvalidConfig("no store registered with the app".to_string()))
+}
+
+///starts a session for a user, returning the cookie that identifies it
+pub async fn start_session(db: &dyn Store, user: &Uuid) -> Result<Cookie<'static>, DBerror> {
+    let token = random_token();
+    let now = Utc::now();
+    db.add_session(&Session {
+        token_hash: hash_token(&token),
+        user: user.clone(),
+        created_at: now,
+        expires_at: now + Duration::days(SESSION_DAYS),
+    }).await?;
+
+    Ok(Cookie::build(SESSION_COOKIE, token)
+        .path("/")
+        .http_only(true)
+        .secure(true)
+        .same_site(SameSite::Lax)
+        .max_age(CookieDuration::days(SESSION_DAYS))
+        .finish())
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Credentials {
+    pub username: String,
+    pub password: String,
+}
+
+fn redirect(location: &str) -> HttpResponse {
+    HttpResponse::SeeOther().insert_header((header::LOCATION, location)).finish()
+}
+
+/// Creates an account from the signup form and logs it in.
+#[post("/auth/signup")]
+pub async fn signup(db: web::Data<dyn Store>, form: web::Form<Credentials>) -> Result<HttpResponse, DBerror> {
+    let username = form.username.trim();
+    if username.is_empty() {
+        return Ok(redirect("/signup?error=username"));
+    }
+    if form.password.chars().count() < MIN_PASSWORD_LENGTH {
+        return Ok(redirect("/signup?error=password"));
+    }
+    let user = match db.add_user(username, &form.password).await {
+        Ok(user) => user,
+        Err(DBerror::UserAlreadyExists(_)) => return Ok(redirect("/signup?error=taken")),
+        Err(x) => return Err(x),
+    };
+
+    let cookie = start_session(db.get_ref(), &user.uuid).await?;
+    let mut response = redirect("/month");
+    response.add_cookie(&cookie).map_err(|x| DBerror::InvalidConfig(x.to_string()))?;
+    Ok(response)
+}
+
+/// Checks the login form and starts a session.
+#[post("/auth/login")]
+pub async fn login(db: web::Data<dyn Store>, form: web::Form<Credentials>) -> Result<HttpResponse, DBerror> {
+    // unknown users and wrong passwords get the same answer, after as much hashing
+    let user = match db.user_id_from_name(form.username.trim()).await {
+        Ok(user) => user,
+        Err(DBerror::UserNameNotFound(_)) => {
+            verify_dummy(&form.password);
+            return Ok(redirect("/login?error=invalid"));
+        }
+        Err(x) => return Err(x),
+    };
+    if !db.verify_password(&user, &form.password).await? {
+        return Ok(redirect("/login?error=invalid"));
+    }
+
+    let cookie = start_session(db.get_ref(), &user).await?;
+    let mut response = redirect("/month");
+    response.add_cookie(&cookie).map_err(|x| DBerror::InvalidConfig(x.to_string()))?;
+    Ok(response)
+}
+
+/// Ends the current session, if there is one, and removes its cookie.
+#[post("/auth/logout")]
+pub async fn logout(db: web::Data<dyn Store>, req: HttpRequest) -> Result<HttpResponse, DBerror> {
+    if let Ok(user) = CurrentUser::from_http_request(&req).await {
+        db.delete_session(&user.session).await?;
+    }
+
+    let mut response = redirect("/login");
+    response.add_cookie(&removal_cookie()).map_err(|x| DBerror::InvalidConfig(x.to_string()))?;
+    Ok(response)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PasswordConfirmation {
+    pub password: String,
+}
+
+/// Deletes the logged-in user's account with everything in it, once the password is given again.
+#[post("/auth/delete")]
+pub async fn delete_account(db: web::Data<dyn Store>, user: CurrentUser, form: web::Form<PasswordConfirmation>) -> Result<HttpResponse, DBerror> {
+    if !db.verify_password(&user.uuid, &form.password).await? {
+        return Ok(redirect("/user?error=password"));
+    }
+    db.delete_user(&user.uuid).await?;
+
+    let mut response = redirect("/signup");
+    response.add_cookie(&removal_cookie()).map_err(|x| DBerror::InvalidConfig(x.to_string()))?;
+    Ok(response)
+}
+
+///a cookie telling the browser to forget its session token
+fn removal_cookie() -> Cookie<'static> {
+    let mut removal = Cookie::named(SESSION_COOKIE);
+    removal.set_path("/");
+    removal.make_removal();
+    removal
+}
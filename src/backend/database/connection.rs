@@ -0,0 +1,58 @@
+use std::env;
+
+use surrealdb::{Surreal, engine::any::{self, Any}, opt::auth::Root};
+
+use crate::backend::database::db_error::DBerror;
+
+/// The SurrealDB engine the server keeps its data in.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DatabaseEngine {
+    /// A separately launched `surreal` server at the given address, reached over WebSocket.
+    Remote(String),
+    /// Embedded in-memory engine, everything is lost on shutdown. Needs the `kv-mem` feature.
+    Memory,
+    /// Embedded engine storing its data in the given directory. Needs the `kv-rocksdb` feature.
+    File(String),
+}
+
+impl DatabaseEngine {
+    ///reads the engine from the `DATABASE_ENGINE` (`remote`, `memory` or `file`), `DATABASE_ADDRESS` and `DATABASE_PATH` environment variables
+    pub fn from_env() -> Result<Self, DBerror> {
+        let engine = env::var("DATABASE_ENGINE").unwrap_or_else(|_| "remote".to_string());
+        match engine.as_str() {
+            "remote" => Ok(DatabaseEngine::Remote(env::var("DATABASE_ADDRESS").unwrap_or_else(|_| "127.0.0.1:8000".to_string()))),
+            "memory" => Ok(DatabaseEngine::Memory),
+            "file" => Ok(DatabaseEngine::File(env::var("DATABASE_PATH").unwrap_or_else(|_| "gressus.db".to_string()))),
+            other => Err(DBerror::InvalidConfig(format!("unknown DATABASE_ENGINE '{other}', expected remote, memory or file"))),
+        }
+    }
+
+    ///the endpoint url SurrealDB uses to pick the engine
+    pub fn endpoint(&self) -> String {
+        match self {
+            DatabaseEngine::Remote(address) => format!("ws://{address}"),
+            DatabaseEngine::Memory => "mem://".to_string(),
+            DatabaseEngine::File(path) => format!("file://{path}"),
+        }
+    }
+}
+
+///connects to the given engine and selects the main namespace and database
+///
+///remote servers are signed in to with the `DATABASE_USER` and `DATABASE_PASS` environment variables, embedded engines need no credentials
+pub async fn connect(engine: &DatabaseEngine) -> Result<Surreal<Any>, DBerror> {
+    let db = any::connect(engine.endpoint()).await?;
+
+    if let DatabaseEngine::Remote(_) = engine {
+        let username = env::var("DATABASE_USER").map_err(|_| DBerror::InvalidConfig("DATABASE_USER is not set".to_string()))?;
+        let password = env::var("DATABASE_PASS").map_err(|_| DBerror::InvalidConfig("DATABASE_PASS is not set".to_string()))?;
+        db.signin(Root {
+            username: &username,
+            password: &password,
+        })
+        .await?;
+    }
+
+    db.use_ns("main").use_db("main").await?;
+    Ok(db)
+}
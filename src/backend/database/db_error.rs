@@ -1,6 +1,9 @@
 use surrealdb::sql::Uuid;
 use thiserror::Error;
 
+use crate::common::model::TimespanError;
+use crate::common::recurrence::RecurrenceError;
+
 #[derive(Debug, Error)]
 pub enum DBerror {
     #[error("database error: {0}")]
@@ -17,4 +20,38 @@ pub enum DBerror {
     EventNotFound(Uuid),
     #[error("no category found with uuid {0}")]
     CategoryNotFound(Uuid),
-}
\ No newline at end of file
+    #[error("invalid database configuration: {0}")]
+    InvalidConfig(String),
+    #[error("database schema version {0} is newer than version {1} supported by this build")]
+    SchemaTooNew(u32, u32),
+    #[error("password hashing failed: {0}")]
+    PasswordHash(String),
+    #[error("no valid session, log in first")]
+    SessionNotFound,
+    #[error("no calendar feed found for this token")]
+    FeedNotFound,
+    #[error("no reminder found with id {0}")]
+    ReminderNotFound(String),
+    #[error("no job found with name {0}")]
+    JobNotFound(String),
+    #[error("category {0} cannot take over the items of the deleted category")]
+    InvalidReplacement(Uuid),
+    #[error("invalid timespan: {0}")]
+    InvalidTimespan(#[from] TimespanError),
+    #[error("invalid recurrence: {0}")]
+    InvalidRecurrence(#[from] RecurrenceError),
+}
+
+#[cfg(feature = "ssr")]
+impl actix_web::ResponseError for DBerror {
+    fn status_code(&self) -> actix_web::http::StatusCode {
+        use actix_web::http::StatusCode;
+        match self {
+            DBerror::UserNameNotFound(_) | DBerror::UserNotFound(_) | DBerror::TaskNotFound(_) | DBerror::EventNotFound(_) | DBerror::CategoryNotFound(_) | DBerror::FeedNotFound | DBerror::ReminderNotFound(_) | DBerror::JobNotFound(_) => StatusCode::NOT_FOUND,
+            DBerror::SessionNotFound => StatusCode::UNAUTHORIZED,
+            DBerror::UserAlreadyExists(_) => StatusCode::CONFLICT,
+            DBerror::InvalidTimespan(_) | DBerror::InvalidRecurrence(_) | DBerror::InvalidReplacement(_) => StatusCode::BAD_REQUEST,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
@@ -1,23 +1,29 @@
-use chrono::{Utc, DateTime};
-use surrealdb::{sql::Uuid, Surreal, engine::remote::ws::Client};
+use chrono::{Duration, Utc, DateTime};
+use serde::Serialize;
+use surrealdb::{sql::Uuid, Surreal, Connection};
 
 use crate::backend::database::db_error::DBerror;
-use crate::common::model::{User, Timespan, Task, Event, Category};
-
-///adds user to database
-pub async fn add_user(db: &Surreal<Client>, username: &str, password: &Vec<u8>) -> Result<User, DBerror> {
-    match user_id_from_name(db, username).await {
-        Ok(_) => return Err(DBerror::UserAlreadyExists(username.to_string())),
-        Err(_) => (),
+use crate::backend::password;
+use crate::common::model::{User, Timespan, Task, Event, Category, Session, WeekStart, LocalZone, ReminderDelivery, Job, UNCATEGORISED, UNCATEGORISED_COLOR};
+use crate::common::recurrence::Recurrence;
+
+///adds user to database, hashing the plaintext password
+pub async fn add_user<C: Connection>(db: &Surreal<C>, username: &str, password: &str) -> Result<User, DBerror> {
+    if user_id_from_name(db, username).await.is_ok() {
+        return Err(DBerror::UserAlreadyExists(username.to_string()));
     }
     let time = Utc::now();
     let id = Uuid::new();
 
     let new_user = User {
         name: username.to_string(),
-        hashed_password: password.to_vec(),
+        hashed_password: password::hash_password(password)?,
         joined_at: time,
         categories: Vec::new(),
+        feed_token: password::random_token(),
+        week_start: WeekStart::default(),
+        email: None,
+        time_zone: LocalZone::default(),
         uuid: id.clone(),
     };
     let created: User = db.create(("users", id.to_raw())).content(new_user).await?;
@@ -25,51 +31,64 @@ pub async fn add_user(db: &Surreal<Client>, username: &str, password: &Vec<u8>)
     Ok(created)
 }
 
-///adds task to database
-pub async fn add_task(db: &Surreal<Client>, name: &str, description: &str, start: &DateTime::<Utc>, end: &DateTime::<Utc>, category: &Uuid, user: &Uuid) -> Result<Task, DBerror> {
-    let timespan = Timespan::new(start, end);
+///adds task to database, timed or on whole dates and optionally recurring
+pub async fn add_task<C: Connection>(db: &Surreal<C>, name: &str, description: &str, timespan: &Timespan, category: &Uuid, recurrence: Option<Recurrence>, user: &Uuid) -> Result<Task, DBerror> {
+    timespan.validate()?;
+    if let Some(recurrence) = &recurrence {
+        recurrence.validate()?;
+    }
     let id = Uuid::new();
 
     let new_task = Task {
         name: name.to_string(),
         description: description.to_string(),
-        timespan,
+        timespan: timespan.clone(),
         category: category.clone(),
         completed: false,
+        recurrence,
+        ical_uid: None,
+        reminders: Vec::new(),
         user: user.clone(),
         uuid: id.clone(),
     };
 
-    let created: Task = db.create(("tasks", id.to_raw())).content(new_task).await?;
+    let created: Task = db.create(("tasks", id.to_raw())).content(StoredItem::task(&new_task)).await?;
 
     Ok(created)
 }
 
-///adds event to database
-pub async fn add_event(db: &Surreal<Client>, name: &str, description: &str, start: &DateTime::<Utc>, end: &DateTime::<Utc>, category: &Uuid, user: &Uuid) -> Result<Event, DBerror> {
-    let timespan = Timespan::new(start, end);
+///adds event to database, timed or on whole dates and optionally recurring
+pub async fn add_event<C: Connection>(db: &Surreal<C>, name: &str, description: &str, timespan: &Timespan, category: &Uuid, recurrence: Option<Recurrence>, user: &Uuid) -> Result<Event, DBerror> {
+    timespan.validate()?;
+    if let Some(recurrence) = &recurrence {
+        recurrence.validate()?;
+    }
     let id = Uuid::new();
 
     let new_event = Event {
         name: name.to_string(),
         description: description.to_string(),
-        timespan,
+        timespan: timespan.clone(),
         category: category.clone(),
+        recurrence,
+        ical_uid: None,
+        reminders: Vec::new(),
         user: user.clone(),
         uuid: id.clone(),
     };
-    let created: Event = db.create(("events", id.to_raw())).content(new_event).await?;
+    let created: Event = db.create(("events", id.to_raw())).content(StoredItem::event(&new_event)).await?;
 
     Ok(created)
 }
 
 ///adds category to database
-pub async fn add_category(db: &Surreal<Client>, name: &str, color: u32, user: &Uuid) -> Result<Category, DBerror> {
+pub async fn add_category<C: Connection>(db: &Surreal<C>, name: &str, color: u32, user: &Uuid) -> Result<Category, DBerror> {
     let id = Uuid::new();
 
     let new_category = Category {
         name: name.to_string(),
         color,
+        hidden: false,
         user: user.clone(),
         uuid: id.clone(),
     };
@@ -86,183 +105,583 @@ pub async fn add_category(db: &Surreal<Client>, name: &str, color: u32, user: &U
 }
 
 ///get user from Uuid
-pub async fn get_user(db: &Surreal<Client>, user_id: &Uuid) -> Result<User, DBerror> {
+pub async fn get_user<C: Connection>(db: &Surreal<C>, user_id: &Uuid) -> Result<User, DBerror> {
     let user: Option<User> = db.select(("users", user_id.to_raw())).await?;
     user.ok_or(DBerror::UserNotFound(user_id.clone()))
 }
 
 ///get task from Uuid
-pub async fn get_task(db: &Surreal<Client>, task_id: &Uuid) -> Result<Task, DBerror> {
+pub async fn get_task<C: Connection>(db: &Surreal<C>, task_id: &Uuid) -> Result<Task, DBerror> {
     let task: Option<Task> = db.select(("tasks", task_id.to_raw())).await?;
     task.ok_or(DBerror::TaskNotFound(task_id.clone()))
 }
 
 ///get event from Uuid
-pub async fn get_event(db: &Surreal<Client>, event_id: &Uuid) -> Result<Event, DBerror> {
+pub async fn get_event<C: Connection>(db: &Surreal<C>, event_id: &Uuid) -> Result<Event, DBerror> {
     let event: Option<Event> = db.select(("events", event_id.to_raw())).await?;
     event.ok_or(DBerror::EventNotFound(event_id.clone()))
 }
 
 ///get category from Uuid
-pub async fn get_category(db: &Surreal<Client>, category_id: &Uuid) -> Result<Category, DBerror> {
+pub async fn get_category<C: Connection>(db: &Surreal<C>, category_id: &Uuid) -> Result<Category, DBerror> {
     let category: Option<Category> = db.select(("categories", category_id.to_raw())).await?;
     category.ok_or(DBerror::CategoryNotFound(category_id.clone()))
 }
 
 ///retrieve tasks for a given user
-pub async fn get_tasks(db: &Surreal<Client>, userid: &Uuid) -> Result<Vec<Task>, DBerror> {
-    let tasks: Vec<Task> = db.select("tasks").await?;
-    let tasks_filtered: Vec<Task> = tasks.into_iter().filter(|x| x.user == userid.clone()).collect();
-    Ok(tasks_filtered)
+pub async fn get_tasks<C: Connection>(db: &Surreal<C>, userid: &Uuid) -> Result<Vec<Task>, DBerror> {
+    let mut response = db.query("SELECT * FROM tasks WHERE user = $user")
+        .bind(("user", userid))
+        .await?;
+    let tasks: Vec<Task> = response.take(0)?;
+    Ok(tasks)
 }
 
 ///retrieve events for a given user
-pub async fn get_events(db: &Surreal<Client>, userid: &Uuid) -> Result<Vec<Event>, DBerror> {
-    let events: Vec<Event> = db.select("events").await?;
-    let events_filtered: Vec<Event> = events.into_iter().filter(|x| x.user == userid.clone()).collect();
-    Ok(events_filtered)
+pub async fn get_events<C: Connection>(db: &Surreal<C>, userid: &Uuid) -> Result<Vec<Event>, DBerror> {
+    let mut response = db.query("SELECT * FROM events WHERE user = $user")
+        .bind(("user", userid))
+        .await?;
+    let events: Vec<Event> = response.take(0)?;
+    Ok(events)
+}
+
+/// A task or event as written to the database, along with the end of its last occurrence.
+///
+/// `recurs_until` is only stored for the range queries to leave out recurring items that are over,
+/// and is left out when the item does not recur or repeats forever.
+#[derive(Serialize)]
+struct StoredItem<'a, T> {
+    #[serde(flatten)]
+    item: &'a T,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    recurs_until: Option<DateTime::<Utc>>,
+}
+
+impl<'a> StoredItem<'a, Task> {
+    fn task(task: &'a Task) -> Self {
+        StoredItem { item: task, recurs_until: task.recurrence.as_ref().and_then(|x| x.last_end(&task.timespan)) }
+    }
+}
+
+impl<'a> StoredItem<'a, Event> {
+    fn event(event: &'a Event) -> Self {
+        StoredItem { item: event, recurs_until: event.recurrence.as_ref().and_then(|x| x.last_end(&event.timespan)) }
+    }
+}
+
+/// SurrealQL condition matching records that may take place during `$start..$end`: those whose
+/// `timespan` overlaps it, and recurring ones starting before its end whose last occurrence does not
+/// end before its start.
+///
+/// Only the parameters are cast, so that the `timespan` indexes can be used. Zero-length timespans
+/// lying exactly on `$start` count as overlapping. Recurring records without `recurs_until` repeat
+/// forever, or were stored before it was. They still have to be expanded with `occurrences` to find
+/// out whether they actually occur in the range.
+const OVERLAPS_RANGE: &str = "(timespan.start < <datetime> $end) \
+    AND ((timespan.end > <datetime> $start) OR (timespan.start >= <datetime> $start) \
+    OR (recurrence != NONE AND (recurs_until = NONE OR recurs_until >= <datetime> $start)))";
+
+///retrieve tasks for a given user that overlap the given range or may recur into it, ordered by start
+pub async fn get_tasks_in_range<C: Connection>(db: &Surreal<C>, userid: &Uuid, range: &Timespan) -> Result<Vec<Task>, DBerror> {
+    let mut response = db.query(format!("SELECT * FROM tasks WHERE user = $user AND {OVERLAPS_RANGE} ORDER BY timespan.start"))
+        .bind(("user", userid))
+        .bind(("start", range.start))
+        .bind(("end", range.end))
+        .await?;
+    let tasks: Vec<Task> = response.take(0)?;
+    Ok(tasks)
+}
+
+///retrieve events for a given user that overlap the given range or may recur into it, ordered by start
+pub async fn get_events_in_range<C: Connection>(db: &Surreal<C>, userid: &Uuid, range: &Timespan) -> Result<Vec<Event>, DBerror> {
+    let mut response = db.query(format!("SELECT * FROM events WHERE user = $user AND {OVERLAPS_RANGE} ORDER BY timespan.start"))
+        .bind(("user", userid))
+        .bind(("start", range.start))
+        .bind(("end", range.end))
+        .await?;
+    let events: Vec<Event> = response.take(0)?;
+    Ok(events)
+}
+
+///retrieve the tasks of a given user that are not completed yet, ordered by due time
+pub async fn get_open_tasks<C: Connection>(db: &Surreal<C>, userid: &Uuid) -> Result<Vec<Task>, DBerror> {
+    let mut response = db.query("SELECT * FROM tasks WHERE user = $user AND completed = false ORDER BY timespan.end")
+        .bind(("user", userid))
+        .await?;
+    let tasks: Vec<Task> = response.take(0)?;
+    Ok(tasks)
+}
+
+///retrieve every user's open tasks with reminders that are due after `now` or recur
+///
+///all-day tasks are kept until a day after their dates end in UTC, which they may end later than elsewhere
+pub async fn get_tasks_with_reminders<C: Connection>(db: &Surreal<C>, now: &DateTime::<Utc>) -> Result<Vec<Task>, DBerror> {
+    let mut response = db.query("SELECT * FROM tasks WHERE completed = false AND array::len(reminders) > 0 \
+        AND ((<datetime> timespan.end > <datetime> $now) OR (timespan.all_day = true AND <datetime> timespan.end > <datetime> $day_before) OR (recurrence != NONE))")
+        .bind(("now", now))
+        .bind(("day_before", *now - Duration::days(1)))
+        .await?;
+    let tasks: Vec<Task> = response.take(0)?;
+    Ok(tasks)
+}
+
+///retrieve every user's events with reminders that start after `now` or recur
+///
+///all-day events are kept until a day after their dates start in UTC, which they may start later than elsewhere
+pub async fn get_events_with_reminders<C: Connection>(db: &Surreal<C>, now: &DateTime::<Utc>) -> Result<Vec<Event>, DBerror> {
+    let mut response = db.query("SELECT * FROM events WHERE array::len(reminders) > 0 \
+        AND ((<datetime> timespan.start > <datetime> $now) OR (timespan.all_day = true AND <datetime> timespan.start > <datetime> $day_before) OR (recurrence != NONE))")
+        .bind(("now", now))
+        .bind(("day_before", *now - Duration::days(1)))
+        .await?;
+    let events: Vec<Event> = response.take(0)?;
+    Ok(events)
 }
 
 ///retrieve categories for a given user
-pub async fn get_categories(db: &Surreal<Client>, userid: &Uuid) -> Result<Vec<Uuid>, DBerror> {
+pub async fn get_categories<C: Connection>(db: &Surreal<C>, userid: &Uuid) -> Result<Vec<Uuid>, DBerror> {
     let user = get_user(db, userid).await?;
     Ok(user.categories)
 }
 
 ///retrieve user id from username
-pub async fn user_id_from_name(db: &Surreal<Client>, name: &str) -> Result<Uuid, DBerror> {
-    let users: Vec<User> = db.select("users").await?;
-    let users_filtered: Vec<User> = users.into_iter().filter(|x| x.name == *name).collect();
-    match users_filtered.get(0) {
+pub async fn user_id_from_name<C: Connection>(db: &Surreal<C>, name: &str) -> Result<Uuid, DBerror> {
+    let mut response = db.query("SELECT * FROM users WHERE name = $name")
+        .bind(("name", name))
+        .await?;
+    let users_filtered: Vec<User> = response.take(0)?;
+    match users_filtered.first() {
         Some(x) => Ok(x.uuid.clone()),
         None => Err(DBerror::UserNameNotFound(name.to_string())),
     }
 }
 
+///retrieve user id from the token in a calendar feed url
+pub async fn user_id_from_feed_token<C: Connection>(db: &Surreal<C>, token: &str) -> Result<Uuid, DBerror> {
+    let mut response = db.query("SELECT * FROM users WHERE feed_token = $feed_token")
+        .bind(("feed_token", token))
+        .await?;
+    let users_filtered: Vec<User> = response.take(0)?;
+    match users_filtered.first() {
+        // an empty token would match users created before feeds had one
+        Some(x) if !token.is_empty() => Ok(x.uuid.clone()),
+        _ => Err(DBerror::FeedNotFound),
+    }
+}
+
 ///change username
-pub async fn change_username(db: &Surreal<Client>, user: &Uuid, new_username: &str) -> Result<User, DBerror> {
+pub async fn change_username<C: Connection>(db: &Surreal<C>, user: &Uuid, new_username: &str) -> Result<User, DBerror> {
     let mut new_user = get_user(db, user).await?;
     new_user.name = new_username.to_string();
     let updated: Option<User> = db.update(("users", user.to_raw())).content(new_user).await?;
     updated.ok_or(DBerror::UserNotFound(user.clone()))
 }
 
-///change password
-pub async fn change_password(db: &Surreal<Client>, user: &Uuid, new_password: &Vec<u8>) -> Result<User, DBerror> {
+///change password, hashing the plaintext password
+pub async fn change_password<C: Connection>(db: &Surreal<C>, user: &Uuid, new_password: &str) -> Result<User, DBerror> {
+    let mut new_user = get_user(db, user).await?;
+    new_user.hashed_password = password::hash_password(new_password)?;
+    let updated: Option<User> = db.update(("users", user.to_raw())).content(new_user).await?;
+    updated.ok_or(DBerror::UserNotFound(user.clone()))
+}
+
+///replace the feed token of a user, so the old calendar feed url stops working
+pub async fn reset_feed_token<C: Connection>(db: &Surreal<C>, user: &Uuid) -> Result<User, DBerror> {
+    let mut new_user = get_user(db, user).await?;
+    new_user.feed_token = password::random_token();
+    let updated: Option<User> = db.update(("users", user.to_raw())).content(new_user).await?;
+    updated.ok_or(DBerror::UserNotFound(user.clone()))
+}
+
+///change the day the user's weeks start on
+pub async fn set_week_start<C: Connection>(db: &Surreal<C>, user: &Uuid, week_start: WeekStart) -> Result<User, DBerror> {
+    let mut new_user = get_user(db, user).await?;
+    new_user.week_start = week_start;
+    let updated: Option<User> = db.update(("users", user.to_raw())).content(new_user).await?;
+    updated.ok_or(DBerror::UserNotFound(user.clone()))
+}
+
+///change the time zone a user's calendar is shown in
+pub async fn set_time_zone<C: Connection>(db: &Surreal<C>, user: &Uuid, time_zone: LocalZone) -> Result<User, DBerror> {
+    let mut new_user = get_user(db, user).await?;
+    new_user.time_zone = time_zone;
+    let updated: Option<User> = db.update(("users", user.to_raw())).content(new_user).await?;
+    updated.ok_or(DBerror::UserNotFound(user.clone()))
+}
+
+///change the address a user's reminders are sent to, `None` to stop sending them
+pub async fn set_email<C: Connection>(db: &Surreal<C>, user: &Uuid, email: Option<String>) -> Result<User, DBerror> {
     let mut new_user = get_user(db, user).await?;
-    new_user.hashed_password = new_password.to_vec();
+    new_user.email = email;
     let updated: Option<User> = db.update(("users", user.to_raw())).content(new_user).await?;
     updated.ok_or(DBerror::UserNotFound(user.clone()))
 }
 
+///check a user's password, rehashing it when it was hashed with outdated parameters
+pub async fn verify_password<C: Connection>(db: &Surreal<C>, user: &Uuid, password: &str) -> Result<bool, DBerror> {
+    let mut stored = get_user(db, user).await?;
+    if !password::verify_password(password, &stored.hashed_password) {
+        return Ok(false);
+    }
+    if password::needs_rehash(&stored.hashed_password) {
+        stored.hashed_password = password::hash_password(password)?;
+        let _: Option<User> = db.update(("users", user.to_raw())).content(stored).await?;
+    }
+    Ok(true)
+}
+
 ///change task name
-pub async fn task_edit_name(db: &Surreal<Client>, task: &Uuid, new_name: &str) -> Result<Task, DBerror> {
+pub async fn task_edit_name<C: Connection>(db: &Surreal<C>, task: &Uuid, new_name: &str) -> Result<Task, DBerror> {
     let mut new_task = get_task(db, task).await?;
     new_task.name = new_name.to_string();
-    let updated: Option<Task> = db.update(("tasks", task.to_raw())).content(new_task).await?;
+    let updated: Option<Task> = db.update(("tasks", task.to_raw())).content(StoredItem::task(&new_task)).await?;
     updated.ok_or(DBerror::TaskNotFound(task.clone()))
 }
 
 ///change task description
-pub async fn task_edit_desc(db: &Surreal<Client>, task: &Uuid, new_desc: &str) -> Result<Task, DBerror> {
+pub async fn task_edit_desc<C: Connection>(db: &Surreal<C>, task: &Uuid, new_desc: &str) -> Result<Task, DBerror> {
     let mut new_task = get_task(db, task).await?;
     new_task.description = new_desc.to_string();
-    let updated: Option<Task> = db.update(("tasks", task.to_raw())).content(new_task).await?;
+    let updated: Option<Task> = db.update(("tasks", task.to_raw())).content(StoredItem::task(&new_task)).await?;
     updated.ok_or(DBerror::TaskNotFound(task.clone()))
 }
 
 ///change task timespan
-pub async fn task_edit_timespan(db: &Surreal<Client>, task: &Uuid, new_timespan: &Timespan) -> Result<Task, DBerror> {
+pub async fn task_edit_timespan<C: Connection>(db: &Surreal<C>, task: &Uuid, new_timespan: &Timespan) -> Result<Task, DBerror> {
+    new_timespan.validate()?;
     let mut new_task = get_task(db, task).await?;
     new_task.timespan = new_timespan.clone();
-    let updated: Option<Task> = db.update(("tasks", task.to_raw())).content(new_task).await?;
+    let updated: Option<Task> = db.update(("tasks", task.to_raw())).content(StoredItem::task(&new_task)).await?;
     updated.ok_or(DBerror::TaskNotFound(task.clone()))
 }
 
 ///change task category
-pub async fn task_change_category(db: &Surreal<Client>, task: &Uuid, new_category: &Uuid) -> Result<Task, DBerror> {
+pub async fn task_change_category<C: Connection>(db: &Surreal<C>, task: &Uuid, new_category: &Uuid) -> Result<Task, DBerror> {
     let mut new_task = get_task(db, task).await?;
     new_task.category = new_category.clone();
-    let updated: Option<Task> = db.update(("tasks", task.to_raw())).content(new_task).await?;
+    let updated: Option<Task> = db.update(("tasks", task.to_raw())).content(StoredItem::task(&new_task)).await?;
     updated.ok_or(DBerror::TaskNotFound(task.clone()))
 }
 
 ///set the 'completed' field of a task
-pub async fn task_set_completion(db: &Surreal<Client>, task: &Uuid, completion: bool) -> Result<Task, DBerror> {
+pub async fn task_set_completion<C: Connection>(db: &Surreal<C>, task: &Uuid, completion: bool) -> Result<Task, DBerror> {
     let mut new_task = get_task(db, task).await?;
     new_task.completed = completion;
-    let updated: Option<Task> = db.update(("tasks", task.to_raw())).content(new_task).await?;
+    let updated: Option<Task> = db.update(("tasks", task.to_raw())).content(StoredItem::task(&new_task)).await?;
+    updated.ok_or(DBerror::TaskNotFound(task.clone()))
+}
+
+///set or clear the recurrence rule of a task
+pub async fn task_set_recurrence<C: Connection>(db: &Surreal<C>, task: &Uuid, recurrence: Option<Recurrence>) -> Result<Task, DBerror> {
+    if let Some(recurrence) = &recurrence {
+        recurrence.validate()?;
+    }
+    let mut new_task = get_task(db, task).await?;
+    new_task.recurrence = recurrence;
+    let updated: Option<Task> = db.update(("tasks", task.to_raw())).content(StoredItem::task(&new_task)).await?;
     updated.ok_or(DBerror::TaskNotFound(task.clone()))
 }
 
+///set the reminders of a task, in minutes before its due time
+pub async fn task_set_reminders<C: Connection>(db: &Surreal<C>, task: &Uuid, reminders: Vec<u32>) -> Result<Task, DBerror> {
+    let mut new_task = get_task(db, task).await?;
+    new_task.reminders = reminders;
+    let updated: Option<Task> = db.update(("tasks", task.to_raw())).content(StoredItem::task(&new_task)).await?;
+    updated.ok_or(DBerror::TaskNotFound(task.clone()))
+}
+
+///creates or replaces a task with all of its fields
+pub async fn put_task<C: Connection>(db: &Surreal<C>, task: &Task) -> Result<Task, DBerror> {
+    task.timespan.validate()?;
+    if let Some(recurrence) = &task.recurrence {
+        recurrence.validate()?;
+    }
+    let stored: Option<Task> = db.update(("tasks", task.uuid.to_raw())).content(StoredItem::task(task)).await?;
+    stored.ok_or(DBerror::TaskNotFound(task.uuid.clone()))
+}
+
 ///change event name
-pub async fn event_edit_name(db: &Surreal<Client>, event: &Uuid, new_name: &str) -> Result<Event, DBerror> {
+pub async fn event_edit_name<C: Connection>(db: &Surreal<C>, event: &Uuid, new_name: &str) -> Result<Event, DBerror> {
     let mut new_event = get_event(db, event).await?;
     new_event.name = new_name.to_string();
-    let updated: Option<Event> = db.update(("events", event.to_raw())).content(new_event).await?;
+    let updated: Option<Event> = db.update(("events", event.to_raw())).content(StoredItem::event(&new_event)).await?;
     updated.ok_or(DBerror::EventNotFound(event.clone()))
 }
 
 ///change event description
-pub async fn event_edit_desc(db: &Surreal<Client>, event: &Uuid, new_desc: &str) -> Result<Event, DBerror> {
+pub async fn event_edit_desc<C: Connection>(db: &Surreal<C>, event: &Uuid, new_desc: &str) -> Result<Event, DBerror> {
     let mut new_event = get_event(db, event).await?;
     new_event.description = new_desc.to_string();
-    let updated: Option<Event> = db.update(("events", event.to_raw())).content(new_event).await?;
+    let updated: Option<Event> = db.update(("events", event.to_raw())).content(StoredItem::event(&new_event)).await?;
     updated.ok_or(DBerror::EventNotFound(event.clone()))    	
 }
 
 ///change event timespan
-pub async fn event_edit_timespan(db: &Surreal<Client>, event: &Uuid, new_timespan: &Timespan) -> Result<Event, DBerror> {
+pub async fn event_edit_timespan<C: Connection>(db: &Surreal<C>, event: &Uuid, new_timespan: &Timespan) -> Result<Event, DBerror> {
+    new_timespan.validate()?;
     let mut new_event = get_event(db, event).await?;
     new_event.timespan = new_timespan.clone();
-    let updated: Option<Event> = db.update(("events", event.to_raw())).content(new_event).await?;
+    let updated: Option<Event> = db.update(("events", event.to_raw())).content(StoredItem::event(&new_event)).await?;
     updated.ok_or(DBerror::EventNotFound(event.clone()))
 }
 
 ///change event category
-pub async fn event_change_category(db: &Surreal<Client>, event: &Uuid, new_category: &Uuid) -> Result<Event, DBerror> {
+pub async fn event_change_category<C: Connection>(db: &Surreal<C>, event: &Uuid, new_category: &Uuid) -> Result<Event, DBerror> {
     let mut new_event = get_event(db, event).await?;
     new_event.category = new_category.clone();
-    let updated: Option<Event> = db.update(("events", event.to_raw())).await?;
+    let updated: Option<Event> = db.update(("events", event.to_raw())).content(StoredItem::event(&new_event)).await?;
+    updated.ok_or(DBerror::EventNotFound(event.clone()))
+}
+
+///set or clear the recurrence rule of an event
+pub async fn event_set_recurrence<C: Connection>(db: &Surreal<C>, event: &Uuid, recurrence: Option<Recurrence>) -> Result<Event, DBerror> {
+    if let Some(recurrence) = &recurrence {
+        recurrence.validate()?;
+    }
+    let mut new_event = get_event(db, event).await?;
+    new_event.recurrence = recurrence;
+    let updated: Option<Event> = db.update(("events", event.to_raw())).content(StoredItem::event(&new_event)).await?;
     updated.ok_or(DBerror::EventNotFound(event.clone()))
 }
 
+///set the reminders of an event, in minutes before its start
+pub async fn event_set_reminders<C: Connection>(db: &Surreal<C>, event: &Uuid, reminders: Vec<u32>) -> Result<Event, DBerror> {
+    let mut new_event = get_event(db, event).await?;
+    new_event.reminders = reminders;
+    let updated: Option<Event> = db.update(("events", event.to_raw())).content(StoredItem::event(&new_event)).await?;
+    updated.ok_or(DBerror::EventNotFound(event.clone()))
+}
+
+///creates or replaces an event with all of its fields
+pub async fn put_event<C: Connection>(db: &Surreal<C>, event: &Event) -> Result<Event, DBerror> {
+    event.timespan.validate()?;
+    if let Some(recurrence) = &event.recurrence {
+        recurrence.validate()?;
+    }
+    let stored: Option<Event> = db.update(("events", event.uuid.to_raw())).content(StoredItem::event(event)).await?;
+    stored.ok_or(DBerror::EventNotFound(event.uuid.clone()))
+}
+
 ///change category name
-pub async fn category_edit_name(db: &Surreal<Client>, category: &Uuid, new_name: &str) -> Result<Category, DBerror> {
+pub async fn category_edit_name<C: Connection>(db: &Surreal<C>, category: &Uuid, new_name: &str) -> Result<Category, DBerror> {
     let mut new_category = get_category(db, category).await?;
     new_category.name = new_name.to_string();
-    let updated: Option<Category> = db.update(("categories", category.to_raw())).await?;
+    let updated: Option<Category> = db.update(("categories", category.to_raw())).content(new_category).await?;
     updated.ok_or(DBerror::CategoryNotFound(category.clone()))
 }
 
 ///change category color
-pub async fn category_change_color(db: &Surreal<Client>, category: &Uuid, new_color: u32) -> Result<Category, DBerror> {
+pub async fn category_change_color<C: Connection>(db: &Surreal<C>, category: &Uuid, new_color: u32) -> Result<Category, DBerror> {
     let mut new_category = get_category(db, category).await?;
     new_category.color = new_color;
-    let updated: Option<Category> = db.update(("categories", category.to_raw())).await?;
+    let updated: Option<Category> = db.update(("categories", category.to_raw())).content(new_category).await?;
     updated.ok_or(DBerror::CategoryNotFound(category.clone()))
 }
 
-///deletes a user
-pub async fn delete_user(db: &Surreal<Client>, user: &Uuid) -> Result<User, DBerror> {
-    let deleted: Option<User> = db.delete(("users", user.to_raw())).await?;
-    deleted.ok_or(DBerror::UserNotFound(user.clone()))
+///hide or show the items of a category in the calendar views
+pub async fn category_set_hidden<C: Connection>(db: &Surreal<C>, category: &Uuid, hidden: bool) -> Result<Category, DBerror> {
+    let mut new_category = get_category(db, category).await?;
+    new_category.hidden = hidden;
+    let updated: Option<Category> = db.update(("categories", category.to_raw())).content(new_category).await?;
+    updated.ok_or(DBerror::CategoryNotFound(category.clone()))
+}
+
+///deletes a user with everything they own, their tasks, events, categories, sessions and sent reminders, in one transaction
+pub async fn delete_user<C: Connection>(db: &Surreal<C>, user: &Uuid) -> Result<User, DBerror> {
+    let deleted = get_user(db, user).await?;
+    db.query("BEGIN TRANSACTION; \
+        DELETE tasks WHERE user = $user; \
+        DELETE events WHERE user = $user; \
+        DELETE categories WHERE user = $user; \
+        DELETE sessions WHERE user = $user; \
+        DELETE reminder_deliveries WHERE user = $user; \
+        DELETE type::thing('users', $id); \
+        COMMIT TRANSACTION;")
+        .bind(("user", user))
+        .bind(("id", user.to_raw()))
+        .await?
+        .check()?;
+    Ok(deleted)
 }
 
 ///deletes a task
-pub async fn delete_task(db: &Surreal<Client>, task: &Uuid) -> Result<Task, DBerror> {
+pub async fn delete_task<C: Connection>(db: &Surreal<C>, task: &Uuid) -> Result<Task, DBerror> {
     let deleted: Option<Task> = db.delete(("tasks", task.to_raw())).await?;
     deleted.ok_or(DBerror::TaskNotFound(task.clone()))
 }
 
 ///deletes an event
-pub async fn delete_event(db: &Surreal<Client>, event: &Uuid) -> Result<Event, DBerror> {
+pub async fn delete_event<C: Connection>(db: &Surreal<C>, event: &Uuid) -> Result<Event, DBerror> {
     let deleted: Option<Event> = db.delete(("events", event.to_raw())).await?;
     deleted.ok_or(DBerror::EventNotFound(event.clone()))
 }
 
-///deletes a category
-pub async fn delete_category(db: Surreal<Client>, category: &Uuid) -> Result<Category, DBerror> {
-    let deleted: Option<Category> = db.delete(("categories", category.to_raw())).await?;
-    deleted.ok_or(DBerror::CategoryNotFound(category.clone()))
+///deletes a category in one transaction, handing its tasks and events to `replacement`, another
+///category of the same user, and removing it from the user's categories
+pub async fn delete_category<C: Connection>(db: &Surreal<C>, category: &Uuid, replacement: &Uuid) -> Result<Category, DBerror> {
+    let deleted = get_category(db, category).await?;
+    if replacement == category || get_category(db, replacement).await?.user != deleted.user {
+        return Err(DBerror::InvalidReplacement(replacement.clone()));
+    }
+    db.query("BEGIN TRANSACTION; \
+        UPDATE tasks SET category = $replacement WHERE category = $category; \
+        UPDATE events SET category = $replacement WHERE category = $category; \
+        UPDATE type::thing('users', $user) SET categories -= $category; \
+        DELETE type::thing('categories', $id); \
+        COMMIT TRANSACTION;")
+        .bind(("category", category))
+        .bind(("replacement", replacement))
+        .bind(("user", deleted.user.to_raw()))
+        .bind(("id", category.to_raw()))
+        .await?
+        .check()?;
+    Ok(deleted)
+}
+
+///get the user's "Uncategorised" category, adding it when there is none
+pub async fn uncategorised_category<C: Connection>(db: &Surreal<C>, user: &Uuid) -> Result<Category, DBerror> {
+    let mut response = db.query("SELECT * FROM categories WHERE user = $user AND name = $name")
+        .bind(("user", user))
+        .bind(("name", UNCATEGORISED))
+        .await?;
+    let found: Vec<Category> = response.take(0)?;
+    match found.into_iter().next() {
+        Some(x) => Ok(x),
+        None => add_category(db, UNCATEGORISED, UNCATEGORISED_COLOR, user).await,
+    }
+}
+
+///stores a new session, keyed by the hash of its token
+pub async fn add_session<C: Connection>(db: &Surreal<C>, session: &Session) -> Result<Session, DBerror> {
+    let created: Session = db.create(("sessions", session.token_hash.as_str())).content(session).await?;
+    Ok(created)
+}
+
+///get session from the hash of its token
+pub async fn get_session<C: Connection>(db: &Surreal<C>, token_hash: &str) -> Result<Session, DBerror> {
+    let session: Option<Session> = db.select(("sessions", token_hash)).await?;
+    session.ok_or(DBerror::SessionNotFound)
+}
+
+///deletes a session, logging its browser out
+pub async fn delete_session<C: Connection>(db: &Surreal<C>, token_hash: &str) -> Result<Session, DBerror> {
+    let deleted: Option<Session> = db.delete(("sessions", token_hash)).await?;
+    deleted.ok_or(DBerror::SessionNotFound)
+}
+
+///deletes every session that expired before the given time, returning how many there were
+pub async fn delete_expired_sessions<C: Connection>(db: &Surreal<C>, now: &DateTime::<Utc>) -> Result<usize, DBerror> {
+    let mut response = db.query("DELETE sessions WHERE expires_at <= <datetime> $now RETURN BEFORE")
+        .bind(("now", now))
+        .await?;
+    let deleted: Vec<Session> = response.take(0)?;
+    Ok(deleted.len())
+}
+
+///records that a reminder is being sent, returning false when it was sent already or another
+///sender claimed it after `stale_before`, in which case the caller must not send it
+pub async fn claim_reminder<C: Connection>(db: &Surreal<C>, delivery: &ReminderDelivery, stale_before: &DateTime::<Utc>) -> Result<bool, DBerror> {
+    let created: Result<ReminderDelivery, surrealdb::Error> = db.create(("reminder_deliveries", delivery.key.as_str())).content(delivery).await;
+    let error = match created {
+        Ok(_) => return Ok(true),
+        Err(x) => x,
+    };
+    // creating fails when the record exists, then only an abandoned claim may be taken over
+    let existing: Option<ReminderDelivery> = db.select(("reminder_deliveries", delivery.key.as_str())).await?;
+    if existing.is_none() {
+        return Err(error.into());
+    }
+    let mut response = db.query("UPDATE type::thing('reminder_deliveries', $key) SET claimed_at = <datetime> $claimed_at \
+        WHERE sent_at = NONE AND <datetime> claimed_at < <datetime> $stale_before")
+        .bind(("key", delivery.key.as_str()))
+        .bind(("claimed_at", delivery.claimed_at))
+        .bind(("stale_before", stale_before))
+        .await?;
+    let taken_over: Vec<ReminderDelivery> = response.take(0)?;
+    Ok(!taken_over.is_empty())
+}
+
+///marks a claimed reminder as sent, so it is never sent again
+pub async fn finish_reminder<C: Connection>(db: &Surreal<C>, key: &str, sent_at: &DateTime::<Utc>) -> Result<ReminderDelivery, DBerror> {
+    let existing: Option<ReminderDelivery> = db.select(("reminder_deliveries", key)).await?;
+    let mut delivery = existing.ok_or(DBerror::ReminderNotFound(key.to_string()))?;
+    delivery.sent_at = Some(*sent_at);
+    let updated: Option<ReminderDelivery> = db.update(("reminder_deliveries", key)).content(delivery).await?;
+    updated.ok_or(DBerror::ReminderNotFound(key.to_string()))
+}
+
+///gives up the claim on a reminder that could not be sent, so it is tried again
+pub async fn release_reminder<C: Connection>(db: &Surreal<C>, key: &str) -> Result<ReminderDelivery, DBerror> {
+    let deleted: Option<ReminderDelivery> = db.delete(("reminder_deliveries", key)).await?;
+    deleted.ok_or(DBerror::ReminderNotFound(key.to_string()))
+}
+
+///retrieve the reminders sent or being sent to a given user, ordered by the time of their occurrence
+pub async fn get_reminder_deliveries<C: Connection>(db: &Surreal<C>, user: &Uuid) -> Result<Vec<ReminderDelivery>, DBerror> {
+    let mut response = db.query("SELECT * FROM reminder_deliveries WHERE user = $user ORDER BY occurs_at")
+        .bind(("user", user))
+        .await?;
+    let deliveries: Vec<ReminderDelivery> = response.take(0)?;
+    Ok(deliveries)
+}
+
+///retrieve every background job, ordered by name
+pub async fn get_jobs<C: Connection>(db: &Surreal<C>) -> Result<Vec<Job>, DBerror> {
+    let mut response = db.query("SELECT * FROM jobs ORDER BY name").await?;
+    let jobs: Vec<Job> = response.take(0)?;
+    Ok(jobs)
+}
+
+///get background job from its name
+pub async fn get_job<C: Connection>(db: &Surreal<C>, name: &str) -> Result<Job, DBerror> {
+    let job: Option<Job> = db.select(("jobs", name)).await?;
+    job.ok_or(DBerror::JobNotFound(name.to_string()))
+}
+
+///stores a job unless one with its name exists already, returning the stored one
+pub async fn ensure_job<C: Connection>(db: &Surreal<C>, job: &Job) -> Result<Job, DBerror> {
+    let existing: Option<Job> = db.select(("jobs", job.name.as_str())).await?;
+    if let Some(x) = existing {
+        return Ok(x);
+    }
+    let created: Result<Job, surrealdb::Error> = db.create(("jobs", job.name.as_str())).content(job).await;
+    match created {
+        Ok(x) => Ok(x),
+        // another runner added it in the meantime
+        Err(error) => {
+            let existing: Option<Job> = db.select(("jobs", job.name.as_str())).await?;
+            existing.ok_or(error.into())
+        }
+    }
+}
+
+///locks a job that is due at `now` and not locked by a live runner, until `locked_until`
+///
+///returns `None` when the job is not due yet or another runner holds it
+pub async fn lock_job<C: Connection>(db: &Surreal<C>, name: &str, runner: &str, now: &DateTime::<Utc>, locked_until: &DateTime::<Utc>) -> Result<Option<Job>, DBerror> {
+    get_job(db, name).await?;
+    let mut response = db.query("UPDATE type::thing('jobs', $name) \
+        SET locked_by = $runner, locked_until = <datetime> $locked_until, last_started_at = <datetime> $now \
+        WHERE <datetime> next_run <= <datetime> $now AND (locked_until = NONE OR <datetime> locked_until <= <datetime> $now)")
+        .bind(("name", name))
+        .bind(("runner", runner))
+        .bind(("now", now))
+        .bind(("locked_until", locked_until))
+        .await?;
+    let locked: Vec<Job> = response.take(0)?;
+    Ok(locked.into_iter().next())
+}
+
+///stores the outcome of a run and releases the lock held by `runner`
+///
+///returns false without storing anything when the lock expired and another runner took it over
+pub async fn finish_job<C: Connection>(db: &Surreal<C>, job: &Job, runner: &str) -> Result<bool, DBerror> {
+    let mut response = db.query("UPDATE type::thing('jobs', $name) CONTENT $job WHERE locked_by = $runner")
+        .bind(("name", job.name.as_str()))
+        .bind(("job", job))
+        .bind(("runner", runner))
+        .await?;
+    let updated: Vec<Job> = response.take(0)?;
+    Ok(!updated.is_empty())
 }
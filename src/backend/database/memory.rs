@@ -0,0 +1,521 @@
+use std::collections::BTreeMap;
+use std::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard, PoisonError};
+
+use async_trait::async_trait;
+use chrono::{Duration, Utc, DateTime};
+use surrealdb::sql::Uuid;
+
+use crate::backend::database::db_error::DBerror;
+use crate::backend::database::store::Store;
+use crate::backend::password;
+use crate::common::model::{User, Timespan, Task, Event, Category, Session, WeekStart, LocalZone, ReminderDelivery, Job, UNCATEGORISED, UNCATEGORISED_COLOR};
+use crate::common::recurrence::Recurrence;
+
+///whether an item is returned by the range queries, as `OVERLAPS_RANGE` decides in the Surreal ones:
+///it overlaps the range, or recurs, starts before its end and has not ended its last occurrence before
+///its start, whether an occurrence falls in it or not
+fn is_candidate(timespan: &Timespan, recurrence: Option<&Recurrence>, range: &Timespan) -> bool {
+    let recurs_into = recurrence.is_some_and(|x| x.last_end(timespan).is_none_or(|end| end >= range.start));
+    timespan.start < range.end && (timespan.end > range.start || timespan.start >= range.start || recurs_into)
+}
+
+#[derive(Debug, Default)]
+struct Tables {
+    users: BTreeMap<Uuid, User>,
+    tasks: BTreeMap<Uuid, Task>,
+    events: BTreeMap<Uuid, Event>,
+    categories: BTreeMap<Uuid, Category>,
+    sessions: BTreeMap<String, Session>,
+    reminder_deliveries: BTreeMap<String, ReminderDelivery>,
+    jobs: BTreeMap<String, Job>,
+}
+
+/// In-process `Store` that keeps every record in memory, for tests and demos.
+///
+/// Nothing is persisted: all data is lost when the store is dropped.
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    tables: RwLock<Tables>,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn read(&self) -> RwLockReadGuard<'_, Tables> {
+        self.tables.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn write(&self) -> RwLockWriteGuard<'_, Tables> {
+        self.tables.write().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn update_user(&self, user: &Uuid, f: impl FnOnce(&mut User)) -> Result<User, DBerror> {
+        let mut tables = self.write();
+        let entry = tables.users.get_mut(user).ok_or(DBerror::UserNotFound(user.clone()))?;
+        f(entry);
+        Ok(entry.clone())
+    }
+
+    fn update_task(&self, task: &Uuid, f: impl FnOnce(&mut Task)) -> Result<Task, DBerror> {
+        let mut tables = self.write();
+        let entry = tables.tasks.get_mut(task).ok_or(DBerror::TaskNotFound(task.clone()))?;
+        f(entry);
+        Ok(entry.clone())
+    }
+
+    fn update_event(&self, event: &Uuid, f: impl FnOnce(&mut Event)) -> Result<Event, DBerror> {
+        let mut tables = self.write();
+        let entry = tables.events.get_mut(event).ok_or(DBerror::EventNotFound(event.clone()))?;
+        f(entry);
+        Ok(entry.clone())
+    }
+
+    fn update_category(&self, category: &Uuid, f: impl FnOnce(&mut Category)) -> Result<Category, DBerror> {
+        let mut tables = self.write();
+        let entry = tables.categories.get_mut(category).ok_or(DBerror::CategoryNotFound(category.clone()))?;
+        f(entry);
+        Ok(entry.clone())
+    }
+}
+
+#[async_trait]
+impl Store for MemoryStore {
+    async fn add_user(&self, username: &str, password: &str) -> Result<User, DBerror> {
+        // hash before taking the lock, it is deliberately slow
+        let hashed_password = password::hash_password(password)?;
+        let mut tables = self.write();
+        if tables.users.values().any(|x| x.name == username) {
+            return Err(DBerror::UserAlreadyExists(username.to_string()));
+        }
+        let id = Uuid::new();
+
+        let new_user = User {
+            name: username.to_string(),
+            hashed_password,
+            joined_at: Utc::now(),
+            categories: Vec::new(),
+            feed_token: password::random_token(),
+            week_start: WeekStart::default(),
+            email: None,
+            time_zone: LocalZone::default(),
+            uuid: id.clone(),
+        };
+        tables.users.insert(id, new_user.clone());
+
+        Ok(new_user)
+    }
+
+    async fn add_task(&self, name: &str, description: &str, timespan: &Timespan, category: &Uuid, recurrence: Option<Recurrence>, user: &Uuid) -> Result<Task, DBerror> {
+        timespan.validate()?;
+        if let Some(recurrence) = &recurrence {
+            recurrence.validate()?;
+        }
+        let id = Uuid::new();
+
+        let new_task = Task {
+            name: name.to_string(),
+            description: description.to_string(),
+            timespan: timespan.clone(),
+            category: category.clone(),
+            completed: false,
+            recurrence,
+            ical_uid: None,
+            reminders: Vec::new(),
+            user: user.clone(),
+            uuid: id.clone(),
+        };
+        self.write().tasks.insert(id, new_task.clone());
+
+        Ok(new_task)
+    }
+
+    async fn add_event(&self, name: &str, description: &str, timespan: &Timespan, category: &Uuid, recurrence: Option<Recurrence>, user: &Uuid) -> Result<Event, DBerror> {
+        timespan.validate()?;
+        if let Some(recurrence) = &recurrence {
+            recurrence.validate()?;
+        }
+        let id = Uuid::new();
+
+        let new_event = Event {
+            name: name.to_string(),
+            description: description.to_string(),
+            timespan: timespan.clone(),
+            category: category.clone(),
+            recurrence,
+            ical_uid: None,
+            reminders: Vec::new(),
+            user: user.clone(),
+            uuid: id.clone(),
+        };
+        self.write().events.insert(id, new_event.clone());
+
+        Ok(new_event)
+    }
+
+    async fn add_category(&self, name: &str, color: u32, user: &Uuid) -> Result<Category, DBerror> {
+        let mut tables = self.write();
+        let id = Uuid::new();
+
+        let new_category = Category {
+            name: name.to_string(),
+            color,
+            hidden: false,
+            user: user.clone(),
+            uuid: id.clone(),
+        };
+
+        let owner = tables.users.get_mut(user).ok_or(DBerror::UserNotFound(user.clone()))?;
+        owner.categories.push(id.clone());
+        tables.categories.insert(id, new_category.clone());
+
+        Ok(new_category)
+    }
+
+    async fn get_user(&self, user_id: &Uuid) -> Result<User, DBerror> {
+        self.read().users.get(user_id).cloned().ok_or(DBerror::UserNotFound(user_id.clone()))
+    }
+
+    async fn get_task(&self, task_id: &Uuid) -> Result<Task, DBerror> {
+        self.read().tasks.get(task_id).cloned().ok_or(DBerror::TaskNotFound(task_id.clone()))
+    }
+
+    async fn get_event(&self, event_id: &Uuid) -> Result<Event, DBerror> {
+        self.read().events.get(event_id).cloned().ok_or(DBerror::EventNotFound(event_id.clone()))
+    }
+
+    async fn get_category(&self, category_id: &Uuid) -> Result<Category, DBerror> {
+        self.read().categories.get(category_id).cloned().ok_or(DBerror::CategoryNotFound(category_id.clone()))
+    }
+
+    async fn get_tasks(&self, userid: &Uuid) -> Result<Vec<Task>, DBerror> {
+        Ok(self.read().tasks.values().filter(|x| x.user == *userid).cloned().collect())
+    }
+
+    async fn get_events(&self, userid: &Uuid) -> Result<Vec<Event>, DBerror> {
+        Ok(self.read().events.values().filter(|x| x.user == *userid).cloned().collect())
+    }
+
+    async fn get_tasks_in_range(&self, userid: &Uuid, range: &Timespan) -> Result<Vec<Task>, DBerror> {
+        let mut tasks: Vec<Task> = self.read().tasks.values()
+            .filter(|x| x.user == *userid && is_candidate(&x.timespan, x.recurrence.as_ref(), range))
+            .cloned()
+            .collect();
+        tasks.sort_by_key(|x| x.timespan.start);
+        Ok(tasks)
+    }
+
+    async fn get_events_in_range(&self, userid: &Uuid, range: &Timespan) -> Result<Vec<Event>, DBerror> {
+        let mut events: Vec<Event> = self.read().events.values()
+            .filter(|x| x.user == *userid && is_candidate(&x.timespan, x.recurrence.as_ref(), range))
+            .cloned()
+            .collect();
+        events.sort_by_key(|x| x.timespan.start);
+        Ok(events)
+    }
+
+    async fn get_open_tasks(&self, userid: &Uuid) -> Result<Vec<Task>, DBerror> {
+        let mut tasks: Vec<Task> = self.read().tasks.values()
+            .filter(|x| x.user == *userid && !x.completed)
+            .cloned()
+            .collect();
+        tasks.sort_by_key(|x| x.timespan.end);
+        Ok(tasks)
+    }
+
+    async fn get_tasks_with_reminders(&self, now: &DateTime::<Utc>) -> Result<Vec<Task>, DBerror> {
+        Ok(self.read().tasks.values()
+            .filter(|x| !x.completed && !x.reminders.is_empty() && (x.timespan.end > *now || x.recurrence.is_some()
+                || (x.timespan.all_day && x.timespan.end > *now - Duration::days(1))))
+            .cloned()
+            .collect())
+    }
+
+    async fn get_events_with_reminders(&self, now: &DateTime::<Utc>) -> Result<Vec<Event>, DBerror> {
+        Ok(self.read().events.values()
+            .filter(|x| !x.reminders.is_empty() && (x.timespan.start > *now || x.recurrence.is_some()
+                || (x.timespan.all_day && x.timespan.start > *now - Duration::days(1))))
+            .cloned()
+            .collect())
+    }
+
+    async fn get_categories(&self, userid: &Uuid) -> Result<Vec<Uuid>, DBerror> {
+        Ok(self.get_user(userid).await?.categories)
+    }
+
+    async fn user_id_from_name(&self, name: &str) -> Result<Uuid, DBerror> {
+        match self.read().users.values().find(|x| x.name == name) {
+            Some(x) => Ok(x.uuid.clone()),
+            None => Err(DBerror::UserNameNotFound(name.to_string())),
+        }
+    }
+
+    async fn user_id_from_feed_token(&self, token: &str) -> Result<Uuid, DBerror> {
+        self.read().users.values()
+            .find(|x| !token.is_empty() && x.feed_token == token)
+            .map(|x| x.uuid.clone())
+            .ok_or(DBerror::FeedNotFound)
+    }
+
+    async fn change_username(&self, user: &Uuid, new_username: &str) -> Result<User, DBerror> {
+        self.update_user(user, |x| x.name = new_username.to_string())
+    }
+
+    async fn change_password(&self, user: &Uuid, new_password: &str) -> Result<User, DBerror> {
+        let hashed = password::hash_password(new_password)?;
+        self.update_user(user, |x| x.hashed_password = hashed)
+    }
+
+    async fn verify_password(&self, user: &Uuid, password: &str) -> Result<bool, DBerror> {
+        let stored = self.get_user(user).await?.hashed_password;
+        if !password::verify_password(password, &stored) {
+            return Ok(false);
+        }
+        if password::needs_rehash(&stored) {
+            let hashed = password::hash_password(password)?;
+            self.update_user(user, |x| x.hashed_password = hashed)?;
+        }
+        Ok(true)
+    }
+
+    async fn reset_feed_token(&self, user: &Uuid) -> Result<User, DBerror> {
+        self.update_user(user, |x| x.feed_token = password::random_token())
+    }
+
+    async fn set_week_start(&self, user: &Uuid, week_start: WeekStart) -> Result<User, DBerror> {
+        self.update_user(user, |x| x.week_start = week_start)
+    }
+
+    async fn set_time_zone(&self, user: &Uuid, time_zone: LocalZone) -> Result<User, DBerror> {
+        self.update_user(user, |x| x.time_zone = time_zone)
+    }
+
+    async fn set_email(&self, user: &Uuid, email: Option<String>) -> Result<User, DBerror> {
+        self.update_user(user, |x| x.email = email)
+    }
+
+    async fn task_edit_name(&self, task: &Uuid, new_name: &str) -> Result<Task, DBerror> {
+        self.update_task(task, |x| x.name = new_name.to_string())
+    }
+
+    async fn task_edit_desc(&self, task: &Uuid, new_desc: &str) -> Result<Task, DBerror> {
+        self.update_task(task, |x| x.description = new_desc.to_string())
+    }
+
+    async fn task_edit_timespan(&self, task: &Uuid, new_timespan: &Timespan) -> Result<Task, DBerror> {
+        new_timespan.validate()?;
+        self.update_task(task, |x| x.timespan = new_timespan.clone())
+    }
+
+    async fn task_change_category(&self, task: &Uuid, new_category: &Uuid) -> Result<Task, DBerror> {
+        self.update_task(task, |x| x.category = new_category.clone())
+    }
+
+    async fn task_set_completion(&self, task: &Uuid, completion: bool) -> Result<Task, DBerror> {
+        self.update_task(task, |x| x.completed = completion)
+    }
+
+    async fn task_set_recurrence(&self, task: &Uuid, recurrence: Option<Recurrence>) -> Result<Task, DBerror> {
+        if let Some(recurrence) = &recurrence {
+            recurrence.validate()?;
+        }
+        self.update_task(task, |x| x.recurrence = recurrence)
+    }
+
+    async fn task_set_reminders(&self, task: &Uuid, reminders: Vec<u32>) -> Result<Task, DBerror> {
+        self.update_task(task, |x| x.reminders = reminders)
+    }
+
+    async fn put_task(&self, task: &Task) -> Result<Task, DBerror> {
+        task.timespan.validate()?;
+        if let Some(recurrence) = &task.recurrence {
+            recurrence.validate()?;
+        }
+        self.write().tasks.insert(task.uuid.clone(), task.clone());
+        Ok(task.clone())
+    }
+
+    async fn event_edit_name(&self, event: &Uuid, new_name: &str) -> Result<Event, DBerror> {
+        self.update_event(event, |x| x.name = new_name.to_string())
+    }
+
+    async fn event_edit_desc(&self, event: &Uuid, new_desc: &str) -> Result<Event, DBerror> {
+        self.update_event(event, |x| x.description = new_desc.to_string())
+    }
+
+    async fn event_edit_timespan(&self, event: &Uuid, new_timespan: &Timespan) -> Result<Event, DBerror> {
+        new_timespan.validate()?;
+        self.update_event(event, |x| x.timespan = new_timespan.clone())
+    }
+
+    async fn event_change_category(&self, event: &Uuid, new_category: &Uuid) -> Result<Event, DBerror> {
+        self.update_event(event, |x| x.category = new_category.clone())
+    }
+
+    async fn event_set_recurrence(&self, event: &Uuid, recurrence: Option<Recurrence>) -> Result<Event, DBerror> {
+        if let Some(recurrence) = &recurrence {
+            recurrence.validate()?;
+        }
+        self.update_event(event, |x| x.recurrence = recurrence)
+    }
+
+    async fn event_set_reminders(&self, event: &Uuid, reminders: Vec<u32>) -> Result<Event, DBerror> {
+        self.update_event(event, |x| x.reminders = reminders)
+    }
+
+    async fn put_event(&self, event: &Event) -> Result<Event, DBerror> {
+        event.timespan.validate()?;
+        if let Some(recurrence) = &event.recurrence {
+            recurrence.validate()?;
+        }
+        self.write().events.insert(event.uuid.clone(), event.clone());
+        Ok(event.clone())
+    }
+
+    async fn category_edit_name(&self, category: &Uuid, new_name: &str) -> Result<Category, DBerror> {
+        self.update_category(category, |x| x.name = new_name.to_string())
+    }
+
+    async fn category_change_color(&self, category: &Uuid, new_color: u32) -> Result<Category, DBerror> {
+        self.update_category(category, |x| x.color = new_color)
+    }
+
+    async fn category_set_hidden(&self, category: &Uuid, hidden: bool) -> Result<Category, DBerror> {
+        self.update_category(category, |x| x.hidden = hidden)
+    }
+
+    async fn delete_user(&self, user: &Uuid) -> Result<User, DBerror> {
+        let mut tables = self.write();
+        let deleted = tables.users.remove(user).ok_or(DBerror::UserNotFound(user.clone()))?;
+        tables.tasks.retain(|_, x| x.user != *user);
+        tables.events.retain(|_, x| x.user != *user);
+        tables.categories.retain(|_, x| x.user != *user);
+        tables.sessions.retain(|_, x| x.user != *user);
+        tables.reminder_deliveries.retain(|_, x| x.user != *user);
+        Ok(deleted)
+    }
+
+    async fn delete_task(&self, task: &Uuid) -> Result<Task, DBerror> {
+        self.write().tasks.remove(task).ok_or(DBerror::TaskNotFound(task.clone()))
+    }
+
+    async fn delete_event(&self, event: &Uuid) -> Result<Event, DBerror> {
+        self.write().events.remove(event).ok_or(DBerror::EventNotFound(event.clone()))
+    }
+
+    async fn delete_category(&self, category: &Uuid, replacement: &Uuid) -> Result<Category, DBerror> {
+        // one lock for the whole change, so nobody sees the items moved and the category still there
+        let mut tables = self.write();
+        let owner = tables.categories.get(category).ok_or(DBerror::CategoryNotFound(category.clone()))?.user.clone();
+        match tables.categories.get(replacement) {
+            None => return Err(DBerror::CategoryNotFound(replacement.clone())),
+            Some(x) if replacement == category || x.user != owner => return Err(DBerror::InvalidReplacement(replacement.clone())),
+            Some(_) => (),
+        }
+        let deleted = tables.categories.remove(category).ok_or(DBerror::CategoryNotFound(category.clone()))?;
+        for task in tables.tasks.values_mut().filter(|x| x.category == *category) {
+            task.category = replacement.clone();
+        }
+        for event in tables.events.values_mut().filter(|x| x.category == *category) {
+            event.category = replacement.clone();
+        }
+        if let Some(owner) = tables.users.get_mut(&deleted.user) {
+            owner.categories.retain(|x| x != category);
+        }
+        Ok(deleted)
+    }
+
+    async fn uncategorised_category(&self, user: &Uuid) -> Result<Category, DBerror> {
+        let found = self.read().categories.values().find(|x| x.user == *user && x.name == UNCATEGORISED).cloned();
+        match found {
+            Some(x) => Ok(x),
+            None => self.add_category(UNCATEGORISED, UNCATEGORISED_COLOR, user).await,
+        }
+    }
+
+    async fn add_session(&self, session: &Session) -> Result<Session, DBerror> {
+        self.write().sessions.insert(session.token_hash.clone(), session.clone());
+        Ok(session.clone())
+    }
+
+    async fn get_session(&self, token_hash: &str) -> Result<Session, DBerror> {
+        self.read().sessions.get(token_hash).cloned().ok_or(DBerror::SessionNotFound)
+    }
+
+    async fn delete_session(&self, token_hash: &str) -> Result<Session, DBerror> {
+        self.write().sessions.remove(token_hash).ok_or(DBerror::SessionNotFound)
+    }
+
+    async fn delete_expired_sessions(&self, now: &DateTime::<Utc>) -> Result<usize, DBerror> {
+        let mut tables = self.write();
+        let before = tables.sessions.len();
+        tables.sessions.retain(|_, x| x.expires_at > *now);
+        Ok(before - tables.sessions.len())
+    }
+
+    async fn claim_reminder(&self, delivery: &ReminderDelivery, stale_before: &DateTime::<Utc>) -> Result<bool, DBerror> {
+        let mut tables = self.write();
+        if let Some(existing) = tables.reminder_deliveries.get(&delivery.key) {
+            if existing.sent_at.is_some() || existing.claimed_at >= *stale_before {
+                return Ok(false);
+            }
+        }
+        tables.reminder_deliveries.insert(delivery.key.clone(), delivery.clone());
+        Ok(true)
+    }
+
+    async fn finish_reminder(&self, key: &str, sent_at: &DateTime::<Utc>) -> Result<ReminderDelivery, DBerror> {
+        let mut tables = self.write();
+        let entry = tables.reminder_deliveries.get_mut(key).ok_or(DBerror::ReminderNotFound(key.to_string()))?;
+        entry.sent_at = Some(*sent_at);
+        Ok(entry.clone())
+    }
+
+    async fn release_reminder(&self, key: &str) -> Result<ReminderDelivery, DBerror> {
+        self.write().reminder_deliveries.remove(key).ok_or(DBerror::ReminderNotFound(key.to_string()))
+    }
+
+    async fn get_reminder_deliveries(&self, user: &Uuid) -> Result<Vec<ReminderDelivery>, DBerror> {
+        let mut deliveries: Vec<ReminderDelivery> = self.read().reminder_deliveries.values()
+            .filter(|x| x.user == *user)
+            .cloned()
+            .collect();
+        deliveries.sort_by_key(|x| x.occurs_at);
+        Ok(deliveries)
+    }
+
+    async fn get_jobs(&self) -> Result<Vec<Job>, DBerror> {
+        Ok(self.read().jobs.values().cloned().collect())
+    }
+
+    async fn get_job(&self, name: &str) -> Result<Job, DBerror> {
+        self.read().jobs.get(name).cloned().ok_or(DBerror::JobNotFound(name.to_string()))
+    }
+
+    async fn ensure_job(&self, job: &Job) -> Result<Job, DBerror> {
+        Ok(self.write().jobs.entry(job.name.clone()).or_insert_with(|| job.clone()).clone())
+    }
+
+    async fn lock_job(&self, name: &str, runner: &str, now: &DateTime::<Utc>, locked_until: &DateTime::<Utc>) -> Result<Option<Job>, DBerror> {
+        let mut tables = self.write();
+        let entry = tables.jobs.get_mut(name).ok_or(DBerror::JobNotFound(name.to_string()))?;
+        if entry.next_run > *now || entry.is_running(now) {
+            return Ok(None);
+        }
+        entry.locked_by = Some(runner.to_string());
+        entry.locked_until = Some(*locked_until);
+        entry.last_started_at = Some(*now);
+        Ok(Some(entry.clone()))
+    }
+
+    async fn finish_job(&self, job: &Job, runner: &str) -> Result<bool, DBerror> {
+        let mut tables = self.write();
+        let entry = tables.jobs.get_mut(&job.name).ok_or(DBerror::JobNotFound(job.name.clone()))?;
+        if entry.locked_by.as_deref() != Some(runner) {
+            return Ok(false);
+        }
+        *entry = job.clone();
+        Ok(true)
+    }
+}
@@ -0,0 +1,54 @@
+use surrealdb::{Surreal, Connection};
+
+use crate::backend::database::db_error::DBerror;
+
+/// SurrealQL scripts bringing the schema from version `n` to `n + 1`, where `n` is the index.
+///
+/// Applied migrations must never be edited: add a new script to change the schema.
+const MIGRATIONS: &[&str] = &[
+    include_str!("schema/001_initial.surql"),
+    include_str!("schema/002_recurrence.surql"),
+    include_str!("schema/003_ical_uid.surql"),
+    include_str!("schema/004_password_hash.surql"),
+    include_str!("schema/005_sessions.surql"),
+    include_str!("schema/006_week_start.surql"),
+    include_str!("schema/007_hidden_categories.surql"),
+    include_str!("schema/008_reminders.surql"),
+    include_str!("schema/009_jobs.surql"),
+    include_str!("schema/010_time_zones.surql"),
+    include_str!("schema/011_all_day.surql"),
+    include_str!("schema/012_recurs_until.surql"),
+];
+
+/// The schema version this build understands.
+pub const LATEST_VERSION: u32 = MIGRATIONS.len() as u32;
+
+///retrieve the schema version recorded in the database, 0 for a fresh database
+pub async fn schema_version<C: Connection>(db: &Surreal<C>) -> Result<u32, DBerror> {
+    let mut response = db.query("SELECT version FROM migrations:current").await?;
+    let version: Option<u32> = response.take((0, "version"))?;
+    Ok(version.unwrap_or(0))
+}
+
+///applies every migration newer than the recorded schema version and returns the resulting version
+///
+///each migration runs in its own transaction together with the version update, refuses to touch a database whose schema is newer than `LATEST_VERSION`
+pub async fn migrate<C: Connection>(db: &Surreal<C>) -> Result<u32, DBerror> {
+    let current = schema_version(db).await?;
+    if current > LATEST_VERSION {
+        return Err(DBerror::SchemaTooNew(current, LATEST_VERSION));
+    }
+
+    for (index, script) in MIGRATIONS.iter().enumerate().skip(current as usize) {
+        let version = index as u32 + 1;
+        db.query("BEGIN TRANSACTION")
+            .query(*script)
+            .query("UPDATE migrations:current SET version = $version")
+            .query("COMMIT TRANSACTION")
+            .bind(("version", version))
+            .await?
+            .check()?;
+    }
+
+    Ok(LATEST_VERSION)
+}
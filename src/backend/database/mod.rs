@@ -1,2 +1,6 @@
 pub mod db_requests;
-pub mod db_error;
\ No newline at end of file
+pub mod db_error;
+pub mod store;
+pub mod memory;
+pub mod connection;
+pub mod migrations;
\ No newline at end of file
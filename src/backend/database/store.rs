@@ -0,0 +1,397 @@
+use async_trait::async_trait;
+use chrono::{Utc, DateTime};
+use surrealdb::{sql::Uuid, Surreal, Connection};
+
+use crate::backend::database::db_error::DBerror;
+use crate::backend::database::db_requests;
+use crate::common::model::{User, Timespan, Task, Event, Category, Session, WeekStart, LocalZone, ReminderDelivery, Job};
+use crate::common::recurrence::Recurrence;
+
+/// Storage backend for users, tasks, events and categories.
+///
+/// Every operation in `db_requests` is mirrored here, so the rest of the app can hold a
+/// `dyn Store` instead of a concrete `Surreal` connection. `Surreal<C>` implements this by
+/// delegating to `db_requests`, and `MemoryStore` provides an in-process implementation.
+#[async_trait]
+pub trait Store: Send + Sync {
+    ///adds user to the store, hashing the plaintext password
+    async fn add_user(&self, username: &str, password: &str) -> Result<User, DBerror>;
+    ///adds task to the store, timed or on whole dates and optionally recurring
+    async fn add_task(&self, name: &str, description: &str, timespan: &Timespan, category: &Uuid, recurrence: Option<Recurrence>, user: &Uuid) -> Result<Task, DBerror>;
+    ///adds event to the store, timed or on whole dates and optionally recurring
+    async fn add_event(&self, name: &str, description: &str, timespan: &Timespan, category: &Uuid, recurrence: Option<Recurrence>, user: &Uuid) -> Result<Event, DBerror>;
+    ///adds category to the store
+    async fn add_category(&self, name: &str, color: u32, user: &Uuid) -> Result<Category, DBerror>;
+
+    ///get user from Uuid
+    async fn get_user(&self, user_id: &Uuid) -> Result<User, DBerror>;
+    ///get task from Uuid
+    async fn get_task(&self, task_id: &Uuid) -> Result<Task, DBerror>;
+    ///get event from Uuid
+    async fn get_event(&self, event_id: &Uuid) -> Result<Event, DBerror>;
+    ///get category from Uuid
+    async fn get_category(&self, category_id: &Uuid) -> Result<Category, DBerror>;
+    ///retrieve tasks for a given user
+    async fn get_tasks(&self, userid: &Uuid) -> Result<Vec<Task>, DBerror>;
+    ///retrieve events for a given user
+    async fn get_events(&self, userid: &Uuid) -> Result<Vec<Event>, DBerror>;
+    ///retrieve tasks for a given user that overlap the given range or may recur into it, ordered by start
+    async fn get_tasks_in_range(&self, userid: &Uuid, range: &Timespan) -> Result<Vec<Task>, DBerror>;
+    ///retrieve events for a given user that overlap the given range or may recur into it, ordered by start
+    async fn get_events_in_range(&self, userid: &Uuid, range: &Timespan) -> Result<Vec<Event>, DBerror>;
+    ///retrieve the tasks of a given user that are not completed yet, ordered by due time
+    async fn get_open_tasks(&self, userid: &Uuid) -> Result<Vec<Task>, DBerror>;
+    ///retrieve every user's open tasks with reminders that are due after `now` or recur
+    async fn get_tasks_with_reminders(&self, now: &DateTime::<Utc>) -> Result<Vec<Task>, DBerror>;
+    ///retrieve every user's events with reminders that start after `now` or recur
+    async fn get_events_with_reminders(&self, now: &DateTime::<Utc>) -> Result<Vec<Event>, DBerror>;
+    ///retrieve categories for a given user
+    async fn get_categories(&self, userid: &Uuid) -> Result<Vec<Uuid>, DBerror>;
+    ///retrieve user id from username
+    async fn user_id_from_name(&self, name: &str) -> Result<Uuid, DBerror>;
+    ///retrieve user id from the token in a calendar feed url
+    async fn user_id_from_feed_token(&self, token: &str) -> Result<Uuid, DBerror>;
+
+    ///change username
+    async fn change_username(&self, user: &Uuid, new_username: &str) -> Result<User, DBerror>;
+    ///change password, hashing the plaintext password
+    async fn change_password(&self, user: &Uuid, new_password: &str) -> Result<User, DBerror>;
+    ///check a user's password, rehashing it when it was hashed with outdated parameters
+    async fn verify_password(&self, user: &Uuid, password: &str) -> Result<bool, DBerror>;
+    ///replace the feed token of a user, so the old calendar feed url stops working
+    async fn reset_feed_token(&self, user: &Uuid) -> Result<User, DBerror>;
+    ///change the day the user's weeks start on
+    async fn set_week_start(&self, user: &Uuid, week_start: WeekStart) -> Result<User, DBerror>;
+    ///change the time zone a user's calendar is shown in
+    async fn set_time_zone(&self, user: &Uuid, time_zone: LocalZone) -> Result<User, DBerror>;
+    ///change the address a user's reminders are sent to, `None` to stop sending them
+    async fn set_email(&self, user: &Uuid, email: Option<String>) -> Result<User, DBerror>;
+    ///change task name
+    async fn task_edit_name(&self, task: &Uuid, new_name: &str) -> Result<Task, DBerror>;
+    ///change task description
+    async fn task_edit_desc(&self, task: &Uuid, new_desc: &str) -> Result<Task, DBerror>;
+    ///change task timespan
+    async fn task_edit_timespan(&self, task: &Uuid, new_timespan: &Timespan) -> Result<Task, DBerror>;
+    ///change task category
+    async fn task_change_category(&self, task: &Uuid, new_category: &Uuid) -> Result<Task, DBerror>;
+    ///set the 'completed' field of a task
+    async fn task_set_completion(&self, task: &Uuid, completion: bool) -> Result<Task, DBerror>;
+    ///set or clear the recurrence rule of a task
+    async fn task_set_recurrence(&self, task: &Uuid, recurrence: Option<Recurrence>) -> Result<Task, DBerror>;
+    ///set the reminders of a task, in minutes before its due time
+    async fn task_set_reminders(&self, task: &Uuid, reminders: Vec<u32>) -> Result<Task, DBerror>;
+    ///creates or replaces a task with all of its fields
+    async fn put_task(&self, task: &Task) -> Result<Task, DBerror>;
+    ///change event name
+    async fn event_edit_name(&self, event: &Uuid, new_name: &str) -> Result<Event, DBerror>;
+    ///change event description
+    async fn event_edit_desc(&self, event: &Uuid, new_desc: &str) -> Result<Event, DBerror>;
+    ///change event timespan
+    async fn event_edit_timespan(&self, event: &Uuid, new_timespan: &Timespan) -> Result<Event, DBerror>;
+    ///change event category
+    async fn event_change_category(&self, event: &Uuid, new_category: &Uuid) -> Result<Event, DBerror>;
+    ///set or clear the recurrence rule of an event
+    async fn event_set_recurrence(&self, event: &Uuid, recurrence: Option<Recurrence>) -> Result<Event, DBerror>;
+    ///set the reminders of an event, in minutes before its start
+    async fn event_set_reminders(&self, event: &Uuid, reminders: Vec<u32>) -> Result<Event, DBerror>;
+    ///creates or replaces an event with all of its fields
+    async fn put_event(&self, event: &Event) -> Result<Event, DBerror>;
+    ///change category name
+    async fn category_edit_name(&self, category: &Uuid, new_name: &str) -> Result<Category, DBerror>;
+    ///change category color
+    async fn category_change_color(&self, category: &Uuid, new_color: u32) -> Result<Category, DBerror>;
+    ///hide or show the items of a category in the calendar views
+    async fn category_set_hidden(&self, category: &Uuid, hidden: bool) -> Result<Category, DBerror>;
+
+    ///deletes a user with everything they own, their tasks, events, categories, sessions and sent reminders, in one transaction
+    async fn delete_user(&self, user: &Uuid) -> Result<User, DBerror>;
+    ///deletes a task
+    async fn delete_task(&self, task: &Uuid) -> Result<Task, DBerror>;
+    ///deletes an event
+    async fn delete_event(&self, event: &Uuid) -> Result<Event, DBerror>;
+    ///deletes a category in one transaction, handing its tasks and events to `replacement`, another
+    ///category of the same user, and removing it from the user's categories. Any other replacement is
+    ///refused with `InvalidReplacement`
+    async fn delete_category(&self, category: &Uuid, replacement: &Uuid) -> Result<Category, DBerror>;
+    ///get the user's "Uncategorised" category, adding it when there is none
+    async fn uncategorised_category(&self, user: &Uuid) -> Result<Category, DBerror>;
+
+    ///stores a new session, keyed by the hash of its token
+    async fn add_session(&self, session: &Session) -> Result<Session, DBerror>;
+    ///get session from the hash of its token
+    async fn get_session(&self, token_hash: &str) -> Result<Session, DBerror>;
+    ///deletes a session, logging its browser out
+    async fn delete_session(&self, token_hash: &str) -> Result<Session, DBerror>;
+    ///deletes every session that expired before the given time, returning how many there were
+    async fn delete_expired_sessions(&self, now: &DateTime::<Utc>) -> Result<usize, DBerror>;
+
+    ///records that a reminder is being sent, returning false when it was sent already or another
+    ///sender claimed it after `stale_before`, in which case the caller must not send it
+    async fn claim_reminder(&self, delivery: &ReminderDelivery, stale_before: &DateTime::<Utc>) -> Result<bool, DBerror>;
+    ///marks a claimed reminder as sent, so it is never sent again
+    async fn finish_reminder(&self, key: &str, sent_at: &DateTime::<Utc>) -> Result<ReminderDelivery, DBerror>;
+    ///gives up the claim on a reminder that could not be sent, so it is tried again
+    async fn release_reminder(&self, key: &str) -> Result<ReminderDelivery, DBerror>;
+    ///retrieve the reminders sent or being sent to a given user, ordered by the time of their occurrence
+    async fn get_reminder_deliveries(&self, user: &Uuid) -> Result<Vec<ReminderDelivery>, DBerror>;
+
+    ///retrieve every background job, ordered by name
+    async fn get_jobs(&self) -> Result<Vec<Job>, DBerror>;
+    ///get background job from its name
+    async fn get_job(&self, name: &str) -> Result<Job, DBerror>;
+    ///stores a job unless one with its name exists already, returning the stored one
+    async fn ensure_job(&self, job: &Job) -> Result<Job, DBerror>;
+    ///locks a job that is due at `now` and not locked by a live runner, until `locked_until`,
+    ///returning `None` when the job is not due yet or another runner holds it
+    async fn lock_job(&self, name: &str, runner: &str, now: &DateTime::<Utc>, locked_until: &DateTime::<Utc>) -> Result<Option<Job>, DBerror>;
+    ///stores the outcome of a run and releases the lock held by `runner`, returning false without
+    ///storing anything when the lock expired and another runner took it over
+    async fn finish_job(&self, job: &Job, runner: &str) -> Result<bool, DBerror>;
+}
+
+#[async_trait]
+impl<C: Connection> Store for Surreal<C> {
+    async fn add_user(&self, username: &str, password: &str) -> Result<User, DBerror> {
+        db_requests::add_user(self, username, password).await
+    }
+
+    async fn add_task(&self, name: &str, description: &str, timespan: &Timespan, category: &Uuid, recurrence: Option<Recurrence>, user: &Uuid) -> Result<Task, DBerror> {
+        db_requests::add_task(self, name, description, timespan, category, recurrence, user).await
+    }
+
+    async fn add_event(&self, name: &str, description: &str, timespan: &Timespan, category: &Uuid, recurrence: Option<Recurrence>, user: &Uuid) -> Result<Event, DBerror> {
+        db_requests::add_event(self, name, description, timespan, category, recurrence, user).await
+    }
+
+    async fn add_category(&self, name: &str, color: u32, user: &Uuid) -> Result<Category, DBerror> {
+        db_requests::add_category(self, name, color, user).await
+    }
+
+    async fn get_user(&self, user_id: &Uuid) -> Result<User, DBerror> {
+        db_requests::get_user(self, user_id).await
+    }
+
+    async fn get_task(&self, task_id: &Uuid) -> Result<Task, DBerror> {
+        db_requests::get_task(self, task_id).await
+    }
+
+    async fn get_event(&self, event_id: &Uuid) -> Result<Event, DBerror> {
+        db_requests::get_event(self, event_id).await
+    }
+
+    async fn get_category(&self, category_id: &Uuid) -> Result<Category, DBerror> {
+        db_requests::get_category(self, category_id).await
+    }
+
+    async fn get_tasks(&self, userid: &Uuid) -> Result<Vec<Task>, DBerror> {
+        db_requests::get_tasks(self, userid).await
+    }
+
+    async fn get_events(&self, userid: &Uuid) -> Result<Vec<Event>, DBerror> {
+        db_requests::get_events(self, userid).await
+    }
+
+    async fn get_tasks_in_range(&self, userid: &Uuid, range: &Timespan) -> Result<Vec<Task>, DBerror> {
+        db_requests::get_tasks_in_range(self, userid, range).await
+    }
+
+    async fn get_events_in_range(&self, userid: &Uuid, range: &Timespan) -> Result<Vec<Event>, DBerror> {
+        db_requests::get_events_in_range(self, userid, range).await
+    }
+
+    async fn get_open_tasks(&self, userid: &Uuid) -> Result<Vec<Task>, DBerror> {
+        db_requests::get_open_tasks(self, userid).await
+    }
+
+    async fn get_tasks_with_reminders(&self, now: &DateTime::<Utc>) -> Result<Vec<Task>, DBerror> {
+        db_requests::get_tasks_with_reminders(self, now).await
+    }
+
+    async fn get_events_with_reminders(&self, now: &DateTime::<Utc>) -> Result<Vec<Event>, DBerror> {
+        db_requests::get_events_with_reminders(self, now).await
+    }
+
+    async fn get_categories(&self, userid: &Uuid) -> Result<Vec<Uuid>, DBerror> {
+        db_requests::get_categories(self, userid).await
+    }
+
+    async fn user_id_from_name(&self, name: &str) -> Result<Uuid, DBerror> {
+        db_requests::user_id_from_name(self, name).await
+    }
+
+    async fn user_id_from_feed_token(&self, token: &str) -> Result<Uuid, DBerror> {
+        db_requests::user_id_from_feed_token(self, token).await
+    }
+
+    async fn change_username(&self, user: &Uuid, new_username: &str) -> Result<User, DBerror> {
+        db_requests::change_username(self, user, new_username).await
+    }
+
+    async fn change_password(&self, user: &Uuid, new_password: &str) -> Result<User, DBerror> {
+        db_requests::change_password(self, user, new_password).await
+    }
+
+    async fn verify_password(&self, user: &Uuid, password: &str) -> Result<bool, DBerror> {
+        db_requests::verify_password(self, user, password).await
+    }
+
+    async fn reset_feed_token(&self, user: &Uuid) -> Result<User, DBerror> {
+        db_requests::reset_feed_token(self, user).await
+    }
+
+    async fn set_week_start(&self, user: &Uuid, week_start: WeekStart) -> Result<User, DBerror> {
+        db_requests::set_week_start(self, user, week_start).await
+    }
+
+    async fn set_time_zone(&self, user: &Uuid, time_zone: LocalZone) -> Result<User, DBerror> {
+        db_requests::set_time_zone(self, user, time_zone).await
+    }
+
+    async fn set_email(&self, user: &Uuid, email: Option<String>) -> Result<User, DBerror> {
+        db_requests::set_email(self, user, email).await
+    }
+
+    async fn task_edit_name(&self, task: &Uuid, new_name: &str) -> Result<Task, DBerror> {
+        db_requests::task_edit_name(self, task, new_name).await
+    }
+
+    async fn task_edit_desc(&self, task: &Uuid, new_desc: &str) -> Result<Task, DBerror> {
+        db_requests::task_edit_desc(self, task, new_desc).await
+    }
+
+    async fn task_edit_timespan(&self, task: &Uuid, new_timespan: &Timespan) -> Result<Task, DBerror> {
+        db_requests::task_edit_timespan(self, task, new_timespan).await
+    }
+
+    async fn task_change_category(&self, task: &Uuid, new_category: &Uuid) -> Result<Task, DBerror> {
+        db_requests::task_change_category(self, task, new_category).await
+    }
+
+    async fn task_set_completion(&self, task: &Uuid, completion: bool) -> Result<Task, DBerror> {
+        db_requests::task_set_completion(self, task, completion).await
+    }
+
+    async fn task_set_recurrence(&self, task: &Uuid, recurrence: Option<Recurrence>) -> Result<Task, DBerror> {
+        db_requests::task_set_recurrence(self, task, recurrence).await
+    }
+
+    async fn task_set_reminders(&self, task: &Uuid, reminders: Vec<u32>) -> Result<Task, DBerror> {
+        db_requests::task_set_reminders(self, task, reminders).await
+    }
+
+    async fn put_task(&self, task: &Task) -> Result<Task, DBerror> {
+        db_requests::put_task(self, task).await
+    }
+
+    async fn event_edit_name(&self, event: &Uuid, new_name: &str) -> Result<Event, DBerror> {
+        db_requests::event_edit_name(self, event, new_name).await
+    }
+
+    async fn event_edit_desc(&self, event: &Uuid, new_desc: &str) -> Result<Event, DBerror> {
+        db_requests::event_edit_desc(self, event, new_desc).await
+    }
+
+    async fn event_edit_timespan(&self, event: &Uuid, new_timespan: &Timespan) -> Result<Event, DBerror> {
+        db_requests::event_edit_timespan(self, event, new_timespan).await
+    }
+
+    async fn event_change_category(&self, event: &Uuid, new_category: &Uuid) -> Result<Event, DBerror> {
+        db_requests::event_change_category(self, event, new_category).await
+    }
+
+    async fn event_set_recurrence(&self, event: &Uuid, recurrence: Option<Recurrence>) -> Result<Event, DBerror> {
+        db_requests::event_set_recurrence(self, event, recurrence).await
+    }
+
+    async fn event_set_reminders(&self, event: &Uuid, reminders: Vec<u32>) -> Result<Event, DBerror> {
+        db_requests::event_set_reminders(self, event, reminders).await
+    }
+
+    async fn put_event(&self, event: &Event) -> Result<Event, DBerror> {
+        db_requests::put_event(self, event).await
+    }
+
+    async fn category_edit_name(&self, category: &Uuid, new_name: &str) -> Result<Category, DBerror> {
+        db_requests::category_edit_name(self, category, new_name).await
+    }
+
+    async fn category_change_color(&self, category: &Uuid, new_color: u32) -> Result<Category, DBerror> {
+        db_requests::category_change_color(self, category, new_color).await
+    }
+
+    async fn category_set_hidden(&self, category: &Uuid, hidden: bool) -> Result<Category, DBerror> {
+        db_requests::category_set_hidden(self, category, hidden).await
+    }
+
+    async fn delete_user(&self, user: &Uuid) -> Result<User, DBerror> {
+        db_requests::delete_user(self, user).await
+    }
+
+    async fn delete_task(&self, task: &Uuid) -> Result<Task, DBerror> {
+        db_requests::delete_task(self, task).await
+    }
+
+    async fn delete_event(&self, event: &Uuid) -> Result<Event, DBerror> {
+        db_requests::delete_event(self, event).await
+    }
+
+    async fn delete_category(&self, category: &Uuid, replacement: &Uuid) -> Result<Category, DBerror> {
+        db_requests::delete_category(self, category, replacement).await
+    }
+
+    async fn uncategorised_category(&self, user: &Uuid) -> Result<Category, DBerror> {
+        db_requests::uncategorised_category(self, user).await
+    }
+
+    async fn add_session(&self, session: &Session) -> Result<Session, DBerror> {
+        db_requests::add_session(self, session).await
+    }
+
+    async fn get_session(&self, token_hash: &str) -> Result<Session, DBerror> {
+        db_requests::get_session(self, token_hash).await
+    }
+
+    async fn delete_session(&self, token_hash: &str) -> Result<Session, DBerror> {
+        db_requests::delete_session(self, token_hash).await
+    }
+
+    async fn delete_expired_sessions(&self, now: &DateTime::<Utc>) -> Result<usize, DBerror> {
+        db_requests::delete_expired_sessions(self, now).await
+    }
+
+    async fn claim_reminder(&self, delivery: &ReminderDelivery, stale_before: &DateTime::<Utc>) -> Result<bool, DBerror> {
+        db_requests::claim_reminder(self, delivery, stale_before).await
+    }
+
+    async fn finish_reminder(&self, key: &str, sent_at: &DateTime::<Utc>) -> Result<ReminderDelivery, DBerror> {
+        db_requests::finish_reminder(self, key, sent_at).await
+    }
+
+    async fn release_reminder(&self, key: &str) -> Result<ReminderDelivery, DBerror> {
+        db_requests::release_reminder(self, key).await
+    }
+
+    async fn get_reminder_deliveries(&self, user: &Uuid) -> Result<Vec<ReminderDelivery>, DBerror> {
+        db_requests::get_reminder_deliveries(self, user).await
+    }
+
+    async fn get_jobs(&self) -> Result<Vec<Job>, DBerror> {
+        db_requests::get_jobs(self).await
+    }
+
+    async fn get_job(&self, name: &str) -> Result<Job, DBerror> {
+        db_requests::get_job(self, name).await
+    }
+
+    async fn ensure_job(&self, job: &Job) -> Result<Job, DBerror> {
+        db_requests::ensure_job(self, job).await
+    }
+
+    async fn lock_job(&self, name: &str, runner: &str, now: &DateTime::<Utc>, locked_until: &DateTime::<Utc>) -> Result<Option<Job>, DBerror> {
+        db_requests::lock_job(self, name, runner, now, locked_until).await
+    }
+
+    async fn finish_job(&self, job: &Job, runner: &str) -> Result<bool, DBerror> {
+        db_requests::finish_job(self, job, runner).await
+    }
+}
@@ -0,0 +1,121 @@
+use actix_web::{get, http::header, post, web, HttpResponse};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use surrealdb::sql::Uuid;
+
+use crate::backend::auth::CurrentUser;
+use crate::backend::database::{db_error::DBerror, store::Store};
+use crate::backend::ical::export::export_calendar;
+use crate::backend::ical::import::import_calendar;
+use crate::common::model::{Category, Event, LocalZone, ReminderDelivery, Task, WeekStart};
+
+/// Largest request body accepted, so whole calendars can be uploaded for import.
+pub const MAX_UPLOAD_BYTES: usize = 16 * 1024 * 1024;
+
+#[derive(Debug, Deserialize)]
+pub struct CalendarQuery {
+    /// Ask the browser to save the feed as a file instead of displaying it
+    #[serde(default)]
+    pub download: bool,
+}
+
+/// Serves all of a user's events and tasks as an iCalendar feed that calendar apps can subscribe to.
+///
+/// Calendar apps cannot log in, so the feed is found by the user's secret feed token instead.
+#[get("/calendar/{token}.ics")]
+pub async fn calendar_feed(db: web::Data<dyn Store>, token: web::Path<String>, query: web::Query<CalendarQuery>) -> Result<HttpResponse, DBerror> {
+    let user = db.user_id_from_feed_token(&token).await?;
+    let events = db.get_events(&user).await?;
+    let tasks = db.get_tasks(&user).await?;
+    // items can point at categories that no longer exist, those are exported without one
+    let categories = existing_categories(db.get_ref(), &user).await?;
+
+    let mut response = HttpResponse::Ok();
+    response.content_type("text/calendar; charset=utf-8");
+    if query.download {
+        response.insert_header((header::CONTENT_DISPOSITION, "attachment; filename=\"gressus.ics\""));
+    }
+    Ok(response.body(export_calendar(&events, &tasks, &categories, &Utc::now())))
+}
+
+/// Imports an uploaded `.ics` file, sent as the request body, into the logged-in user's events and tasks.
+///
+/// Responds with the `ImportReport` as JSON, including the components that were skipped.
+#[post("/calendar/import")]
+pub async fn calendar_import(db: web::Data<dyn Store>, user: CurrentUser, body: String) -> Result<HttpResponse, DBerror> {
+    let report = import_calendar(db.get_ref(), &user.uuid, &body).await?;
+    Ok(HttpResponse::Ok().json(report))
+}
+
+/// Gives the logged-in user a new feed token, so the old feed url stops working.
+#[post("/calendar/feed/reset")]
+pub async fn calendar_feed_reset(db: web::Data<dyn Store>, user: CurrentUser) -> Result<HttpResponse, DBerror> {
+    db.reset_feed_token(&user.uuid).await?;
+    Ok(HttpResponse::SeeOther().insert_header((header::LOCATION, "/user")).finish())
+}
+
+///the user's categories, leaving out the ids of categories that no longer exist
+async fn existing_categories(db: &dyn Store, user: &Uuid) -> Result<Vec<Category>, DBerror> {
+    let mut categories = Vec::new();
+    for category in db.get_categories(user).await? {
+        match db.get_category(&category).await {
+            Ok(category) => categories.push(category),
+            Err(DBerror::CategoryNotFound(_)) => (),
+            Err(x) => return Err(x),
+        }
+    }
+    Ok(categories)
+}
+
+/// The account part of a data export, without the password hash and the feed token, which are
+/// credentials rather than data about the user.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ExportedUser {
+    pub uuid: Uuid,
+    pub name: String,
+    pub joined_at: DateTime::<Utc>,
+    pub week_start: WeekStart,
+    pub email: Option<String>,
+    pub time_zone: LocalZone,
+}
+
+/// Every record stored about a user, as handed out by the data export.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct DataExport {
+    pub exported_at: DateTime::<Utc>,
+    pub user: ExportedUser,
+    pub categories: Vec<Category>,
+    pub tasks: Vec<Task>,
+    pub events: Vec<Event>,
+    /// Reminders that were sent, or are being sent, to the user
+    pub reminder_deliveries: Vec<ReminderDelivery>,
+}
+
+///collects everything stored about a user
+pub async fn export_user_data(db: &dyn Store, user: &Uuid, now: &DateTime::<Utc>) -> Result<DataExport, DBerror> {
+    let stored = db.get_user(user).await?;
+    Ok(DataExport {
+        exported_at: *now,
+        user: ExportedUser {
+            uuid: stored.uuid,
+            name: stored.name,
+            joined_at: stored.joined_at,
+            week_start: stored.week_start,
+            email: stored.email,
+            time_zone: stored.time_zone,
+        },
+        categories: existing_categories(db, user).await?,
+        tasks: db.get_tasks(user).await?,
+        events: db.get_events(user).await?,
+        reminder_deliveries: db.get_reminder_deliveries(user).await?,
+    })
+}
+
+/// Hands the logged-in user everything stored about them, as a JSON file.
+#[get("/user/export.json")]
+pub async fn data_export(db: web::Data<dyn Store>, user: CurrentUser) -> Result<HttpResponse, DBerror> {
+    let export = export_user_data(db.get_ref(), &user.uuid, &Utc::now()).await?;
+    Ok(HttpResponse::Ok()
+        .insert_header((header::CONTENT_DISPOSITION, "attachment; filename=\"gressus-export.json\""))
+        .json(export))
+}
@@ -0,0 +1,241 @@
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveDateTime, Offset, TimeZone, Utc};
+use chrono_tz::{OffsetComponents, OffsetName, Tz};
+use surrealdb::sql::Uuid;
+
+use crate::backend::ical::{CalendarWriter, escape_text};
+use crate::common::model::{Category, Event, LocalZone, Task, Timespan};
+use crate::common::recurrence::{format_ical_datetime, weekday_code, Recurrence};
+
+/// Product identifier written to every exported calendar.
+pub const PRODID: &str = "-//gressus//gressus agenda//EN";
+
+///the iCalendar UID of a task or event created in gressus, imported items keep their original UID
+pub fn uid(uuid: &Uuid) -> String {
+    format!("{}@gressus", uuid.to_raw())
+}
+
+/// Renders a user's events as VEVENTs and tasks as VTODOs in a single VCALENDAR.
+///
+/// Category names are looked up in `categories` and written as CATEGORIES. Recurring items get
+/// their RRULE and EXDATEs, plus one extra component per overridden occurrence. The times of an
+/// item repeating in another zone than UTC are written in that zone with its IANA name as TZID, so
+/// other calendars repeat it at the same wall-clock time, and every such zone gets a VTIMEZONE.
+/// All-day items are written as dates.
+pub fn export_calendar(events: &[Event], tasks: &[Task], categories: &[Category], stamp: &DateTime::<Utc>) -> String {
+    let mut writer = CalendarWriter::new();
+    writer.begin("VCALENDAR");
+    writer.property("VERSION", "2.0");
+    writer.property("PRODID", PRODID);
+    writer.property("CALSCALE", "GREGORIAN");
+    writer.text("X-WR-CALNAME", "gressus");
+
+    let timed_zones = events.iter().map(|x| (&x.timespan, &x.recurrence))
+        .chain(tasks.iter().map(|x| (&x.timespan, &x.recurrence)))
+        .filter(|(timespan, _)| !timespan.all_day)
+        .filter_map(|(_, recurrence)| recurrence.as_ref().map(|x| x.time_zone.0))
+        .filter(|x| *x != Tz::UTC);
+    let mut zones: Vec<Tz> = Vec::new();
+    for zone in timed_zones {
+        if !zones.contains(&zone) {
+            zones.push(zone);
+        }
+    }
+    for zone in zones {
+        write_time_zone(&mut writer, zone, stamp);
+    }
+
+    for event in events {
+        let item = Item {
+            uid: event.ical_uid.clone().unwrap_or_else(|| uid(&event.uuid)),
+            name: &event.name,
+            description: &event.description,
+            timespan: &event.timespan,
+            category: category_name(categories, &event.category),
+            recurrence: event.recurrence.as_ref(),
+        };
+        item.write(&mut writer, "VEVENT", "DTEND", stamp, |_| ());
+    }
+
+    for task in tasks {
+        let item = Item {
+            uid: task.ical_uid.clone().unwrap_or_else(|| uid(&task.uuid)),
+            name: &task.name,
+            description: &task.description,
+            timespan: &task.timespan,
+            category: category_name(categories, &task.category),
+            recurrence: task.recurrence.as_ref(),
+        };
+        item.write(&mut writer, "VTODO", "DUE", stamp, |writer| {
+            writer.property("STATUS", if task.completed { "COMPLETED" } else { "NEEDS-ACTION" });
+        });
+    }
+
+    writer.end("VCALENDAR");
+    writer.finish()
+}
+
+/// Writes a VTIMEZONE for `zone` with the offsets it observes in the year of `now`, from 1970 on.
+///
+/// A zone changing its clocks twice that year gets a STANDARD and a DAYLIGHT observance repeating
+/// yearly on the weekday of the month the changes fell on, e.g. the last Sunday of March. Any other
+/// zone gets a single observance with its offset at `now`.
+fn write_time_zone(writer: &mut CalendarWriter, zone: Tz, now: &DateTime::<Utc>) {
+    writer.begin("VTIMEZONE");
+    writer.property("TZID", zone.name());
+    let changes = offset_changes(zone, now.year());
+    if changes.len() == 2 {
+        for change in changes {
+            let before = zone.offset_from_utc_datetime(&(change - Duration::minutes(1)).naive_utc());
+            let after = zone.offset_from_utc_datetime(&change.naive_utc());
+            // RFC 5545 gives the onset in the wall-clock time before the change
+            let onset = change.naive_utc() + Duration::seconds(before.fix().local_minus_utc().into());
+            let last_week = onset.day() + 7 > days_in_month(onset.year(), onset.month());
+            let ordinal = if last_week { -1 } else { (onset.day() as i8 - 1) / 7 + 1 };
+            let first = if last_week {
+                NaiveDate::from_weekday_of_month_opt(1970, onset.month(), onset.weekday(), 5)
+                    .or_else(|| NaiveDate::from_weekday_of_month_opt(1970, onset.month(), onset.weekday(), 4))
+            } else {
+                NaiveDate::from_weekday_of_month_opt(1970, onset.month(), onset.weekday(), ordinal as u8)
+            };
+            let Some(first) = first else { continue };
+
+            let observance = if after.dst_offset().is_zero() { "STANDARD" } else { "DAYLIGHT" };
+            writer.begin(observance);
+            writer.property("DTSTART", &format_local(&first.and_time(onset.time())));
+            writer.property("RRULE", &format!("FREQ=YEARLY;BYMONTH={};BYDAY={ordinal}{}", onset.month(), weekday_code(onset.weekday())));
+            writer.property("TZOFFSETFROM", &format_offset(before.fix().local_minus_utc()));
+            writer.property("TZOFFSETTO", &format_offset(after.fix().local_minus_utc()));
+            writer.text("TZNAME", after.abbreviation());
+            writer.end(observance);
+        }
+    } else {
+        let offset = zone.offset_from_utc_datetime(&now.naive_utc());
+        writer.begin("STANDARD");
+        writer.property("DTSTART", "19700101T000000");
+        writer.property("TZOFFSETFROM", &format_offset(offset.fix().local_minus_utc()));
+        writer.property("TZOFFSETTO", &format_offset(offset.fix().local_minus_utc()));
+        writer.text("TZNAME", offset.abbreviation());
+        writer.end("STANDARD");
+    }
+    writer.end("VTIMEZONE");
+}
+
+///the instants during `year` at which `zone` changes its offset from UTC, to the minute
+fn offset_changes(zone: Tz, year: i32) -> Vec<DateTime::<Utc>> {
+    let offset = |time: &DateTime::<Utc>| zone.offset_from_utc_datetime(&time.naive_utc()).fix();
+    let (Some(start), Some(end)) = (Utc.with_ymd_and_hms(year, 1, 1, 0, 0, 0).single(), Utc.with_ymd_and_hms(year + 1, 1, 1, 0, 0, 0).single()) else {
+        return Vec::new();
+    };
+    let mut changes = Vec::new();
+    let mut hour = start;
+    while hour < end {
+        let next = hour + Duration::hours(1);
+        if offset(&hour) != offset(&next) {
+            // the clocks change on a whole minute, somewhere in this hour
+            let (mut unchanged, mut changed) = (hour, next);
+            while changed - unchanged > Duration::minutes(1) {
+                let middle = unchanged + Duration::minutes((changed - unchanged).num_minutes() / 2);
+                if offset(&middle) == offset(&hour) {
+                    unchanged = middle;
+                } else {
+                    changed = middle;
+                }
+            }
+            changes.push(changed);
+        }
+        hour = next;
+    }
+    changes
+}
+
+fn days_in_month(year: i32, month: u32) -> u32 {
+    let next = if month == 12 { NaiveDate::from_ymd_opt(year + 1, 1, 1) } else { NaiveDate::from_ymd_opt(year, month + 1, 1) };
+    next.and_then(|x| x.pred_opt()).map_or(31, |x| x.day())
+}
+
+///a UTC offset in seconds as RFC 5545 writes it, e.g. `+0100` or `-0930`
+fn format_offset(seconds: i32) -> String {
+    let sign = if seconds < 0 { '-' } else { '+' };
+    let seconds = seconds.unsigned_abs();
+    match seconds % 60 {
+        0 => format!("{sign}{:02}{:02}", seconds / 3600, seconds / 60 % 60),
+        x => format!("{sign}{:02}{:02}{x:02}", seconds / 3600, seconds / 60 % 60),
+    }
+}
+
+fn format_local(time: &NaiveDateTime) -> String {
+    time.format("%Y%m%dT%H%M%S").to_string()
+}
+
+fn category_name<'a>(categories: &'a [Category], category: &Uuid) -> Option<&'a str> {
+    categories.iter().find(|x| x.uuid == *category).map(|x| x.name.as_str())
+}
+
+/// The fields events and tasks have in common.
+struct Item<'a> {
+    uid: String,
+    name: &'a str,
+    description: &'a str,
+    timespan: &'a Timespan,
+    category: Option<&'a str>,
+    recurrence: Option<&'a Recurrence>,
+}
+
+impl Item<'_> {
+    ///the zone the item's times are written in, `None` for UTC
+    fn zone(&self) -> Option<LocalZone> {
+        self.recurrence.map(|x| x.time_zone).filter(|x| x.0 != Tz::UTC)
+    }
+
+    ///writes a DATE property for an all-day item, otherwise a DATE-TIME one in the item's zone
+    fn time(&self, writer: &mut CalendarWriter, name: &str, time: &DateTime::<Utc>, all_day: bool) {
+        if all_day {
+            return writer.property(&format!("{name};VALUE=DATE"), &time.format("%Y%m%d").to_string());
+        }
+        match self.zone() {
+            Some(zone) => writer.property(&format!("{name};TZID={}", zone.0.name()), &zone.local(time).format("%Y%m%dT%H%M%S").to_string()),
+            None => writer.property(name, &format_ical_datetime(time)),
+        }
+    }
+
+    /// Writes the item as `component`, its end as `end_property` (DTEND or DUE), and `extra`
+    /// component-specific properties into the master and every override.
+    fn write(&self, writer: &mut CalendarWriter, component: &str, end_property: &str, stamp: &DateTime::<Utc>, extra: impl Fn(&mut CalendarWriter)) {
+        writer.begin(component);
+        self.write_common(writer, stamp, self.name, self.description, self.timespan, end_property);
+        extra(writer);
+        if let Some(recurrence) = self.recurrence {
+            writer.property("RRULE", &recurrence.to_string());
+            for exdate in &recurrence.exdates {
+                self.time(writer, "EXDATE", exdate, self.timespan.all_day);
+            }
+        }
+        writer.end(component);
+
+        for x in self.recurrence.iter().flat_map(|x| &x.overrides) {
+            writer.begin(component);
+            self.time(writer, "RECURRENCE-ID", &x.recurrence_id, self.timespan.all_day);
+            let name = x.name.as_deref().unwrap_or(self.name);
+            let description = x.description.as_deref().unwrap_or(self.description);
+            self.write_common(writer, stamp, name, description, &x.timespan, end_property);
+            extra(writer);
+            writer.end(component);
+        }
+    }
+
+    fn write_common(&self, writer: &mut CalendarWriter, stamp: &DateTime::<Utc>, name: &str, description: &str, timespan: &Timespan, end_property: &str) {
+        writer.property("UID", &self.uid);
+        writer.property("DTSTAMP", &format_ical_datetime(stamp));
+        self.time(writer, "DTSTART", &timespan.start, timespan.all_day);
+        // DTEND is the day after the last date, while DUE is the last date itself
+        let end = if timespan.all_day && end_property == "DUE" { timespan.end - Duration::days(1) } else { timespan.end };
+        self.time(writer, end_property, &end, timespan.all_day);
+        writer.text("SUMMARY", name);
+        if !description.is_empty() {
+            writer.text("DESCRIPTION", description);
+        }
+        if let Some(category) = self.category {
+            writer.property("CATEGORIES", &escape_text(category));
+        }
+    }
+}
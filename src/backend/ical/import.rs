@@ -0,0 +1,379 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use serde::Serialize;
+use surrealdb::sql::Uuid;
+
+use crate::backend::database::db_error::DBerror;
+use crate::backend::database::store::Store;
+use crate::backend::ical::export::uid;
+use crate::backend::ical::{parse_components, unescape_text, Component, ContentLine};
+use crate::common::model::{Event, LocalZone, Task, Timespan};
+use crate::common::recurrence::{parse_ical_datetime, OccurrenceOverride, Recurrence};
+
+/// Colour given to categories created because an imported item names one that does not exist yet.
+pub const IMPORTED_CATEGORY_COLOR: u32 = 0x1E70F0;
+
+/// Outcome of an import: what was written, and which components were skipped and why.
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct ImportReport {
+    /// Items that did not exist yet
+    pub created: usize,
+    /// Items matched by UID to an earlier import or export, and overwritten
+    pub updated: usize,
+    /// Names of the categories that had to be created
+    pub categories_created: Vec<String>,
+    pub errors: Vec<ImportError>,
+}
+
+/// A component that could not be imported.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ImportError {
+    /// Line on which the component begins, 0 if the file as a whole could not be read
+    pub line: usize,
+    pub uid: Option<String>,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Kind {
+    Event,
+    Task,
+}
+
+/// A VEVENT or VTODO, before it is matched against the store.
+#[derive(Debug)]
+struct ParsedItem {
+    kind: Kind,
+    line: usize,
+    uid: Option<String>,
+    recurrence_id: Option<DateTime::<Utc>>,
+    name: String,
+    description: String,
+    timespan: Timespan,
+    category: Option<String>,
+    completed: bool,
+    recurrence: Option<Recurrence>,
+}
+
+/// Imports every VEVENT as an event and every VTODO as a task of `user`.
+///
+/// Items whose UID matches one the user already has (imported earlier, or exported by gressus) are
+/// updated in place rather than duplicated. Times are converted from the zone their TZID names,
+/// and times without a zone are taken in the user's time zone. Items given as dates become all-day
+/// items, the same dates wherever they are looked at. Categories are matched by
+/// name, ignoring case, and created when missing. Items without one keep the category they have,
+/// new ones go to "Uncategorised". Components that cannot be read are listed in the
+/// report and skipped; only a failing store aborts the import.
+pub async fn import_calendar(db: &dyn Store, user: &Uuid, input: &str) -> Result<ImportReport, DBerror> {
+    let mut report = ImportReport::default();
+
+    let calendars = match parse_components(input) {
+        Ok(components) => components,
+        Err(message) => {
+            report.errors.push(ImportError { line: 0, uid: None, message });
+            return Ok(report);
+        }
+    };
+
+    let zone = db.get_user(user).await?.time_zone;
+    let mut masters = Vec::new();
+    let mut overrides = Vec::new();
+    for component in calendars.iter().filter(|x| x.name == "VCALENDAR").flat_map(|x| &x.components) {
+        let kind = match component.name.as_str() {
+            "VEVENT" => Kind::Event,
+            "VTODO" => Kind::Task,
+            _ => continue,
+        };
+        match parse_item(component, kind, &zone) {
+            Ok(item) if item.recurrence_id.is_some() => overrides.push(item),
+            Ok(item) => masters.push(item),
+            Err(message) => report.errors.push(ImportError {
+                line: component.line,
+                uid: component.property("UID").map(|x| x.value.clone()),
+                message,
+            }),
+        }
+    }
+    if calendars.iter().all(|x| x.name != "VCALENDAR") {
+        report.errors.push(ImportError { line: 0, uid: None, message: "no VCALENDAR found".to_string() });
+    }
+
+    // overridden occurrences are folded into the recurrence of their master
+    for item in overrides {
+        let master = masters.iter_mut()
+            .find(|x| x.kind == item.kind && x.uid.is_some() && x.uid == item.uid)
+            .and_then(|x| x.recurrence.as_mut());
+        match (master, item.recurrence_id) {
+            (Some(recurrence), Some(recurrence_id)) => recurrence.overrides.push(OccurrenceOverride {
+                recurrence_id,
+                timespan: item.timespan,
+                name: Some(item.name),
+                description: Some(item.description),
+            }),
+            _ => report.errors.push(ImportError {
+                line: item.line,
+                uid: item.uid,
+                message: "RECURRENCE-ID does not belong to a recurring item in this file".to_string(),
+            }),
+        }
+    }
+
+    let mut categories = HashMap::new();
+    for id in db.get_categories(user).await? {
+        if let Ok(category) = db.get_category(&id).await {
+            categories.insert(category.name.to_lowercase(), category.uuid);
+        }
+    }
+    let mut tasks: HashMap<String, Task> = db.get_tasks(user).await?
+        .into_iter()
+        .map(|x| (x.ical_uid.clone().unwrap_or_else(|| uid(&x.uuid)), x))
+        .collect();
+    let mut events: HashMap<String, Event> = db.get_events(user).await?
+        .into_iter()
+        .map(|x| (x.ical_uid.clone().unwrap_or_else(|| uid(&x.uuid)), x))
+        .collect();
+
+    // looked up the first time an item has no category to go to
+    let mut uncategorised: Option<Uuid> = None;
+    for item in masters {
+        let named = match &item.category {
+            Some(name) => match categories.get(&name.to_lowercase()) {
+                Some(id) => Some(id.clone()),
+                None => {
+                    let created = db.add_category(name, IMPORTED_CATEGORY_COLOR, user).await?;
+                    categories.insert(name.to_lowercase(), created.uuid.clone());
+                    report.categories_created.push(name.clone());
+                    Some(created.uuid)
+                }
+            },
+            None => None,
+        };
+        // an updated item without CATEGORIES keeps the category it has
+        let kept = match item.kind {
+            Kind::Event => item.uid.as_ref().and_then(|x| events.get(x)).map(|x| x.category.clone()),
+            Kind::Task => item.uid.as_ref().and_then(|x| tasks.get(x)).map(|x| x.category.clone()),
+        };
+        let category = match named.or(kept) {
+            Some(category) => category,
+            None => match &uncategorised {
+                Some(category) => category.clone(),
+                None => {
+                    let category = db.uncategorised_category(user).await?.uuid;
+                    uncategorised = Some(category.clone());
+                    category
+                }
+            },
+        };
+
+        match item.kind {
+            Kind::Event => {
+                let existing = item.uid.as_ref().and_then(|x| events.get(x));
+                let event = Event {
+                    name: item.name,
+                    description: item.description,
+                    timespan: item.timespan,
+                    category,
+                    recurrence: item.recurrence,
+                    ical_uid: existing.map_or(item.uid.clone(), |x| x.ical_uid.clone()),
+                    reminders: existing.map(|x| x.reminders.clone()).unwrap_or_default(),
+                    user: user.clone(),
+                    uuid: existing.map_or_else(Uuid::new, |x| x.uuid.clone()),
+                };
+                if existing.is_some() { report.updated += 1 } else { report.created += 1 }
+                let stored = db.put_event(&event).await?;
+                if let Some(uid) = item.uid {
+                    events.insert(uid, stored);
+                }
+            }
+            Kind::Task => {
+                let existing = item.uid.as_ref().and_then(|x| tasks.get(x));
+                let task = Task {
+                    name: item.name,
+                    description: item.description,
+                    timespan: item.timespan,
+                    category,
+                    completed: item.completed,
+                    recurrence: item.recurrence,
+                    ical_uid: existing.map_or(item.uid.clone(), |x| x.ical_uid.clone()),
+                    reminders: existing.map(|x| x.reminders.clone()).unwrap_or_default(),
+                    user: user.clone(),
+                    uuid: existing.map_or_else(Uuid::new, |x| x.uuid.clone()),
+                };
+                if existing.is_some() { report.updated += 1 } else { report.created += 1 }
+                let stored = db.put_task(&task).await?;
+                if let Some(uid) = item.uid {
+                    tasks.insert(uid, stored);
+                }
+            }
+        }
+    }
+
+    Ok(report)
+}
+
+fn parse_item(component: &Component, kind: Kind, zone: &LocalZone) -> Result<ParsedItem, String> {
+    let parse_time = |line: &ContentLine| parse_time(line, zone);
+    let start = component.property("DTSTART").map(parse_time).transpose()?;
+    let end_property = if kind == Kind::Event { "DTEND" } else { "DUE" };
+    let end = component.property(end_property).map(parse_time).transpose()?;
+    let duration = component.property("DURATION").map(|x| parse_duration(&x.value)).transpose()?;
+
+    let day = Duration::days(1);
+    let add = |time: DateTime::<Utc>, duration: Duration| time.checked_add_signed(duration)
+        .ok_or_else(|| format!("{} ends too far from when it starts", component.name));
+    // an all-day span lasts at least the date it starts on
+    let all_day = |start: DateTime::<Utc>, end: DateTime::<Utc>| {
+        let timespan = Timespan { start, end: if end == start { start + day } else { end }, all_day: true };
+        timespan.validate().map(|_| timespan)
+    };
+    // a date next to a time stands for midnight in the user's zone
+    let timed = |(time, is_date): (DateTime::<Utc>, bool)| if is_date { zone.start_of_day(&time.date_naive()) } else { time };
+    let timespan = match (start, end, duration) {
+        // DTEND is the day after the last date, while DUE is the last date itself
+        (Some((start, true)), Some((end, true)), _) => all_day(start, if kind == Kind::Task { end + day } else { end }),
+        (Some((start, true)), None, Some(duration)) if duration.num_seconds() % day.num_seconds() == 0 => all_day(start, add(start, duration)?),
+        (Some((start, true)), None, None) => all_day(start, start),
+        (None, Some((end, true)), _) => all_day(end, end),
+        (Some(start), Some(end), _) => Timespan::new(&timed(start), &timed(end)),
+        (Some(start), None, Some(duration)) => Timespan::new(&timed(start), &add(timed(start), duration)?),
+        (Some(start), None, None) => Timespan::new(&timed(start), &timed(start)),
+        (None, Some(end), _) => Timespan::new(&timed(end), &timed(end)),
+        (None, None, _) => return Err(format!("{} has neither DTSTART nor {end_property}", component.name)),
+    }.map_err(|_| format!("{end_property} is before DTSTART"))?;
+
+    let recurrence = match component.property("RRULE") {
+        Some(rule) => {
+            let mut recurrence = Recurrence::from_str(&rule.value).map_err(|x| x.to_string())?;
+            // the rule repeats at the wall-clock time of the first occurrence, all-day items on dates
+            if let Some(first) = component.property("DTSTART").or(component.property(end_property)).filter(|_| !timespan.all_day) {
+                recurrence.time_zone = line_zone(first, zone);
+            }
+            for exdate in component.properties("EXDATE") {
+                for value in exdate.value.split(',') {
+                    let line = ContentLine { value: value.to_string(), ..exdate.clone() };
+                    recurrence.exdates.push(parse_time(&line)?.0);
+                }
+            }
+            Some(recurrence)
+        }
+        None => None,
+    };
+
+    let completed = component.property("STATUS").is_some_and(|x| x.value.eq_ignore_ascii_case("COMPLETED"))
+        || component.property("COMPLETED").is_some();
+
+    // gressus has a single category per item, so only the first one is kept
+    let category = component.property("CATEGORIES")
+        .and_then(|x| split_text_list(&x.value).into_iter().next())
+        .filter(|x| !x.is_empty());
+
+    Ok(ParsedItem {
+        kind,
+        line: component.line,
+        uid: component.property("UID").map(|x| x.value.clone()),
+        recurrence_id: component.property("RECURRENCE-ID").map(parse_time).transpose()?.map(|x| x.0),
+        name: component.property("SUMMARY").map(|x| unescape_text(&x.value)).unwrap_or_default(),
+        description: component.property("DESCRIPTION").map(|x| unescape_text(&x.value)).unwrap_or_default(),
+        timespan,
+        category,
+        completed,
+        recurrence,
+    })
+}
+
+///the zone the time of a property is in: UTC when it ends in `Z`, else the zone its TZID names
+///
+///a TZID that is not an IANA name, or a time without one, is taken to be in `default`
+fn line_zone(line: &ContentLine, default: &LocalZone) -> LocalZone {
+    if line.value.trim().ends_with('Z') {
+        return LocalZone(Tz::UTC);
+    }
+    line.param("TZID")
+        .and_then(|x| x.trim_matches('"').parse().ok())
+        .map_or(*default, LocalZone)
+}
+
+///parses a DATE or DATE-TIME property, also returning whether it was a date
+///
+///dates are given as midnight UTC, the way all-day spans keep them. Times are converted from the
+///zone `line_zone` finds, `zone` if it finds none. Years have four digits, which keeps a day or so
+///either side of every value within what chrono can represent
+fn parse_time(line: &ContentLine, zone: &LocalZone) -> Result<(DateTime::<Utc>, bool), String> {
+    let value = line.value.trim();
+    let is_date = line.param("VALUE").map_or(value.len() == 8, |x| x.eq_ignore_ascii_case("DATE"));
+    let parsed = if value.ends_with('Z') {
+        parse_ical_datetime(value)
+    } else if is_date {
+        NaiveDate::parse_from_str(value, "%Y%m%d").ok().map(|x| Utc.from_utc_datetime(&x.and_time(Default::default())))
+    } else {
+        NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S").ok().map(|x| line_zone(line, zone).instant(&x))
+    };
+    parsed.filter(|x| (0..=9999).contains(&x.year()))
+        .map(|x| (x, is_date))
+        .ok_or(format!("invalid {} value '{value}'", line.name))
+}
+
+///parses a DURATION value such as `PT1H30M`, `P1D` or `P2W`
+fn parse_duration(value: &str) -> Result<Duration, String> {
+    let invalid = || format!("invalid DURATION value '{value}'");
+    let (negative, rest) = match value.trim().strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, value.trim().strip_prefix('+').unwrap_or(value.trim())),
+    };
+    let rest = rest.strip_prefix('P').ok_or_else(invalid)?;
+
+    // counted in seconds with checked arithmetic, a huge value is refused rather than overflowing
+    let mut seconds: i64 = 0;
+    let mut number = String::new();
+    let mut in_time = false;
+    for c in rest.chars() {
+        match c {
+            '0'..='9' => number.push(c),
+            'T' if !in_time && number.is_empty() => in_time = true,
+            _ => {
+                let amount: i64 = number.parse().map_err(|_| invalid())?;
+                number.clear();
+                let unit = match (c, in_time) {
+                    ('W', false) => 7 * 24 * 60 * 60,
+                    ('D', false) => 24 * 60 * 60,
+                    ('H', true) => 60 * 60,
+                    ('M', true) => 60,
+                    ('S', true) => 1,
+                    _ => return Err(invalid()),
+                };
+                seconds = amount.checked_mul(unit).and_then(|x| seconds.checked_add(x)).ok_or_else(invalid)?;
+            }
+        }
+    }
+    if !number.is_empty() || rest.is_empty() {
+        return Err(invalid());
+    }
+
+    // chrono keeps durations in milliseconds
+    let milliseconds = seconds.checked_mul(1000).ok_or_else(invalid)?;
+    Ok(Duration::milliseconds(if negative { -milliseconds } else { milliseconds }))
+}
+
+///splits a comma separated list of TEXT values, unescaping each one
+fn split_text_list(value: &str) -> Vec<String> {
+    let mut values = Vec::new();
+    let mut current = String::new();
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                current.push(c);
+                if let Some(next) = chars.next() {
+                    current.push(next);
+                }
+            }
+            ',' => values.push(unescape_text(std::mem::take(&mut current).trim())),
+            c => current.push(c),
+        }
+    }
+    values.push(unescape_text(current.trim()));
+    values
+}
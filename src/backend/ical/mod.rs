@@ -0,0 +1,208 @@
+pub mod export;
+pub mod import;
+
+/// Lines longer than this many octets are folded, as RFC 5545 recommends.
+const MAX_LINE_OCTETS: usize = 75;
+
+///escapes a TEXT value: backslashes, semicolons, commas and newlines
+pub fn escape_text(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            ';' => escaped.push_str("\\;"),
+            ',' => escaped.push_str("\\,"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => (),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Builds an iCalendar stream, taking care of line folding and CRLF line endings.
+#[derive(Debug, Default)]
+pub struct CalendarWriter {
+    output: String,
+}
+
+impl CalendarWriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    ///writes a `NAME:value` content line, the value must already be escaped
+    pub fn property(&mut self, name: &str, value: &str) {
+        let line = format!("{name}:{value}");
+        let mut octets = 0;
+        for c in line.chars() {
+            if octets + c.len_utf8() > MAX_LINE_OCTETS {
+                self.output.push_str("\r\n ");
+                // the leading space of a continuation line counts towards its length
+                octets = 1;
+            }
+            self.output.push(c);
+            octets += c.len_utf8();
+        }
+        self.output.push_str("\r\n");
+    }
+
+    ///writes a TEXT property, escaping its value
+    pub fn text(&mut self, name: &str, value: &str) {
+        self.property(name, &escape_text(value));
+    }
+
+    pub fn begin(&mut self, component: &str) {
+        self.property("BEGIN", component);
+    }
+
+    pub fn end(&mut self, component: &str) {
+        self.property("END", component);
+    }
+
+    pub fn finish(self) -> String {
+        self.output
+    }
+}
+
+///reverses `escape_text`
+pub fn unescape_text(text: &str) -> String {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') | Some('N') => unescaped.push('\n'),
+            Some(c) => unescaped.push(c),
+            None => unescaped.push('\\'),
+        }
+    }
+    unescaped
+}
+
+/// A single `NAME;PARAM=value:value` line of an iCalendar stream.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContentLine {
+    /// Upper-cased property name
+    pub name: String,
+    /// Upper-cased parameter names with their (unquoted) values
+    pub params: Vec<(String, String)>,
+    pub value: String,
+}
+
+impl ContentLine {
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.params.iter().find(|(key, _)| key == name).map(|(_, value)| value.as_str())
+    }
+
+    ///parses a single unfolded line, `None` if it has no `:` separating name and value
+    pub fn parse(line: &str) -> Option<Self> {
+        // the value starts at the first colon outside a quoted parameter value
+        let mut quoted = false;
+        let mut split = None;
+        for (i, c) in line.char_indices() {
+            match c {
+                '"' => quoted = !quoted,
+                ':' if !quoted => {
+                    split = Some(i);
+                    break;
+                }
+                _ => (),
+            }
+        }
+        let (head, value) = line.split_at(split?);
+
+        let mut parts = Vec::new();
+        let mut current = String::new();
+        quoted = false;
+        for c in head.chars() {
+            match c {
+                '"' => quoted = !quoted,
+                ';' if !quoted => parts.push(std::mem::take(&mut current)),
+                c => current.push(c),
+            }
+        }
+        parts.push(current);
+
+        let mut parts = parts.into_iter();
+        let name = parts.next()?.trim().to_ascii_uppercase();
+        if name.is_empty() {
+            return None;
+        }
+        let params = parts
+            .filter_map(|x| x.split_once('=').map(|(key, value)| (key.trim().to_ascii_uppercase(), value.to_string())))
+            .collect();
+
+        Some(ContentLine { name, params, value: value[1..].to_string() })
+    }
+}
+
+/// A `BEGIN:NAME` ... `END:NAME` block with its properties and nested components.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Component {
+    pub name: String,
+    /// Line on which the component begins, for error reporting
+    pub line: usize,
+    pub properties: Vec<ContentLine>,
+    pub components: Vec<Component>,
+}
+
+impl Component {
+    pub fn property(&self, name: &str) -> Option<&ContentLine> {
+        self.properties.iter().find(|x| x.name == name)
+    }
+
+    pub fn properties<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a ContentLine> {
+        self.properties.iter().filter(move |x| x.name == name)
+    }
+}
+
+///parses an iCalendar stream into its top-level components, unfolding continuation lines
+pub fn parse_components(input: &str) -> Result<Vec<Component>, String> {
+    // unfold: a line starting with a space or tab continues the previous one
+    let mut lines: Vec<(usize, String)> = Vec::new();
+    for (number, line) in input.lines().enumerate() {
+        let line = line.trim_end_matches('\r');
+        match (line.strip_prefix(' ').or_else(|| line.strip_prefix('\t')), lines.last_mut()) {
+            (Some(rest), Some((_, previous))) => previous.push_str(rest),
+            _ if line.is_empty() => (),
+            _ => lines.push((number + 1, line.to_string())),
+        }
+    }
+
+    let mut stack: Vec<Component> = Vec::new();
+    let mut finished = Vec::new();
+    for (number, line) in lines {
+        let content = ContentLine::parse(&line).ok_or(format!("line {number}: malformed content line"))?;
+        match content.name.as_str() {
+            "BEGIN" => stack.push(Component {
+                name: content.value.trim().to_ascii_uppercase(),
+                line: number,
+                properties: Vec::new(),
+                components: Vec::new(),
+            }),
+            "END" => {
+                let component = stack.pop().ok_or(format!("line {number}: END without BEGIN"))?;
+                if !component.name.eq_ignore_ascii_case(content.value.trim()) {
+                    return Err(format!("line {number}: END:{} does not close BEGIN:{} from line {}", content.value, component.name, component.line));
+                }
+                match stack.last_mut() {
+                    Some(parent) => parent.components.push(component),
+                    None => finished.push(component),
+                }
+            }
+            _ => match stack.last_mut() {
+                Some(component) => component.properties.push(content),
+                None => return Err(format!("line {number}: property outside of a component")),
+            },
+        }
+    }
+
+    match stack.pop() {
+        Some(component) => Err(format!("line {}: BEGIN:{} is never closed", component.line, component.name)),
+        None => Ok(finished),
+    }
+}
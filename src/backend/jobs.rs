@@ -0,0 +1,143 @@
+use std::sync::Arc;
+use std::time::Duration as StdDuration;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+
+use crate::backend::database::{db_error::DBerror, store::Store};
+use crate::backend::password::random_token;
+use crate::common::model::Job;
+
+/// How often the runner looks for jobs that are due.
+pub const TICK_INTERVAL: StdDuration = StdDuration::from_secs(15);
+
+/// How long a runner holds the lock on a job it started. A job still running by then is assumed to
+/// belong to a runner that died, and is started again elsewhere.
+pub const LOCK_MINUTES: i64 = 10;
+
+/// Wait before retrying a job that failed once, doubled with every further failure.
+pub const FIRST_RETRY_SECONDS: i64 = 30;
+
+/// Longest wait before retrying a failed job.
+pub const MAX_RETRY_SECONDS: i64 = 60 * 60;
+
+pub type JobResult = Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+/// Periodic work the server does in the background.
+#[async_trait]
+pub trait BackgroundJob: Send + Sync {
+    ///name the job is stored under, which has to stay the same across releases
+    fn name(&self) -> &'static str;
+    ///time from the start of a successful run to the start of the next one
+    fn interval(&self) -> Duration;
+    ///does the work due at `now`
+    async fn run(&self, db: &dyn Store, now: &DateTime::<Utc>) -> JobResult;
+}
+
+///the wait before the next attempt after `failures` failed runs in a row
+pub fn retry_delay(failures: u32) -> Duration {
+    let seconds = FIRST_RETRY_SECONDS.saturating_mul(1i64 << failures.saturating_sub(1).min(16));
+    Duration::seconds(seconds.min(MAX_RETRY_SECONDS))
+}
+
+/// Runs background jobs when they are due, keeping their schedule in the store.
+///
+/// Every run of a job is locked in the store first, so several server instances sharing a database
+/// can each have a runner without doing the same work twice.
+pub struct JobRunner {
+    db: Arc<dyn Store>,
+    jobs: Vec<Arc<dyn BackgroundJob>>,
+    /// Identifies this runner's locks
+    runner: String,
+}
+
+impl JobRunner {
+    pub fn new(db: Arc<dyn Store>) -> Self {
+        JobRunner { db, jobs: Vec::new(), runner: random_token() }
+    }
+
+    ///adds a job to run
+    pub fn with(mut self, job: impl BackgroundJob + 'static) -> Self {
+        self.jobs.push(Arc::new(job));
+        self
+    }
+
+    ///runs every job that is due at `now` and not running elsewhere, returning how many ran
+    ///
+    ///a failed job is retried after `retry_delay`, a successful one after its interval. Every job runs
+    ///in a task of its own, so one that panics fails like one returning an error
+    pub async fn run_due(&self, now: &DateTime::<Utc>) -> Result<usize, DBerror> {
+        let mut ran = 0;
+        for job in &self.jobs {
+            self.db.ensure_job(&Job::new(job.name(), now)).await?;
+            let locked_until = *now + Duration::minutes(LOCK_MINUTES);
+            let mut record = match self.db.lock_job(job.name(), &self.runner, now, &locked_until).await? {
+                Some(x) => x,
+                None => continue,
+            };
+            let running = tokio::spawn({
+                let (job, db, now) = (job.clone(), self.db.clone(), *now);
+                async move { job.run(db.as_ref(), &now).await }
+            });
+            let result = match running.await {
+                Ok(x) => x,
+                Err(x) => Err(x.into()),
+            };
+            ran += 1;
+
+            match result {
+                Ok(()) => {
+                    record.failures = 0;
+                    record.last_error = None;
+                    record.next_run = *now + job.interval();
+                }
+                Err(x) => {
+                    log::warn!("job {} failed: {x}", job.name());
+                    record.failures += 1;
+                    record.last_error = Some(x.to_string());
+                    record.next_run = *now + retry_delay(record.failures);
+                }
+            }
+            record.locked_by = None;
+            record.locked_until = None;
+            record.last_finished_at = Some(Utc::now());
+            if !self.db.finish_job(&record, &self.runner).await? {
+                log::warn!("job {} ran past its lock, another runner took it over", job.name());
+            }
+        }
+        Ok(ran)
+    }
+
+    ///looks for due jobs every `TICK_INTERVAL`, for as long as the server runs
+    pub async fn run(self) {
+        let mut interval = actix_web::rt::time::interval(TICK_INTERVAL);
+        loop {
+            interval.tick().await;
+            if let Err(x) = self.run_due(&Utc::now()).await {
+                log::error!("running background jobs failed: {x}");
+            }
+        }
+    }
+}
+
+/// Deletes sessions that have expired.
+pub struct DeleteExpiredSessions;
+
+#[async_trait]
+impl BackgroundJob for DeleteExpiredSessions {
+    fn name(&self) -> &'static str {
+        "delete_expired_sessions"
+    }
+
+    fn interval(&self) -> Duration {
+        Duration::hours(1)
+    }
+
+    async fn run(&self, db: &dyn Store, now: &DateTime::<Utc>) -> JobResult {
+        let deleted = db.delete_expired_sessions(now).await?;
+        if deleted > 0 {
+            log::info!("deleted {deleted} expired sessions");
+        }
+        Ok(())
+    }
+}
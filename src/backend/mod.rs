@@ -1 +1,11 @@
-pub mod database;
\ No newline at end of file
+pub mod database;
+pub mod ical;
+pub mod password;
+#[cfg(feature = "ssr")]
+pub mod auth;
+#[cfg(feature = "ssr")]
+pub mod handlers;
+#[cfg(feature = "ssr")]
+pub mod jobs;
+#[cfg(feature = "ssr")]
+pub mod reminders;
@@ -0,0 +1,81 @@
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::{Algorithm, Argon2, Params, Version};
+use blake2::{Blake2s256, Digest};
+use rand_core::{OsRng, RngCore};
+
+use crate::backend::database::db_error::DBerror;
+
+/// The Argon2 configuration new hashes are made with. Hashes made with anything else are replaced
+/// the next time their password is verified.
+fn hasher() -> Argon2<'static> {
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, Params::default())
+}
+
+///hashes a password with a random salt, returning the PHC string to store
+pub fn hash_password(password: &str) -> Result<String, DBerror> {
+    let salt = SaltString::generate(&mut OsRng);
+    let hash = hasher().hash_password(password.as_bytes(), &salt).map_err(|x| DBerror::PasswordHash(x.to_string()))?;
+    Ok(hash.to_string())
+}
+
+/// Prefix of the passwords stored before they were hashed, which migration 004 kept as the list of
+/// their bytes, e.g. `legacy:[112, 97, 115, 115]`.
+const LEGACY_PREFIX: &str = "legacy:";
+
+///checks a password against a stored PHC string or a password kept from before hashing, a malformed
+///or empty one never matches
+pub fn verify_password(password: &str, hash: &str) -> bool {
+    if let Some(list) = hash.strip_prefix(LEGACY_PREFIX) {
+        return legacy_bytes(list).is_some_and(|x| !x.is_empty() && x == password.as_bytes());
+    }
+    match PasswordHash::new(hash) {
+        Ok(parsed) => Argon2::default().verify_password(password.as_bytes(), &parsed).is_ok(),
+        Err(_) => false,
+    }
+}
+
+///the bytes of a password kept from before hashing, from their list as SurrealQL writes it
+fn legacy_bytes(list: &str) -> Option<Vec<u8>> {
+    let inner = list.strip_prefix('[')?.strip_suffix(']')?;
+    if inner.trim().is_empty() {
+        return Some(Vec::new());
+    }
+    inner.split(',').map(|x| x.trim().parse().ok()).collect()
+}
+
+/// The hash of a password nobody has, made with the parameters `hasher` uses, for logins with an
+/// unknown username to take as long as those with a wrong password.
+pub(crate) const DUMMY_HASH: &str = "$argon2id$v=19$m=19456,t=2,p=1$z0xR2AfX/SBGcltL+MJaPg$YTB9AHuhCPK0BMAnceZHFVH1IdW695UF3GZQKeHUfYk";
+
+///verifies a password against `DUMMY_HASH`, taking as long as checking it for an existing user
+pub fn verify_dummy(password: &str) {
+    verify_password(password, DUMMY_HASH);
+}
+
+///whether a stored hash was made with different parameters than `hash_password` uses now
+pub fn needs_rehash(hash: &str) -> bool {
+    let parsed = match PasswordHash::new(hash) {
+        Ok(parsed) => parsed,
+        Err(_) => return true,
+    };
+    let current = Params::default();
+    parsed.algorithm != Algorithm::Argon2id.ident()
+        || parsed.version != Some(Version::V0x13.into())
+        || Params::try_from(&parsed).map_or(true, |x| (x.m_cost(), x.t_cost(), x.p_cost()) != (current.m_cost(), current.t_cost(), current.p_cost()))
+}
+
+///generates a random secret of 256 bits as a hex string, for session cookies and feed urls
+pub fn random_token() -> String {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    to_hex(&bytes)
+}
+
+///hashes a random token for storage, a fast hash suffices since the token itself has full entropy
+pub fn hash_token(token: &str) -> String {
+    to_hex(&Blake2s256::digest(token.as_bytes()))
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|x| format!("{x:02x}")).collect()
+}
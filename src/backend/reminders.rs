@@ -0,0 +1,293 @@
+use std::collections::HashMap;
+use std::env;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use lettre::message::{header::ContentType, Mailbox};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use surrealdb::sql::Uuid;
+use thiserror::Error;
+
+use crate::backend::database::{db_error::DBerror, store::Store};
+use crate::backend::jobs::{BackgroundJob, JobResult};
+use crate::common::model::{Event, LocalZone, ReminderDelivery, Task, Timespan};
+use crate::common::recurrence::{occurrences, Recurrence};
+
+/// Largest reminder offset accepted, four weeks in minutes.
+pub const MAX_REMINDER_MINUTES: u32 = 4 * 7 * 24 * 60;
+
+/// How long a claimed reminder may stay unsent before another attempt takes it over, in case the
+/// sender died halfway.
+pub const CLAIM_TIMEOUT_MINUTES: i64 = 10;
+
+#[derive(Debug, Error)]
+pub enum ReminderError {
+    #[error(transparent)]
+    Database(#[from] DBerror),
+    #[error("invalid mail configuration: {0}")]
+    InvalidConfig(String),
+    #[error("invalid e-mail address: {0}")]
+    Address(#[from] lettre::address::AddressError),
+    #[error("could not compose the e-mail: {0}")]
+    Message(#[from] lettre::error::Error),
+    #[error("could not send the e-mail: {0}")]
+    Smtp(#[from] lettre::transport::smtp::Error),
+}
+
+/// How the connection to the SMTP server is secured.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SmtpSecurity {
+    /// Plain text, only for a server on the same machine, like a local test sink
+    None,
+    /// Upgrade the connection with `STARTTLS`, refusing servers that do not offer it
+    StartTls,
+    /// TLS from the first byte, usually on port 465
+    Tls,
+}
+
+/// Where reminder e-mails are sent through, and who they are from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SmtpConfig {
+    pub host: String,
+    pub port: u16,
+    pub security: SmtpSecurity,
+    /// User name and password to log in with, if the server wants them
+    pub credentials: Option<(String, String)>,
+    pub from: String,
+}
+
+impl SmtpConfig {
+    ///reads the configuration from the `SMTP_HOST`, `SMTP_PORT`, `SMTP_SECURITY` (`starttls`, `tls` or `none`), `SMTP_USER`, `SMTP_PASS` and `SMTP_FROM` environment variables
+    ///
+    ///`None` when `SMTP_HOST` is not set, then no reminders are sent
+    pub fn from_env() -> Result<Option<Self>, ReminderError> {
+        let host = match env::var("SMTP_HOST") {
+            Ok(host) => host,
+            Err(_) => return Ok(None),
+        };
+        let security = match env::var("SMTP_SECURITY").unwrap_or_else(|_| "starttls".to_string()).as_str() {
+            "starttls" => SmtpSecurity::StartTls,
+            "tls" => SmtpSecurity::Tls,
+            "none" => SmtpSecurity::None,
+            other => return Err(ReminderError::InvalidConfig(format!("unknown SMTP_SECURITY '{other}', expected starttls, tls or none"))),
+        };
+        let port = match env::var("SMTP_PORT") {
+            Ok(port) => port.parse().map_err(|_| ReminderError::InvalidConfig(format!("SMTP_PORT '{port}' is not a port number")))?,
+            Err(_) => match security {
+                SmtpSecurity::None => 25,
+                SmtpSecurity::StartTls => 587,
+                SmtpSecurity::Tls => 465,
+            },
+        };
+        let credentials = match (env::var("SMTP_USER"), env::var("SMTP_PASS")) {
+            (Ok(user), Ok(password)) => Some((user, password)),
+            _ => None,
+        };
+        let from = env::var("SMTP_FROM").map_err(|_| ReminderError::InvalidConfig("SMTP_FROM is not set".to_string()))?;
+        Ok(Some(SmtpConfig { host, port, security, credentials, from }))
+    }
+}
+
+/// Sends e-mails over SMTP.
+pub struct Mailer {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+}
+
+impl Mailer {
+    pub fn new(config: &SmtpConfig) -> Result<Self, ReminderError> {
+        let builder = match config.security {
+            SmtpSecurity::None => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&config.host),
+            SmtpSecurity::StartTls => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&config.host)?,
+            SmtpSecurity::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(&config.host)?,
+        };
+        let builder = builder.port(config.port);
+        let builder = match &config.credentials {
+            Some((user, password)) => builder.credentials(Credentials::new(user.clone(), password.clone())),
+            None => builder,
+        };
+        Ok(Mailer { transport: builder.build(), from: config.from.parse()? })
+    }
+
+    ///sends a plain text e-mail
+    pub async fn send(&self, to: &str, subject: &str, body: String) -> Result<(), ReminderError> {
+        let message = Message::builder()
+            .from(self.from.clone())
+            .to(to.parse()?)
+            .subject(subject)
+            .header(ContentType::TEXT_PLAIN)
+            .body(body)?;
+        self.transport.send(message).await?;
+        Ok(())
+    }
+}
+
+/// One reminder that has to go out.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DueReminder {
+    pub item: Uuid,
+    pub user: Uuid,
+    /// Name of the occurrence, which an override may have changed
+    pub name: String,
+    /// Whether the reminder is for a task, which is due at `occurs_at`, rather than an event starting then
+    pub task: bool,
+    /// Whether the occurrence covers whole dates, so that the message leaves out the time
+    pub all_day: bool,
+    pub occurs_at: DateTime::<Utc>,
+    pub minutes_before: u32,
+}
+
+impl DueReminder {
+    ///the record claiming this reminder at `now`
+    pub fn delivery(&self, now: &DateTime::<Utc>) -> ReminderDelivery {
+        ReminderDelivery {
+            key: ReminderDelivery::key_for(&self.item, &self.occurs_at, self.minutes_before),
+            item: self.item.clone(),
+            user: self.user.clone(),
+            occurs_at: self.occurs_at,
+            minutes_before: self.minutes_before,
+            claimed_at: *now,
+            sent_at: None,
+        }
+    }
+
+    ///subject and body of the e-mail, with the time as the clock shows it in `zone`
+    pub fn message(&self, zone: &LocalZone) -> (String, String) {
+        let subject = format!("Reminder: {}", self.name);
+        let body = match (self.all_day, self.task) {
+            // an all-day task is due at the midnight ending its last date
+            (true, true) => format!("{} is due by the end of {}.\n", self.name, zone.date_of(&(self.occurs_at - Duration::seconds(1))).format("%A %B %-d, %Y")),
+            (true, false) => format!("{} starts on {}.\n", self.name, zone.date_of(&self.occurs_at).format("%A %B %-d, %Y")),
+            (false, task) => {
+                let what = if task { "is due" } else { "starts" };
+                format!("{} {} at {}.\n", self.name, what, zone.local(&self.occurs_at).format("%A %B %-d, %Y %H:%M %Z"))
+            }
+        };
+        (subject, body)
+    }
+}
+
+///the reminders of one item that are due at `now`: those whose time has come for an occurrence that has not happened yet
+///
+///reminders that should have gone out while the server was down are still due, as long as their occurrence lies ahead.
+///all-day occurrences start and end at midnight in `zone`
+fn due_for(name: &str, timespan: &Timespan, recurrence: Option<&Recurrence>, reminders: &[u32], task: bool, zone: &LocalZone, now: &DateTime::<Utc>) -> Vec<(String, DateTime::<Utc>, u32)> {
+    let longest = reminders.iter().copied().max().unwrap_or(0);
+    // the dates of all-day items are kept as midnight UTC, up to a day away from midnight in `zone`
+    let margin = if timespan.all_day { Duration::days(1) } else { Duration::zero() };
+    let range = Timespan {
+        start: *now - margin,
+        end: *now + Duration::minutes(i64::from(longest) + 1) + margin,
+        all_day: false,
+    };
+    let mut due = Vec::new();
+    for occurrence in occurrences(timespan, recurrence, &range) {
+        let resolved = zone.resolve(&occurrence.timespan);
+        let occurs_at = if task { resolved.end } else { resolved.start };
+        if occurs_at <= *now {
+            continue;
+        }
+        for minutes in reminders {
+            if occurs_at - Duration::minutes(i64::from(*minutes)) <= *now {
+                due.push((occurrence.name.clone().unwrap_or_else(|| name.to_string()), occurs_at, *minutes));
+            }
+        }
+    }
+    due
+}
+
+///the reminders of a task of a user in `zone` that are due at `now`
+pub fn task_reminders(task: &Task, zone: &LocalZone, now: &DateTime::<Utc>) -> Vec<DueReminder> {
+    let all_day = task.timespan.all_day;
+    due_for(&task.name, &task.timespan, task.recurrence.as_ref(), &task.reminders, true, zone, now)
+        .into_iter()
+        .map(|(name, occurs_at, minutes_before)| DueReminder { item: task.uuid.clone(), user: task.user.clone(), name, task: true, all_day, occurs_at, minutes_before })
+        .collect()
+}
+
+///the reminders of an event of a user in `zone` that are due at `now`
+pub fn event_reminders(event: &Event, zone: &LocalZone, now: &DateTime::<Utc>) -> Vec<DueReminder> {
+    let all_day = event.timespan.all_day;
+    due_for(&event.name, &event.timespan, event.recurrence.as_ref(), &event.reminders, false, zone, now)
+        .into_iter()
+        .map(|(name, occurs_at, minutes_before)| DueReminder { item: event.uuid.clone(), user: event.user.clone(), name, task: false, all_day, occurs_at, minutes_before })
+        .collect()
+}
+
+///the e-mail address and time zone of a user, looked up once per run
+async fn recipient(db: &dyn Store, recipients: &mut HashMap<Uuid, (Option<String>, LocalZone)>, user: &Uuid) -> Result<(Option<String>, LocalZone), DBerror> {
+    if let Some(x) = recipients.get(user) {
+        return Ok(x.clone());
+    }
+    let user = db.get_user(user).await?;
+    recipients.insert(user.uuid.clone(), (user.email.clone(), user.time_zone));
+    Ok((user.email, user.time_zone))
+}
+
+///sends every reminder that is due at `now` and was not sent before, returning how many went out
+///
+///each reminder is claimed in the store before it is sent and released again when sending fails, so it is tried again on the next run
+pub async fn send_due_reminders(db: &dyn Store, mailer: &Mailer, now: &DateTime::<Utc>) -> Result<usize, ReminderError> {
+    let mut recipients = HashMap::new();
+    let mut due = Vec::new();
+    for task in db.get_tasks_with_reminders(now).await? {
+        let zone = recipient(db, &mut recipients, &task.user).await?.1;
+        due.extend(task_reminders(&task, &zone, now));
+    }
+    for event in db.get_events_with_reminders(now).await? {
+        let zone = recipient(db, &mut recipients, &event.user).await?.1;
+        due.extend(event_reminders(&event, &zone, now));
+    }
+
+    let stale_before = *now - Duration::minutes(CLAIM_TIMEOUT_MINUTES);
+    let mut sent = 0;
+    for reminder in due {
+        let (address, zone) = recipient(db, &mut recipients, &reminder.user).await?;
+        let address = match address {
+            Some(x) => x,
+            None => continue,
+        };
+
+        let delivery = reminder.delivery(now);
+        if !db.claim_reminder(&delivery, &stale_before).await? {
+            continue;
+        }
+        let (subject, body) = reminder.message(&zone);
+        match mailer.send(&address, &subject, body).await {
+            Ok(()) => {
+                db.finish_reminder(&delivery.key, &Utc::now()).await?;
+                sent += 1;
+            }
+            Err(x) => {
+                log::warn!("could not send reminder {}: {x}", delivery.key);
+                db.release_reminder(&delivery.key).await?;
+            }
+        }
+    }
+    Ok(sent)
+}
+
+/// Sends the reminders that are due, as a background job.
+pub struct SendReminders {
+    pub mailer: Mailer,
+}
+
+#[async_trait]
+impl BackgroundJob for SendReminders {
+    fn name(&self) -> &'static str {
+        "send_reminders"
+    }
+
+    fn interval(&self) -> Duration {
+        Duration::minutes(1)
+    }
+
+    async fn run(&self, db: &dyn Store, now: &DateTime::<Utc>) -> JobResult {
+        let sent = send_due_reminders(db, &self.mailer, now).await?;
+        if sent > 0 {
+            log::info!("sent {sent} reminders");
+        }
+        Ok(())
+    }
+}
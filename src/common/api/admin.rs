@@ -0,0 +1,21 @@
+use leptos::*;
+
+use crate::common::api::ApiResult;
+use crate::common::model::Job;
+#[cfg(feature = "ssr")]
+use crate::common::api::Caller;
+
+#[cfg(feature = "ssr")]
+pub(crate) fn register() {
+    _ = ListJobs::register();
+}
+
+///every background job with its schedule and the outcome of its last run, for admins only
+#[server(ListJobs, "/api", "Cbor")]
+pub async fn list_jobs(_cx: Scope) -> Result<ApiResult<Vec<Job>>, ServerFnError> {
+    Ok(async {
+        let caller = Caller::get(_cx).await?;
+        caller.admin().await?;
+        Ok(caller.db.get_jobs().await?)
+    }.await)
+}
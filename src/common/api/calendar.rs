@@ -0,0 +1,173 @@
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use leptos::*;
+use serde::{Deserialize, Serialize};
+use surrealdb::sql::Uuid;
+
+use crate::common::api::ApiResult;
+use crate::common::model::{LocalZone, Timespan};
+#[cfg(feature = "ssr")]
+use crate::common::api::Caller;
+#[cfg(feature = "ssr")]
+use crate::common::model::UNCATEGORISED_COLOR;
+
+#[cfg(feature = "ssr")]
+pub(crate) fn register() {
+    _ = ListItems::register();
+    _ = ListDayActivity::register();
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum ItemKind {
+    Event,
+    Task { completed: bool },
+}
+
+///one occurrence of an event or task, with what a calendar view needs to draw it
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct CalendarItem {
+    pub kind: ItemKind,
+    ///the event or task this is an occurrence of
+    pub uuid: Uuid,
+    ///the start the recurrence rule gave this occurrence, `None` if the item does not recur
+    pub recurrence_id: Option<DateTime::<Utc>>,
+    pub name: String,
+    ///the instants the occurrence takes up in the user's time zone
+    pub timespan: Timespan,
+    ///whether the occurrence covers whole dates, which `timespan` then runs from and to midnight of
+    pub all_day: bool,
+    pub category: Uuid,
+    ///the colour of the category, as `0xRRGGBB`
+    pub color: u32,
+}
+
+impl CalendarItem {
+    ///the colour as a CSS hex colour
+    pub fn css_color(&self) -> String {
+        format!("#{:06X}", self.color & 0xFFFFFF)
+    }
+}
+
+#[cfg(feature = "ssr")]
+impl Caller {
+    ///every occurrence of the logged-in user's events and tasks overlapping `range`, ordered by start
+    ///
+    ///all-day occurrences are given the midnights of their dates in the user's time zone
+    pub async fn items(&self, range: &Timespan) -> ApiResult<Vec<CalendarItem>> {
+        use std::collections::HashMap;
+
+        let zone = self.zone().await?;
+        let mut colors = HashMap::new();
+        for id in self.db.get_categories(&self.user).await? {
+            if let Ok(category) = self.db.get_category(&id).await {
+                colors.insert(category.uuid, category.color);
+            }
+        }
+        let color = |category: &Uuid| colors.get(category).copied().unwrap_or(UNCATEGORISED_COLOR);
+
+        let mut items = Vec::new();
+        for event in self.db.get_events_in_range(&self.user, range).await? {
+            for occurrence in event.occurrences(range) {
+                items.push(CalendarItem {
+                    kind: ItemKind::Event,
+                    uuid: event.uuid.clone(),
+                    recurrence_id: occurrence.recurrence_id,
+                    name: occurrence.name.unwrap_or_else(|| event.name.clone()),
+                    timespan: zone.resolve(&occurrence.timespan),
+                    all_day: occurrence.timespan.all_day,
+                    category: event.category.clone(),
+                    color: color(&event.category),
+                });
+            }
+        }
+        for task in self.db.get_tasks_in_range(&self.user, range).await? {
+            for occurrence in task.occurrences(range) {
+                items.push(CalendarItem {
+                    kind: ItemKind::Task { completed: task.completed },
+                    uuid: task.uuid.clone(),
+                    recurrence_id: occurrence.recurrence_id,
+                    name: occurrence.name.unwrap_or_else(|| task.name.clone()),
+                    timespan: zone.resolve(&occurrence.timespan),
+                    all_day: occurrence.timespan.all_day,
+                    category: task.category.clone(),
+                    color: color(&task.category),
+                });
+            }
+        }
+        // dates found in range in UTC can lie just outside it in the user's zone
+        items.retain(|x| x.timespan.overlaps(range));
+        // the sort is stable, so events stay ahead of tasks with the same start
+        items.sort_by_key(|x| x.timespan.start);
+        Ok(items)
+    }
+}
+
+///every occurrence of the logged-in user's events and tasks overlapping `start..end`, ordered by start
+///
+///events come before tasks starting at the same time
+#[server(ListItems, "/api", "Cbor")]
+pub async fn list_items(_cx: Scope, start: DateTime::<Utc>, end: DateTime::<Utc>) -> Result<ApiResult<Vec<CalendarItem>>, ServerFnError> {
+    Ok(async {
+        let caller = Caller::get(_cx).await?;
+        caller.items(&Timespan::new(&start, &end)?).await
+    }.await)
+}
+
+///how busy a day is
+#[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq)]
+pub struct DayActivity {
+    ///number of occurrences of events and tasks on the day
+    pub count: usize,
+    ///colour of the category most of them belong to, `None` on a day without any
+    pub color: Option<u32>,
+}
+
+///the activity of `days` consecutive days in `zone`, the first being `first`
+///
+///an item counts towards every day it overlaps; when categories are tied, the one whose item comes
+///first decides the colour
+pub fn day_activity(items: &[CalendarItem], first: &NaiveDate, days: usize, zone: &LocalZone) -> Vec<DayActivity> {
+    // the categories of every day in order of appearance, with their colour and number of items
+    let mut categories: Vec<Vec<(&Uuid, u32, usize)>> = vec![Vec::new(); days];
+    let range = zone.dates_span(first, &(*first + Duration::days(days as i64 - 1)));
+    for item in items {
+        let parts = item.timespan.intersection(&range).map(|x| x.split_days(zone)).unwrap_or_default();
+        for (date, _) in parts {
+            if let Some(day) = usize::try_from((date - *first).num_days()).ok().and_then(|n| categories.get_mut(n)) {
+                match day.iter_mut().find(|x| *x.0 == item.category) {
+                    Some(entry) => entry.2 += 1,
+                    None => day.push((&item.category, item.color, 1)),
+                }
+            }
+        }
+    }
+    categories.iter().map(|day| {
+        // max_by_key keeps the last of equal elements, so go through them backwards
+        let dominant = day.iter().rev().max_by_key(|x| x.2);
+        DayActivity {
+            count: day.iter().map(|x| x.2).sum(),
+            color: dominant.map(|x| x.1),
+        }
+    }).collect()
+}
+
+///the activity of the logged-in user's `days` consecutive days, the first being `first`, with the
+///days starting and ending at midnight in the user's time zone
+#[server(ListDayActivity, "/api", "Cbor")]
+pub async fn list_day_activity(_cx: Scope, first: NaiveDate, days: u32) -> Result<ApiResult<Vec<DayActivity>>, ServerFnError> {
+    use crate::common::api::ApiError;
+
+    Ok(async {
+        let caller = Caller::get(_cx).await?;
+        if days > 366 {
+            return Err(ApiError::Invalid("at most a year of activity can be asked for at once".to_string()));
+        }
+        // a day to spare either side, the zone's offset can take the range's instants past the dates
+        if first.pred_opt().and_then(|x| x.checked_add_signed(Duration::days(i64::from(days) + 2))).is_none() {
+            return Err(ApiError::Invalid("those dates are out of range".to_string()));
+        }
+        let zone = caller.zone().await?;
+        let range = zone.dates_span(&first, &(first + Duration::days(i64::from(days) - 1)));
+        let items = caller.items(&range).await?;
+        Ok(day_activity(&items, &first, days as usize, &zone))
+    }.await)
+}
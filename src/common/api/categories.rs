@@ -0,0 +1,89 @@
+use leptos::*;
+use surrealdb::sql::Uuid;
+
+use crate::common::api::ApiResult;
+use crate::common::model::Category;
+#[cfg(feature = "ssr")]
+use crate::common::api::{check_name, Caller};
+
+#[cfg(feature = "ssr")]
+pub(crate) fn register() {
+    _ = ListCategories::register();
+    _ = AddCategory::register();
+    _ = EditCategory::register();
+    _ = DeleteCategory::register();
+    _ = SetCategoryHidden::register();
+}
+
+///the logged-in user's categories
+#[server(ListCategories, "/api", "Cbor")]
+pub async fn list_categories(_cx: Scope) -> Result<ApiResult<Vec<Category>>, ServerFnError> {
+    use crate::backend::database::db_error::DBerror;
+    Ok(async {
+        let caller = Caller::get(_cx).await?;
+        let mut categories = Vec::new();
+        for id in caller.db.get_categories(&caller.user).await? {
+            // users from before categories were unlinked on deletion can still list deleted ones
+            match caller.db.get_category(&id).await {
+                Ok(category) => categories.push(category),
+                Err(DBerror::CategoryNotFound(_)) => (),
+                Err(x) => return Err(x.into()),
+            }
+        }
+        Ok(categories)
+    }.await)
+}
+
+///adds a category for the logged-in user
+#[server(AddCategory, "/api", "Cbor")]
+pub async fn add_category(_cx: Scope, name: String, color: u32) -> Result<ApiResult<Category>, ServerFnError> {
+    Ok(async {
+        let caller = Caller::get(_cx).await?;
+        check_name(&name)?;
+        Ok(caller.db.add_category(&name, color, &caller.user).await?)
+    }.await)
+}
+
+///renames and recolours one of the logged-in user's categories
+#[server(EditCategory, "/api", "Cbor")]
+pub async fn edit_category(_cx: Scope, category: Uuid, name: String, color: u32) -> Result<ApiResult<Category>, ServerFnError> {
+    Ok(async {
+        let caller = Caller::get(_cx).await?;
+        check_name(&name)?;
+        let stored = caller.owned_category(&category).await?;
+        if stored.name != name {
+            caller.db.category_edit_name(&category, &name).await?;
+        }
+        if stored.color != color {
+            caller.db.category_change_color(&category, color).await?;
+        }
+        Ok(caller.db.get_category(&category).await?)
+    }.await)
+}
+
+///hides or shows the items of one of the logged-in user's categories in the calendar views
+#[server(SetCategoryHidden, "/api", "Cbor")]
+pub async fn set_category_hidden(_cx: Scope, category: Uuid, hidden: bool) -> Result<ApiResult<Category>, ServerFnError> {
+    Ok(async {
+        let caller = Caller::get(_cx).await?;
+        caller.owned_category(&category).await?;
+        Ok(caller.db.category_set_hidden(&category, hidden).await?)
+    }.await)
+}
+
+///deletes one of the logged-in user's categories, moving its items to `replacement`
+///
+///without a replacement the items go to the user's "Uncategorised" category, which is added when
+///the user has none
+#[server(DeleteCategory, "/api", "Cbor")]
+pub async fn delete_category(_cx: Scope, category: Uuid, replacement: Option<Uuid>) -> Result<ApiResult<Category>, ServerFnError> {
+    Ok(async {
+        let caller = Caller::get(_cx).await?;
+        caller.owned_category(&category).await?;
+        let replacement = match replacement {
+            Some(replacement) => caller.owned_category(&replacement).await?,
+            None => caller.db.uncategorised_category(&caller.user).await?,
+        };
+        Ok(caller.db.delete_category(&category, &replacement.uuid).await?)
+    }.await)
+}
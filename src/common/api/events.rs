@@ -0,0 +1,90 @@
+use chrono::{DateTime, Utc};
+use leptos::*;
+use surrealdb::sql::Uuid;
+
+use crate::common::api::ApiResult;
+use crate::common::model::{Event, Timespan};
+use crate::common::recurrence::Recurrence;
+#[cfg(feature = "ssr")]
+use crate::common::api::{check_name, check_reminders, ApiError, Caller};
+
+#[cfg(feature = "ssr")]
+pub(crate) fn register() {
+    _ = ListEvents::register();
+    _ = AddEvent::register();
+    _ = EditEvent::register();
+    _ = SetEventReminders::register();
+    _ = DeleteEvent::register();
+}
+
+#[cfg(feature = "ssr")]
+impl Caller {
+    ///checks that an event exists and belongs to the logged-in user
+    pub async fn owned_event(&self, event: &Uuid) -> ApiResult<Event> {
+        use crate::backend::database::db_error::DBerror;
+        match self.db.get_event(event).await {
+            Ok(x) if x.user == self.user => Ok(x),
+            Ok(_) | Err(DBerror::EventNotFound(_)) => Err(ApiError::NotFound("event".to_string())),
+            Err(x) => Err(x.into()),
+        }
+    }
+}
+
+///the logged-in user's events overlapping `start..end`, recurring ones included, ordered by start
+#[server(ListEvents, "/api", "Cbor")]
+pub async fn list_events(_cx: Scope, start: DateTime::<Utc>, end: DateTime::<Utc>) -> Result<ApiResult<Vec<Event>>, ServerFnError> {
+    Ok(async {
+        let caller = Caller::get(_cx).await?;
+        Ok(caller.db.get_events_in_range(&caller.user, &Timespan::new(&start, &end)?).await?)
+    }.await)
+}
+
+///adds an event for the logged-in user, timed or on whole dates
+#[server(AddEvent, "/api", "Cbor")]
+pub async fn add_event(_cx: Scope, name: String, description: String, timespan: Timespan, category: Uuid, recurrence: Option<Recurrence>) -> Result<ApiResult<Event>, ServerFnError> {
+    Ok(async {
+        let caller = Caller::get(_cx).await?;
+        check_name(&name)?;
+        caller.owned_category(&category).await?;
+        Ok(caller.db.add_event(&name, &description, &timespan, &category, recurrence, &caller.user).await?)
+    }.await)
+}
+
+///replaces the editable fields of one of the logged-in user's events
+#[server(EditEvent, "/api", "Cbor")]
+pub async fn edit_event(_cx: Scope, event: Uuid, name: String, description: String, timespan: Timespan, category: Uuid, recurrence: Option<Recurrence>) -> Result<ApiResult<Event>, ServerFnError> {
+    Ok(async {
+        let caller = Caller::get(_cx).await?;
+        check_name(&name)?;
+        timespan.validate()?;
+        let mut stored = caller.owned_event(&event).await?;
+        caller.owned_category(&category).await?;
+        stored.name = name;
+        stored.description = description;
+        stored.timespan = timespan;
+        stored.category = category;
+        stored.recurrence = recurrence;
+        Ok(caller.db.put_event(&stored).await?)
+    }.await)
+}
+
+///sets when the logged-in user is reminded of one of their events, in minutes before its start
+#[server(SetEventReminders, "/api", "Cbor")]
+pub async fn set_event_reminders(_cx: Scope, event: Uuid, reminders: Vec<u32>) -> Result<ApiResult<Event>, ServerFnError> {
+    Ok(async {
+        let caller = Caller::get(_cx).await?;
+        let reminders = check_reminders(reminders)?;
+        caller.owned_event(&event).await?;
+        Ok(caller.db.event_set_reminders(&event, reminders).await?)
+    }.await)
+}
+
+///deletes one of the logged-in user's events
+#[server(DeleteEvent, "/api", "Cbor")]
+pub async fn delete_event(_cx: Scope, event: Uuid) -> Result<ApiResult<Event>, ServerFnError> {
+    Ok(async {
+        let caller = Caller::get(_cx).await?;
+        caller.owned_event(&event).await?;
+        Ok(caller.db.delete_event(&event).await?)
+    }.await)
+}
@@ -0,0 +1,202 @@
+//! Server functions the frontend reads and writes data through.
+//!
+//! Every function works on the data of the logged-in user only, found through the session cookie,
+//! and never takes a user id from the client. Failures the frontend can act on come back as an
+//! `ApiError` inside the `Ok` of the server function, because leptos reduces a `ServerFnError`
+//! to its status text on the way to the browser.
+//!
+//! The scope of a server function is called `_cx` because its body is left out of the client
+//! build, where the scope would otherwise be an unused variable.
+
+use chrono::{DateTime, Utc};
+use leptos::*;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::common::model::{LocalZone, TimespanError, WeekStart};
+use crate::common::recurrence::RecurrenceError;
+
+pub mod admin;
+pub mod calendar;
+pub mod categories;
+pub mod events;
+pub mod settings;
+pub mod tasks;
+
+/// An error from a server function, in a form that survives the trip to the browser.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Error)]
+pub enum ApiError {
+    #[error("not logged in")]
+    NotLoggedIn,
+    /// The logged-in user may not do this
+    #[error("not allowed")]
+    NotAllowed,
+    /// The item does not exist, or belongs to another user
+    #[error("{0} not found")]
+    NotFound(String),
+    #[error("invalid input: {0}")]
+    Invalid(String),
+    #[error("server error: {0}")]
+    Server(String),
+    /// The server could not be reached, or its answer could not be read
+    #[error("request failed: {0}")]
+    Request(String),
+}
+
+pub type ApiResult<T> = Result<T, ApiError>;
+
+impl From<ServerFnError> for ApiError {
+    fn from(error: ServerFnError) -> Self {
+        ApiError::Request(error.to_string())
+    }
+}
+
+impl From<TimespanError> for ApiError {
+    fn from(error: TimespanError) -> Self {
+        ApiError::Invalid(error.to_string())
+    }
+}
+
+impl From<RecurrenceError> for ApiError {
+    fn from(error: RecurrenceError) -> Self {
+        ApiError::Invalid(error.to_string())
+    }
+}
+
+///merges the transport error of a server function call into its `ApiResult`
+pub fn flatten<T>(result: Result<ApiResult<T>, ServerFnError>) -> ApiResult<T> {
+    result.map_err(ApiError::from).and_then(|x| x)
+}
+
+#[cfg(feature = "ssr")]
+impl From<crate::backend::database::db_error::DBerror> for ApiError {
+    fn from(error: crate::backend::database::db_error::DBerror) -> Self {
+        use crate::backend::database::db_error::DBerror;
+        match error {
+            DBerror::SessionNotFound => ApiError::NotLoggedIn,
+            DBerror::UserNotFound(_) | DBerror::UserNameNotFound(_) => ApiError::NotFound("user".to_string()),
+            DBerror::TaskNotFound(_) => ApiError::NotFound("task".to_string()),
+            DBerror::EventNotFound(_) => ApiError::NotFound("event".to_string()),
+            DBerror::CategoryNotFound(_) => ApiError::NotFound("category".to_string()),
+            DBerror::UserAlreadyExists(name) => ApiError::Invalid(format!("username {name} is taken")),
+            DBerror::InvalidTimespan(x) => x.into(),
+            DBerror::InvalidRecurrence(x) => x.into(),
+            DBerror::InvalidReplacement(_) => ApiError::Invalid("the items of a category need another category to move to".to_string()),
+            x => {
+                log::error!("server function failed: {x}");
+                ApiError::Server(x.to_string())
+            }
+        }
+    }
+}
+
+/// The store and the logged-in user, as every server function needs them.
+#[cfg(feature = "ssr")]
+pub(crate) struct Caller {
+    pub db: actix_web::web::Data<dyn crate::backend::database::store::Store>,
+    pub user: surrealdb::sql::Uuid,
+}
+
+#[cfg(feature = "ssr")]
+impl Caller {
+    pub async fn get(cx: Scope) -> ApiResult<Self> {
+        use crate::backend::auth::{current_user, store};
+        Ok(Caller { db: store(cx)?, user: current_user(cx).await?.uuid })
+    }
+
+    ///the time zone the logged-in user's calendar is shown in
+    pub async fn zone(&self) -> ApiResult<LocalZone> {
+        Ok(self.db.get_user(&self.user).await?.time_zone)
+    }
+
+    ///checks that the logged-in user is an admin
+    pub async fn admin(&self) -> ApiResult<()> {
+        let user = self.db.get_user(&self.user).await?;
+        if !crate::backend::auth::is_admin(&user.name) {
+            return Err(ApiError::NotAllowed);
+        }
+        Ok(())
+    }
+
+    ///checks that a category exists and belongs to the logged-in user
+    pub async fn owned_category(&self, category: &surrealdb::sql::Uuid) -> ApiResult<crate::common::model::Category> {
+        match self.db.get_category(category).await {
+            Ok(x) if x.user == self.user => Ok(x),
+            Ok(_) | Err(crate::backend::database::db_error::DBerror::CategoryNotFound(_)) => Err(ApiError::NotFound("category".to_string())),
+            Err(x) => Err(x.into()),
+        }
+    }
+}
+
+///refuses an empty name
+#[cfg(feature = "ssr")]
+pub(crate) fn check_name(name: &str) -> ApiResult<()> {
+    if name.trim().is_empty() {
+        return Err(ApiError::Invalid("the name is empty".to_string()));
+    }
+    Ok(())
+}
+
+///refuses reminders that are further ahead than `MAX_REMINDER_MINUTES`, sorting the others and dropping duplicates
+#[cfg(feature = "ssr")]
+pub(crate) fn check_reminders(mut reminders: Vec<u32>) -> ApiResult<Vec<u32>> {
+    use crate::backend::reminders::MAX_REMINDER_MINUTES;
+    if reminders.iter().any(|x| *x > MAX_REMINDER_MINUTES) {
+        return Err(ApiError::Invalid(format!("reminders can be at most {} days ahead", MAX_REMINDER_MINUTES / (24 * 60))));
+    }
+    reminders.sort_unstable();
+    reminders.dedup();
+    Ok(reminders)
+}
+
+///registers every server function with leptos, which has to happen explicitly outside of wasm
+#[cfg(feature = "ssr")]
+pub fn register_server_functions() {
+    _ = GetCurrentUser::register();
+    calendar::register();
+    tasks::register();
+    events::register();
+    categories::register();
+    settings::register();
+    admin::register();
+}
+
+/// What the frontend gets to know about the logged-in user.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct UserInfo {
+    pub name: String,
+    pub joined_at: DateTime::<Utc>,
+    /// Path of the user's calendar feed, secret because it works without logging in
+    pub feed_url: String,
+    pub week_start: WeekStart,
+    pub time_zone: LocalZone,
+    /// Address reminders are sent to
+    pub email: Option<String>,
+    /// Whether the user may see the admin pages
+    pub admin: bool,
+}
+
+///the logged-in user, `None` if the request has no valid session
+#[server(GetCurrentUser, "/api")]
+pub async fn get_current_user(_cx: Scope) -> Result<Option<UserInfo>, ServerFnError> {
+    use crate::backend::auth::{current_user, is_admin, store};
+    use crate::backend::database::db_error::DBerror;
+
+    let user = match current_user(_cx).await {
+        Ok(user) => user,
+        Err(DBerror::SessionNotFound) => return Ok(None),
+        Err(x) => return Err(ServerFnError::ServerError(x.to_string())),
+    };
+    let db = store(_cx).map_err(|x| ServerFnError::ServerError(x.to_string()))?;
+    let user = db.get_user(&user.uuid).await.map_err(|x| ServerFnError::ServerError(x.to_string()))?;
+
+    Ok(Some(UserInfo {
+        admin: is_admin(&user.name),
+        name: user.name,
+        joined_at: user.joined_at,
+        feed_url: format!("/calendar/{}.ics", user.feed_token),
+        week_start: user.week_start,
+        time_zone: user.time_zone,
+        email: user.email,
+    }))
+}
@@ -0,0 +1,47 @@
+use leptos::*;
+
+use crate::common::api::ApiResult;
+use crate::common::model::{LocalZone, WeekStart};
+#[cfg(feature = "ssr")]
+use crate::common::api::{ApiError, Caller};
+
+#[cfg(feature = "ssr")]
+pub(crate) fn register() {
+    _ = SetWeekStart::register();
+    _ = SetTimeZone::register();
+    _ = SetEmail::register();
+}
+
+///changes the day the logged-in user's weeks start on
+#[server(SetWeekStart, "/api", "Cbor")]
+pub async fn set_week_start(_cx: Scope, week_start: WeekStart) -> Result<ApiResult<WeekStart>, ServerFnError> {
+    Ok(async {
+        let caller = Caller::get(_cx).await?;
+        Ok(caller.db.set_week_start(&caller.user, week_start).await?.week_start)
+    }.await)
+}
+
+///changes the time zone the logged-in user's calendar is shown in, given by its IANA name such as `Europe/Berlin`
+#[server(SetTimeZone, "/api", "Cbor")]
+pub async fn set_time_zone(_cx: Scope, time_zone: String) -> Result<ApiResult<LocalZone>, ServerFnError> {
+    Ok(async {
+        let caller = Caller::get(_cx).await?;
+        let zone = time_zone.trim().parse().map_err(|_| ApiError::Invalid(format!("unknown time zone '{}'", time_zone.trim())))?;
+        Ok(caller.db.set_time_zone(&caller.user, LocalZone(zone)).await?.time_zone)
+    }.await)
+}
+
+///changes the address the logged-in user's reminders are sent to, an empty one stops them
+#[server(SetEmail, "/api", "Cbor")]
+pub async fn set_email(_cx: Scope, email: String) -> Result<ApiResult<Option<String>>, ServerFnError> {
+    Ok(async {
+        let caller = Caller::get(_cx).await?;
+        let email = email.trim();
+        let email = if email.is_empty() {
+            None
+        } else {
+            Some(email.parse::<lettre::Address>().map_err(|x| ApiError::Invalid(x.to_string()))?.to_string())
+        };
+        Ok(caller.db.set_email(&caller.user, email).await?.email)
+    }.await)
+}
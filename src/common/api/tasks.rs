@@ -0,0 +1,213 @@
+use chrono::{DateTime, Utc};
+use leptos::*;
+use serde::{Deserialize, Serialize};
+use surrealdb::sql::Uuid;
+
+use crate::common::api::ApiResult;
+use crate::common::model::{LocalZone, Task, Timespan};
+use crate::common::recurrence::Recurrence;
+#[cfg(feature = "ssr")]
+use crate::common::api::{check_name, check_reminders, ApiError, Caller};
+
+#[cfg(feature = "ssr")]
+pub(crate) fn register() {
+    _ = ListTasks::register();
+    _ = AddTask::register();
+    _ = EditTask::register();
+    _ = SetTaskCompletion::register();
+    _ = SetTaskReminders::register();
+    _ = DeleteTask::register();
+    _ = ListOpenTasks::register();
+    _ = CompleteTasks::register();
+    _ = RescheduleTasks::register();
+}
+
+#[cfg(feature = "ssr")]
+impl Caller {
+    ///checks that a task exists and belongs to the logged-in user
+    pub async fn owned_task(&self, task: &Uuid) -> ApiResult<Task> {
+        use crate::backend::database::db_error::DBerror;
+        match self.db.get_task(task).await {
+            Ok(x) if x.user == self.user => Ok(x),
+            Ok(_) | Err(DBerror::TaskNotFound(_)) => Err(ApiError::NotFound("task".to_string())),
+            Err(x) => Err(x.into()),
+        }
+    }
+}
+
+///the logged-in user's tasks overlapping `start..end`, recurring ones included, ordered by start
+#[server(ListTasks, "/api", "Cbor")]
+pub async fn list_tasks(_cx: Scope, start: DateTime::<Utc>, end: DateTime::<Utc>) -> Result<ApiResult<Vec<Task>>, ServerFnError> {
+    Ok(async {
+        let caller = Caller::get(_cx).await?;
+        Ok(caller.db.get_tasks_in_range(&caller.user, &Timespan::new(&start, &end)?).await?)
+    }.await)
+}
+
+///adds a task for the logged-in user, timed or on whole dates
+#[server(AddTask, "/api", "Cbor")]
+pub async fn add_task(_cx: Scope, name: String, description: String, timespan: Timespan, category: Uuid, recurrence: Option<Recurrence>) -> Result<ApiResult<Task>, ServerFnError> {
+    Ok(async {
+        let caller = Caller::get(_cx).await?;
+        check_name(&name)?;
+        caller.owned_category(&category).await?;
+        Ok(caller.db.add_task(&name, &description, &timespan, &category, recurrence, &caller.user).await?)
+    }.await)
+}
+
+///replaces the editable fields of one of the logged-in user's tasks
+#[server(EditTask, "/api", "Cbor")]
+pub async fn edit_task(_cx: Scope, task: Uuid, name: String, description: String, timespan: Timespan, category: Uuid, recurrence: Option<Recurrence>) -> Result<ApiResult<Task>, ServerFnError> {
+    Ok(async {
+        let caller = Caller::get(_cx).await?;
+        check_name(&name)?;
+        timespan.validate()?;
+        let mut stored = caller.owned_task(&task).await?;
+        caller.owned_category(&category).await?;
+        stored.name = name;
+        stored.description = description;
+        stored.timespan = timespan;
+        stored.category = category;
+        stored.recurrence = recurrence;
+        Ok(caller.db.put_task(&stored).await?)
+    }.await)
+}
+
+///marks one of the logged-in user's tasks as done or not done
+#[server(SetTaskCompletion, "/api", "Cbor")]
+pub async fn set_task_completion(_cx: Scope, task: Uuid, completed: bool) -> Result<ApiResult<Task>, ServerFnError> {
+    Ok(async {
+        let caller = Caller::get(_cx).await?;
+        caller.owned_task(&task).await?;
+        Ok(caller.db.task_set_completion(&task, completed).await?)
+    }.await)
+}
+
+///sets when the logged-in user is reminded of one of their tasks, in minutes before its due time
+#[server(SetTaskReminders, "/api", "Cbor")]
+pub async fn set_task_reminders(_cx: Scope, task: Uuid, reminders: Vec<u32>) -> Result<ApiResult<Task>, ServerFnError> {
+    Ok(async {
+        let caller = Caller::get(_cx).await?;
+        let reminders = check_reminders(reminders)?;
+        caller.owned_task(&task).await?;
+        Ok(caller.db.task_set_reminders(&task, reminders).await?)
+    }.await)
+}
+
+///deletes one of the logged-in user's tasks
+#[server(DeleteTask, "/api", "Cbor")]
+pub async fn delete_task(_cx: Scope, task: Uuid) -> Result<ApiResult<Task>, ServerFnError> {
+    Ok(async {
+        let caller = Caller::get(_cx).await?;
+        caller.owned_task(&task).await?;
+        Ok(caller.db.delete_task(&task).await?)
+    }.await)
+}
+
+/// Which unfinished tasks to list, and in what order.
+#[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq)]
+pub struct TaskFilter {
+    /// Only tasks of this category
+    pub category: Option<Uuid>,
+    /// Only tasks due at or after this
+    pub due_from: Option<DateTime::<Utc>>,
+    /// Only tasks due before this
+    pub due_until: Option<DateTime::<Utc>>,
+    /// The latest due first, instead of the soonest
+    pub latest_first: bool,
+}
+
+impl TaskFilter {
+    ///the tasks passing the filter, sorted by due time in `zone`
+    pub fn apply(&self, mut tasks: Vec<Task>, zone: &LocalZone) -> Vec<Task> {
+        let due = |x: &Task| due(x, zone);
+        tasks.retain(|x| {
+            self.category.iter().all(|category| x.category == *category)
+                && self.due_from.iter().all(|from| due(x) >= *from)
+                && self.due_until.iter().all(|until| due(x) < *until)
+        });
+        tasks.sort_by_key(due);
+        if self.latest_first {
+            tasks.reverse();
+        }
+        tasks
+    }
+}
+
+///the time a task has to be done by in `zone`, which is the end of its timespan
+///
+///a task due on a date has to be done by the midnight that ends it
+pub fn due(task: &Task, zone: &LocalZone) -> DateTime::<Utc> {
+    zone.resolve(&task.timespan).end
+}
+
+///whether a task that is not done yet should have been by `now`
+pub fn is_overdue(task: &Task, zone: &LocalZone, now: &DateTime::<Utc>) -> bool {
+    !task.completed && due(task, zone) < *now
+}
+
+#[cfg(feature = "ssr")]
+impl Caller {
+    ///checks that every one of `tasks` belongs to the logged-in user, before any of them is changed
+    async fn owned_tasks(&self, tasks: &[Uuid]) -> ApiResult<Vec<Task>> {
+        let mut owned = Vec::with_capacity(tasks.len());
+        for task in tasks {
+            owned.push(self.owned_task(task).await?);
+        }
+        Ok(owned)
+    }
+}
+
+///the logged-in user's tasks that are not done yet, filtered and sorted by due time
+#[server(ListOpenTasks, "/api", "Cbor")]
+pub async fn list_open_tasks(_cx: Scope, filter: TaskFilter) -> Result<ApiResult<Vec<Task>>, ServerFnError> {
+    Ok(async {
+        let caller = Caller::get(_cx).await?;
+        if let (Some(from), Some(until)) = (&filter.due_from, &filter.due_until) {
+            Timespan::new(from, until)?;
+        }
+        Ok(filter.apply(caller.db.get_open_tasks(&caller.user).await?, &caller.zone().await?))
+    }.await)
+}
+
+///marks several of the logged-in user's tasks as done, none of them if one is not the user's
+#[server(CompleteTasks, "/api", "Cbor")]
+pub async fn complete_tasks(_cx: Scope, tasks: Vec<Uuid>) -> Result<ApiResult<Vec<Task>>, ServerFnError> {
+    Ok(async {
+        let caller = Caller::get(_cx).await?;
+        caller.owned_tasks(&tasks).await?;
+        let mut completed = Vec::with_capacity(tasks.len());
+        for task in &tasks {
+            completed.push(caller.db.task_set_completion(task, true).await?);
+        }
+        Ok(completed)
+    }.await)
+}
+
+/// The furthest, in days, tasks can be moved at once, about ten years.
+pub const MAX_RESCHEDULE_DAYS: u32 = 3660;
+
+///moves several of the logged-in user's tasks by a number of days, none of them if one is not the
+///user's or would move too far
+#[server(RescheduleTasks, "/api", "Cbor")]
+pub async fn reschedule_tasks(_cx: Scope, tasks: Vec<Uuid>, days: i32) -> Result<ApiResult<Vec<Task>>, ServerFnError> {
+    Ok(async {
+        let caller = Caller::get(_cx).await?;
+        if days.unsigned_abs() > MAX_RESCHEDULE_DAYS {
+            return Err(ApiError::Invalid(format!("tasks can be moved by at most {MAX_RESCHEDULE_DAYS} days")));
+        }
+        // days in the user's time zone, so the tasks keep their time of day across a clock change
+        let zone = caller.zone().await?;
+        let mut timespans = Vec::with_capacity(tasks.len());
+        for task in caller.owned_tasks(&tasks).await? {
+            let timespan = zone.shift_days(&task.timespan, days.into())
+                .ok_or_else(|| ApiError::Invalid(format!("{} cannot be moved that far", task.name)))?;
+            timespans.push((task.uuid, timespan));
+        }
+        let mut rescheduled = Vec::with_capacity(timespans.len());
+        for (task, timespan) in timespans {
+            rescheduled.push(caller.db.task_edit_timespan(&task, &timespan).await?);
+        }
+        Ok(rescheduled)
+    }.await)
+}
@@ -1 +1,3 @@
-pub mod model;
\ No newline at end of file
+pub mod api;
+pub mod model;
+pub mod recurrence;
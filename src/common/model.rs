@@ -1,56 +1,418 @@
 use serde::{Serialize, Deserialize};
 use surrealdb::sql::{Uuid};
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Duration, LocalResult, NaiveDate, NaiveDateTime, Offset, TimeZone, Utc, Weekday};
+use chrono_tz::Tz;
+use thiserror::Error;
 
-#[derive(Debug, Default, Serialize, Deserialize, PartialEq)]
+use crate::common::recurrence::{Recurrence, Occurrence, occurrences};
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq)]
 pub struct User {
     pub name: String,
-    pub hashed_password: Vec<u8>,
+    ///Argon2 hash of the password as a PHC string
+    pub hashed_password: String,
     pub joined_at: DateTime::<Utc>,
     pub categories: Vec<Uuid>,
+    ///secret in the url of the user's calendar feed, which calendar apps fetch without a session
+    #[serde(default)]
+    pub feed_token: String,
+    #[serde(default)]
+    pub week_start: WeekStart,
+    ///address reminders are sent to, none are sent without one
+    #[serde(default)]
+    pub email: Option<String>,
+    #[serde(default)]
+    pub time_zone: LocalZone,
     pub uuid: Uuid,
 }
 
-#[derive(Debug, Serialize, Deserialize, PartialEq)]
+///the day weeks start on in the calendar views, Monday unless the user picks another
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(transparent)]
+pub struct WeekStart(pub Weekday);
+
+impl Default for WeekStart {
+    fn default() -> Self {
+        WeekStart(Weekday::Mon)
+    }
+}
+
+///the IANA time zone a user's calendar is shown in, UTC unless the user picks another
+///
+///items are stored in UTC; this is what turns them into wall-clock times and dates and back,
+///following the zone's daylight saving time changes
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(transparent)]
+pub struct LocalZone(pub Tz);
+
+impl Default for LocalZone {
+    fn default() -> Self {
+        LocalZone(Tz::UTC)
+    }
+}
+
+impl LocalZone {
+    ///the wall-clock time of an instant in this zone
+    pub fn local(&self, time: &DateTime::<Utc>) -> DateTime::<Tz> {
+        time.with_timezone(&self.0)
+    }
+
+    ///the date an instant falls on in this zone
+    pub fn date_of(&self, time: &DateTime::<Utc>) -> NaiveDate {
+        self.local(time).date_naive()
+    }
+
+    ///the instant a wall-clock time in this zone stands for
+    ///
+    ///a time that happens twice when the clocks go back is the first of the two, and one that is
+    ///skipped when they go forward is moved on by the length of the gap
+    pub fn instant(&self, local: &NaiveDateTime) -> DateTime::<Utc> {
+        match self.0.from_local_datetime(local) {
+            LocalResult::Single(x) | LocalResult::Ambiguous(x, _) => x.with_timezone(&Utc),
+            // the offset from before the gap carries the time past it
+            LocalResult::None => {
+                let before = self.0.offset_from_utc_datetime(&(*local - Duration::days(1))).fix();
+                Utc.from_utc_datetime(&(*local - Duration::seconds(before.local_minus_utc().into())))
+            }
+        }
+    }
+
+    ///the instant a date starts in this zone
+    pub fn start_of_day(&self, date: &NaiveDate) -> DateTime::<Utc> {
+        self.instant(&date.and_time(Default::default()))
+    }
+
+    ///the whole of a date in this zone, which is 23 or 25 hours long when the clocks change on it
+    pub fn day_span(&self, date: &NaiveDate) -> Timespan {
+        self.dates_span(date, date)
+    }
+
+    ///the whole of the dates from `first` to `last` in this zone, both included, just `first` when
+    ///`last` comes before it, and up to the last instant there is when it is the last date
+    pub fn dates_span(&self, first: &NaiveDate, last: &NaiveDate) -> Timespan {
+        Timespan {
+            start: self.start_of_day(first),
+            end: last.max(first).succ_opt().map_or(DateTime::<Utc>::MAX_UTC, |x| self.start_of_day(&x)),
+            all_day: false,
+        }
+    }
+
+    ///the same wall-clock time `days` days later, or earlier for a negative number, `None` beyond the
+    ///dates chrono can represent
+    pub fn add_days(&self, time: &DateTime::<Utc>, days: i64) -> Option<DateTime::<Utc>> {
+        let local = self.local(time).naive_local().checked_add_signed(whole_days(days)?)?;
+        Some(self.instant(&local))
+    }
+
+    ///the instants a timespan covers in this zone
+    ///
+    ///an all-day span runs from midnight to midnight here, a timed one is returned as it is
+    pub fn resolve(&self, timespan: &Timespan) -> Timespan {
+        match timespan.dates() {
+            Some((first, last)) => self.dates_span(&first, &last),
+            None => timespan.clone(),
+        }
+    }
+
+    ///the same span `days` days later, or earlier for a negative number, `None` beyond the dates
+    ///chrono can represent
+    ///
+    ///a timed span keeps its wall-clock times in this zone, and an all-day one moves by whole dates
+    pub fn shift_days(&self, timespan: &Timespan, days: i64) -> Option<Timespan> {
+        if timespan.all_day {
+            return timespan.shift(whole_days(days)?);
+        }
+        Some(Timespan {
+            start: self.add_days(&timespan.start, days)?,
+            end: self.add_days(&timespan.end, days)?,
+            all_day: false,
+        })
+    }
+}
+
+///`days` days as a duration, `None` if that is too long for chrono
+fn whole_days(days: i64) -> Option<Duration> {
+    days.checked_mul(24 * 60 * 60 * 1000).map(Duration::milliseconds)
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct Task {
     pub name: String,
     pub description: String,
     pub timespan: Timespan,
     pub category: Uuid,
     pub completed: bool,
+    #[serde(default)]
+    pub recurrence: Option<Recurrence>,
+    ///UID of the iCalendar component this was imported from, if it did not originate here
+    #[serde(default)]
+    pub ical_uid: Option<String>,
+    ///minutes before the due time of each occurrence at which the user is e-mailed a reminder
+    #[serde(default)]
+    pub reminders: Vec<u32>,
     pub user: Uuid,
     pub uuid: Uuid,
 }
 
-#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct Event {
     pub name: String,
     pub description: String,
     pub timespan: Timespan,
     pub category: Uuid,
+    #[serde(default)]
+    pub recurrence: Option<Recurrence>,
+    ///UID of the iCalendar component this was imported from, if it did not originate here
+    #[serde(default)]
+    pub ical_uid: Option<String>,
+    ///minutes before the start of each occurrence at which the user is e-mailed a reminder
+    #[serde(default)]
+    pub reminders: Vec<u32>,
     pub user: Uuid,
     pub uuid: Uuid,
 }
 
-#[derive(Debug, Serialize, Deserialize, PartialEq)]
+impl Task {
+    ///the occurrences of this task overlapping `range`, expanding its recurrence if it has one
+    pub fn occurrences(&self, range: &Timespan) -> Vec<Occurrence> {
+        occurrences(&self.timespan, self.recurrence.as_ref(), range)
+    }
+}
+
+impl Event {
+    ///the occurrences of this event overlapping `range`, expanding its recurrence if it has one
+    pub fn occurrences(&self, range: &Timespan) -> Vec<Occurrence> {
+        occurrences(&self.timespan, self.recurrence.as_ref(), range)
+    }
+}
+
+///a logged-in browser, identified by the hash of the random token in its session cookie
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Session {
+    pub token_hash: String,
+    pub user: Uuid,
+    pub created_at: DateTime::<Utc>,
+    pub expires_at: DateTime::<Utc>,
+}
+
+///a reminder e-mail for one occurrence of a task or event, recorded before it is sent so that it
+///goes out only once, also across restarts. Stored under its `key`
+///
+///a record whose `sent_at` is still empty is being sent. If the sender died before finishing, the
+///claim goes stale and the reminder is sent again
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ReminderDelivery {
+    pub key: String,
+    pub item: Uuid,
+    pub user: Uuid,
+    ///start of the event occurrence, or due time of the task occurrence, the reminder is for
+    pub occurs_at: DateTime::<Utc>,
+    ///how long before `occurs_at` the reminder was asked for
+    pub minutes_before: u32,
+    pub claimed_at: DateTime::<Utc>,
+    pub sent_at: Option<DateTime::<Utc>>,
+}
+
+impl ReminderDelivery {
+    ///the key of the reminder `minutes_before` the occurrence of `item` at `occurs_at`
+    pub fn key_for(item: &Uuid, occurs_at: &DateTime::<Utc>, minutes_before: u32) -> String {
+        format!("{}_{}_{}", item.to_raw(), occurs_at.timestamp(), minutes_before)
+    }
+}
+
+///a periodic background job, with the schedule and lock the job runner keeps for it
+///
+///a runner only starts a job after locking it, so with several server instances sharing a
+///database each run happens once. A lock that outlives `locked_until` belongs to a runner that
+///died, and is ignored
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Job {
+    pub name: String,
+    ///when the job is due to run next, pushed back after failures
+    pub next_run: DateTime::<Utc>,
+    ///the runner holding the lock
+    pub locked_by: Option<String>,
+    pub locked_until: Option<DateTime::<Utc>>,
+    pub last_started_at: Option<DateTime::<Utc>>,
+    pub last_finished_at: Option<DateTime::<Utc>>,
+    ///why the last run failed, `None` if it succeeded
+    pub last_error: Option<String>,
+    ///failed runs in a row
+    pub failures: u32,
+}
+
+impl Job {
+    pub fn new(name: &str, next_run: &DateTime::<Utc>) -> Self {
+        Job {
+            name: name.to_string(),
+            next_run: *next_run,
+            locked_by: None,
+            locked_until: None,
+            last_started_at: None,
+            last_finished_at: None,
+            last_error: None,
+            failures: 0,
+        }
+    }
+
+    ///whether a runner holds an unexpired lock on the job at `now`
+    pub fn is_running(&self, now: &DateTime::<Utc>) -> bool {
+        matches!(self.locked_until, Some(x) if x > *now)
+    }
+}
+
+///name of the category a user's items go to when their category is deleted without naming another
+pub const UNCATEGORISED: &str = "Uncategorised";
+
+///colour of the "Uncategorised" category, and of items whose category no longer exists
+pub const UNCATEGORISED_COLOR: u32 = 0x1E70F0;
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct Category {
     pub name: String,
     pub color: u32,
+    ///whether the calendar views leave out the items of this category
+    #[serde(default)]
+    pub hidden: bool,
     pub user: Uuid,
     pub uuid: Uuid,
 }
 
+impl Category {
+    ///the colour as a CSS hex colour
+    pub fn css_color(&self) -> String {
+        format!("#{:06x}", self.color & 0xFFFFFF)
+    }
+}
+
+///why a timespan was refused
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum TimespanError {
+    #[error("the end lies before the start")]
+    EndBeforeStart,
+    ///an all-day span has to run from one midnight UTC to a later one
+    #[error("an all-day timespan has to cover whole dates")]
+    NotWholeDates,
+}
+
+///the time an item takes up, either between two instants or on whole dates
+///
+///an all-day span covers the same dates in every time zone. Its `start` is midnight UTC of the
+///first date and its `end` midnight UTC after the last, which `LocalZone::resolve` turns into the
+///instants those dates begin and end in a particular zone
+///
+///spans are half-open (`start..end`) and never end before they start, which the constructors
+///check. Spans that come from elsewhere, like a request or a database record, are checked with
+///`validate`
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct Timespan {
     pub start: DateTime::<Utc>,
     pub end: DateTime::<Utc>,
+    ///whether this covers whole dates rather than running from one instant to another
+    #[serde(default)]
+    pub all_day: bool,
 }
 
 impl Timespan {
-    pub fn new(start: &DateTime::<Utc>, end: &DateTime::<Utc>) -> Self {
-        Timespan {
-            start: start.clone(),
-            end: end.clone()
+    ///the span from `start` to `end`, refused if it ends before it starts
+    pub fn new(start: &DateTime::<Utc>, end: &DateTime::<Utc>) -> Result<Self, TimespanError> {
+        let timespan = Timespan {
+            start: *start,
+            end: *end,
+            all_day: false,
+        };
+        timespan.validate()?;
+        Ok(timespan)
+    }
+
+    ///the all-day span from `first` to `last`, both included, refused if `last` comes before `first`
+    pub fn all_day(first: &NaiveDate, last: &NaiveDate) -> Result<Self, TimespanError> {
+        if last < first {
+            return Err(TimespanError::EndBeforeStart);
+        }
+        Ok(Timespan {
+            start: Utc.from_utc_datetime(&first.and_time(Default::default())),
+            end: Utc.from_utc_datetime(&(*last + Duration::days(1)).and_time(Default::default())),
+            all_day: true,
+        })
+    }
+
+    ///checks that the span does not end before it starts, and that an all-day one covers whole dates
+    pub fn validate(&self) -> Result<(), TimespanError> {
+        if self.end < self.start {
+            return Err(TimespanError::EndBeforeStart);
+        }
+        let midnight = |x: &DateTime::<Utc>| x.time() == Default::default();
+        if self.all_day && !(midnight(&self.start) && midnight(&self.end) && self.start < self.end) {
+            return Err(TimespanError::NotWholeDates);
+        }
+        Ok(())
+    }
+
+    ///the first and last date of an all-day span, `None` for a timed one
+    pub fn dates(&self) -> Option<(NaiveDate, NaiveDate)> {
+        self.all_day.then(|| (self.start.date_naive(), (self.end - Duration::days(1)).date_naive().max(self.start.date_naive())))
+    }
+
+    ///how long the span lasts
+    pub fn duration(&self) -> Duration {
+        self.end - self.start
+    }
+
+    ///whether this timespan and `other` share any instant
+    ///
+    ///spans are half-open (`start..end`), except that a zero-length span still overlaps a span
+    ///starting at the same instant
+    pub fn overlaps(&self, other: &Timespan) -> bool {
+        self.start < other.end && (self.end > other.start || self.start >= other.start)
+    }
+
+    ///the part of this span that lies within `other`, `None` if they do not overlap
+    ///
+    ///the part is only all-day when both spans are
+    pub fn intersection(&self, other: &Timespan) -> Option<Timespan> {
+        self.overlaps(other).then(|| Timespan {
+            start: self.start.max(other.start),
+            end: self.end.min(other.end),
+            all_day: self.all_day && other.all_day,
+        })
+    }
+
+    ///whether `time` lies within the span, which includes its start but not its end
+    pub fn contains(&self, time: &DateTime::<Utc>) -> bool {
+        self.start <= *time && *time < self.end
+    }
+
+    ///whether the whole of `other` lies within this span
+    pub fn covers(&self, other: &Timespan) -> bool {
+        self.start <= other.start && other.end <= self.end
+    }
+
+    ///the same span moved by `by`, later for a positive duration and earlier for a negative one,
+    ///`None` beyond the instants chrono can represent
+    pub fn shift(&self, by: Duration) -> Option<Timespan> {
+        Some(Timespan {
+            start: self.start.checked_add_signed(by)?,
+            end: self.end.checked_add_signed(by)?,
+            all_day: self.all_day,
+        })
+    }
+
+    ///the span cut at every midnight in `zone`, as the date and part of the span of each day it
+    ///overlaps, in order
+    ///
+    ///a zero-length span has a single part. The span is split as it is, so an all-day one has to
+    ///be resolved in `zone` first
+    pub fn split_days(&self, zone: &LocalZone) -> Vec<(NaiveDate, Timespan)> {
+        let mut parts = Vec::new();
+        let mut date = zone.date_of(&self.start);
+        while let Some(part) = self.intersection(&zone.day_span(&date)) {
+            parts.push((date, part));
+            date = match date.succ_opt() {
+                Some(x) => x,
+                None => break,
+            };
         }
+        parts
     }
 }
@@ -0,0 +1,404 @@
+use std::fmt;
+use std::str::FromStr;
+
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveDateTime, TimeZone, Utc, Weekday};
+use serde::{Serialize, Deserialize};
+use thiserror::Error;
+
+use crate::common::model::{LocalZone, Timespan, TimespanError};
+
+/// Upper bound on the number of periods a rule is expanded over, so a rule that never
+/// produces a matching date cannot loop forever.
+const MAX_PERIODS: u32 = 100_000;
+
+#[derive(Debug, Error, PartialEq)]
+pub enum RecurrenceError {
+    #[error("invalid RRULE: {0}")]
+    InvalidRule(String),
+    #[error("unsupported RRULE part: {0}")]
+    Unsupported(String),
+    #[error("invalid overridden occurrence: {0}")]
+    InvalidOverride(#[from] TimespanError),
+}
+
+/// How often a recurrence repeats (RFC 5545 `FREQ`).
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+/// A `BYDAY` entry: a weekday, optionally restricted to the n-th one of the month or year.
+/// Negative ordinals count from the end, so `-1` is the last one. A month has at most 5 of a
+/// weekday and a year 53, and daily and weekly rules take no ordinals at all.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub struct ByDay {
+    pub ordinal: Option<i8>,
+    pub weekday: Weekday,
+}
+
+/// Replaces a single occurrence of a recurring item, identified by the start the rule gave it
+/// (its RFC 5545 `RECURRENCE-ID`).
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct OccurrenceOverride {
+    pub recurrence_id: DateTime::<Utc>,
+    pub timespan: Timespan,
+    pub name: Option<String>,
+    pub description: Option<String>,
+}
+
+/// An RFC 5545 recurrence rule together with its exceptions.
+///
+/// Occurrences are computed in `time_zone`, starting from the timespan of the item the rule belongs
+/// to (its `DTSTART`), except that all-day items repeat on dates whatever the zone. Weeks start on
+/// Monday.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Recurrence {
+    pub frequency: Frequency,
+    pub interval: u32,
+    #[serde(default)]
+    pub by_day: Vec<ByDay>,
+    pub count: Option<u32>,
+    pub until: Option<DateTime::<Utc>>,
+    /// Starts of occurrences that are skipped (`EXDATE`)
+    #[serde(default)]
+    pub exdates: Vec<DateTime::<Utc>>,
+    #[serde(default)]
+    pub overrides: Vec<OccurrenceOverride>,
+    /// Zone whose wall-clock time the occurrences keep, so that a meeting at 09:00 stays at 09:00
+    /// when daylight saving time starts or ends (the `TZID` of `DTSTART`)
+    #[serde(default)]
+    pub time_zone: LocalZone,
+}
+
+/// A concrete instance of a task or event.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Occurrence {
+    /// The start the rule gave this occurrence, `None` if the item does not recur
+    pub recurrence_id: Option<DateTime::<Utc>>,
+    pub timespan: Timespan,
+    /// Replacement name from an override, `None` to use the item's own
+    pub name: Option<String>,
+    /// Replacement description from an override, `None` to use the item's own
+    pub description: Option<String>,
+}
+
+impl Recurrence {
+    pub fn new(frequency: Frequency) -> Self {
+        Recurrence {
+            frequency,
+            interval: 1,
+            by_day: Vec::new(),
+            count: None,
+            until: None,
+            exdates: Vec::new(),
+            overrides: Vec::new(),
+            time_zone: LocalZone::default(),
+        }
+    }
+
+    /// The occurrences overlapping `range` of an item first taking place during `first`, ordered by start.
+    pub fn expand(&self, first: &Timespan, range: &Timespan) -> Vec<Occurrence> {
+        // overrides can move an occurrence generated after the range into it
+        let limit = self.overrides.iter()
+            .map(|x| x.recurrence_id + Duration::nanoseconds(1))
+            .fold(range.end, |a, b| a.max(b));
+
+        let mut occurrences = Vec::new();
+        // DTSTART is always the first occurrence, even if the rule would not produce it
+        let mut generated = 1;
+        occurrences.extend(self.occurrence_at(first, first.start));
+        // all-day items repeat on dates, which are kept as midnight UTC
+        let zone = if first.all_day { LocalZone::default() } else { self.time_zone };
+        'periods: for period in 0..MAX_PERIODS {
+            let starts = match self.period_starts(&zone, &first.start, period) {
+                Some(x) => x,
+                None => break,
+            };
+            for start in starts.into_iter().filter(|x| *x > first.start) {
+                if self.count.is_some_and(|count| generated >= count)
+                    || self.until.is_some_and(|until| start > until)
+                    || start >= limit {
+                    break 'periods;
+                }
+                generated += 1;
+                occurrences.extend(self.occurrence_at(first, start));
+            }
+        }
+
+        occurrences.retain(|x| x.timespan.overlaps(range));
+        occurrences.sort_by_key(|x| x.timespan.start);
+        occurrences
+    }
+
+    /// The end of the last occurrence of an item first taking place during `first`, or `None` if the
+    /// rule repeats forever, bounded by neither `COUNT` nor `UNTIL`.
+    pub fn last_end(&self, first: &Timespan) -> Option<DateTime::<Utc>> {
+        if self.count.is_none() && self.until.is_none() {
+            return None;
+        }
+        let everything = Timespan {
+            start: DateTime::<Utc>::MIN_UTC,
+            end: DateTime::<Utc>::MAX_UTC,
+            all_day: false,
+        };
+        // an item whose occurrences are all excluded never takes place after its first timespan
+        Some(self.expand(first, &everything).into_iter()
+            .map(|x| x.timespan.end)
+            .max()
+            .unwrap_or(first.end))
+    }
+
+    /// The occurrence of the item spanning `first` the rule generates at `start`, with its override
+    /// applied. `None` if it is excluded, or would end past the last instant chrono can represent.
+    fn occurrence_at(&self, first: &Timespan, start: DateTime::<Utc>) -> Option<Occurrence> {
+        if self.exdates.contains(&start) {
+            return None;
+        }
+        Some(match self.overrides.iter().find(|x| x.recurrence_id == start) {
+            Some(x) => Occurrence {
+                recurrence_id: Some(start),
+                timespan: x.timespan.clone(),
+                name: x.name.clone(),
+                description: x.description.clone(),
+            },
+            None => Occurrence {
+                recurrence_id: Some(start),
+                timespan: first.shift(start - first.start)?,
+                name: None,
+                description: None,
+            },
+        })
+    }
+
+    ///checks that the interval is positive and that every `BYDAY` ordinal can match, as a rule that
+    ///never matches would be expanded over all of `MAX_PERIODS` every time, and that the timespans
+    ///of overridden occurrences are valid
+    pub fn validate(&self) -> Result<(), RecurrenceError> {
+        let most = match self.frequency {
+            Frequency::Daily | Frequency::Weekly => 0,
+            Frequency::Monthly => 5,
+            Frequency::Yearly => 53,
+        };
+        let fits = |x: &ByDay| x.ordinal.into_iter().all(|n| n != 0 && n.unsigned_abs() <= most);
+        if self.interval == 0 || !self.by_day.iter().all(fits) {
+            return Err(RecurrenceError::InvalidRule(self.to_string()));
+        }
+        for x in &self.overrides {
+            x.timespan.validate()?;
+        }
+        Ok(())
+    }
+
+    /// Starts the rule produces in the `period`-th interval after `dtstart`, in order, counting days
+    /// in `zone`. `None` once the dates leave the representable range.
+    fn period_starts(&self, zone: &LocalZone, dtstart: &DateTime::<Utc>, period: u32) -> Option<Vec<DateTime::<Utc>>> {
+        let local = zone.local(dtstart).naive_local();
+        let date = local.date();
+        let time = local.time();
+        let step = period.checked_mul(self.interval.max(1))?;
+
+        let dates = match self.frequency {
+            Frequency::Daily => {
+                let day = date.checked_add_signed(Duration::days(step.into()))?;
+                if self.by_day.is_empty() || self.by_day.iter().any(|x| x.weekday == day.weekday()) {
+                    vec![day]
+                } else {
+                    Vec::new()
+                }
+            }
+            Frequency::Weekly => {
+                let monday = date.checked_sub_signed(Duration::days(date.weekday().num_days_from_monday().into()))?
+                    .checked_add_signed(Duration::weeks(step.into()))?;
+                let mut days: Vec<NaiveDate> = if self.by_day.is_empty() {
+                    vec![monday + Duration::days(date.weekday().num_days_from_monday().into())]
+                } else {
+                    self.by_day.iter().map(|x| monday + Duration::days(x.weekday.num_days_from_monday().into())).collect()
+                };
+                days.sort();
+                days.dedup();
+                days
+            }
+            Frequency::Monthly => {
+                let months = date.month0().checked_add(step)?;
+                let year = date.year().checked_add((months / 12).try_into().ok()?)?;
+                let month = months % 12 + 1;
+                let first_of_month = NaiveDate::from_ymd_opt(year, month, 1)?;
+                if self.by_day.is_empty() {
+                    // months without this day are skipped, as RFC 5545 requires
+                    NaiveDate::from_ymd_opt(year, month, date.day()).into_iter().collect()
+                } else {
+                    let next_month = NaiveDate::from_ymd_opt(year + (month / 12) as i32, month % 12 + 1, 1)?;
+                    self.matching_days(first_of_month, next_month)
+                }
+            }
+            Frequency::Yearly => {
+                let year = date.year().checked_add(step.try_into().ok()?)?;
+                if self.by_day.is_empty() {
+                    NaiveDate::from_ymd_opt(year, date.month(), date.day()).into_iter().collect()
+                } else {
+                    self.matching_days(NaiveDate::from_ymd_opt(year, 1, 1)?, NaiveDate::from_ymd_opt(year + 1, 1, 1)?)
+                }
+            }
+        };
+
+        Some(dates.into_iter().map(|x| zone.instant(&x.and_time(time))).collect())
+    }
+
+    /// Days in `from..to` selected by `by_day`, in order.
+    fn matching_days(&self, from: NaiveDate, to: NaiveDate) -> Vec<NaiveDate> {
+        let mut days = Vec::new();
+        for by_day in &self.by_day {
+            let candidates: Vec<NaiveDate> = from.iter_days()
+                .take_while(|x| *x < to)
+                .filter(|x| x.weekday() == by_day.weekday)
+                .collect();
+            match by_day.ordinal {
+                None => days.extend(candidates),
+                Some(n) if n > 0 => days.extend(candidates.get(n as usize - 1)),
+                Some(n) => days.extend(candidates.len().checked_sub(n.unsigned_abs() as usize).and_then(|i| candidates.get(i))),
+            }
+        }
+        days.sort();
+        days.dedup();
+        days
+    }
+}
+
+/// The occurrences of an item during `timespan`, optionally recurring, that overlap `range`.
+pub fn occurrences(timespan: &Timespan, recurrence: Option<&Recurrence>, range: &Timespan) -> Vec<Occurrence> {
+    match recurrence {
+        Some(x) => x.expand(timespan, range),
+        None if timespan.overlaps(range) => vec![Occurrence {
+            recurrence_id: None,
+            timespan: timespan.clone(),
+            name: None,
+            description: None,
+        }],
+        None => Vec::new(),
+    }
+}
+
+pub(crate) fn weekday_code(weekday: Weekday) -> &'static str {
+    match weekday {
+        Weekday::Mon => "MO",
+        Weekday::Tue => "TU",
+        Weekday::Wed => "WE",
+        Weekday::Thu => "TH",
+        Weekday::Fri => "FR",
+        Weekday::Sat => "SA",
+        Weekday::Sun => "SU",
+    }
+}
+
+/// Formats an instant as an RFC 5545 UTC date-time, e.g. `20230410T090000Z`.
+pub fn format_ical_datetime(time: &DateTime::<Utc>) -> String {
+    time.format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+/// Parses an RFC 5545 UTC date-time (`20230410T090000Z`) or date (`20230410`, taken as midnight UTC).
+pub fn parse_ical_datetime(value: &str) -> Option<DateTime::<Utc>> {
+    if let Some(value) = value.strip_suffix('Z') {
+        let time = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S").ok()?;
+        Some(Utc.from_utc_datetime(&time))
+    } else {
+        let date = NaiveDate::parse_from_str(value, "%Y%m%d").ok()?;
+        Some(Utc.from_utc_datetime(&date.and_hms_opt(0, 0, 0)?))
+    }
+}
+
+/// Formats the rule as an RFC 5545 `RRULE` value. Exceptions and overrides are not part of it.
+impl fmt::Display for Recurrence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let frequency = match self.frequency {
+            Frequency::Daily => "DAILY",
+            Frequency::Weekly => "WEEKLY",
+            Frequency::Monthly => "MONTHLY",
+            Frequency::Yearly => "YEARLY",
+        };
+        write!(f, "FREQ={frequency}")?;
+        if self.interval > 1 {
+            write!(f, ";INTERVAL={}", self.interval)?;
+        }
+        if !self.by_day.is_empty() {
+            let days: Vec<String> = self.by_day.iter()
+                .map(|x| format!("{}{}", x.ordinal.map(|n| n.to_string()).unwrap_or_default(), weekday_code(x.weekday)))
+                .collect();
+            write!(f, ";BYDAY={}", days.join(","))?;
+        }
+        if let Some(count) = self.count {
+            write!(f, ";COUNT={count}")?;
+        }
+        if let Some(until) = &self.until {
+            write!(f, ";UNTIL={}", format_ical_datetime(until))?;
+        }
+        Ok(())
+    }
+}
+
+/// Parses an RFC 5545 `RRULE` value. Parts other than `FREQ`, `INTERVAL`, `BYDAY`, `COUNT`,
+/// `UNTIL` and a Monday `WKST` are rejected rather than silently ignored.
+impl FromStr for Recurrence {
+    type Err = RecurrenceError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || RecurrenceError::InvalidRule(s.to_string());
+        let mut recurrence: Option<Recurrence> = None;
+        let mut rest = Vec::new();
+
+        for part in s.trim().trim_start_matches("RRULE:").split(';').filter(|x| !x.is_empty()) {
+            let (key, value) = part.split_once('=').ok_or_else(invalid)?;
+            if key.eq_ignore_ascii_case("FREQ") {
+                recurrence = Some(Recurrence::new(match value.to_ascii_uppercase().as_str() {
+                    "DAILY" => Frequency::Daily,
+                    "WEEKLY" => Frequency::Weekly,
+                    "MONTHLY" => Frequency::Monthly,
+                    "YEARLY" => Frequency::Yearly,
+                    other => return Err(RecurrenceError::Unsupported(format!("FREQ={other}"))),
+                }));
+            } else {
+                rest.push((key.to_ascii_uppercase(), value));
+            }
+        }
+
+        let mut recurrence = recurrence.ok_or_else(invalid)?;
+        for (key, value) in rest {
+            match key.as_str() {
+                "INTERVAL" => recurrence.interval = value.parse().ok().filter(|x| *x > 0).ok_or_else(invalid)?,
+                "COUNT" => recurrence.count = Some(value.parse().map_err(|_| invalid())?),
+                "UNTIL" => recurrence.until = Some(parse_ical_datetime(value).ok_or_else(invalid)?),
+                "BYDAY" => {
+                    for day in value.split(',') {
+                        let day = day.trim().to_ascii_uppercase();
+                        if day.len() < 2 {
+                            return Err(invalid());
+                        }
+                        let (ordinal, code) = day.split_at(day.len() - 2);
+                        let weekday = match code {
+                            "MO" => Weekday::Mon,
+                            "TU" => Weekday::Tue,
+                            "WE" => Weekday::Wed,
+                            "TH" => Weekday::Thu,
+                            "FR" => Weekday::Fri,
+                            "SA" => Weekday::Sat,
+                            "SU" => Weekday::Sun,
+                            _ => return Err(invalid()),
+                        };
+                        let ordinal = match ordinal.trim_start_matches('+') {
+                            "" => None,
+                            n => Some(n.parse::<i8>().ok().filter(|x| *x != 0).ok_or_else(invalid)?),
+                        };
+                        recurrence.by_day.push(ByDay { ordinal, weekday });
+                    }
+                }
+                "WKST" if value.eq_ignore_ascii_case("MO") => (),
+                _ => return Err(RecurrenceError::Unsupported(format!("{key}={value}"))),
+            }
+        }
+
+        recurrence.validate()?;
+        Ok(recurrence)
+    }
+}
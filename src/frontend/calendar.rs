@@ -0,0 +1,101 @@
+use chrono::{Datelike, NaiveDate, Utc};
+use leptos::*;
+use serde::{Deserialize, Serialize};
+use time::{Date, Month, Weekday};
+
+use crate::common::api::calendar::CalendarItem;
+use crate::common::api::get_current_user;
+use crate::common::model::{LocalZone, Timespan, WeekStart};
+
+///a date of the `time` crate the views work with as one of chrono, which the model uses
+pub fn naive_date(date: Date) -> NaiveDate {
+	NaiveDate::from_ymd_opt(date.year(), u8::from(date.month()) as u32, date.day() as u32).unwrap()
+}
+
+///the whole of a date in `zone`, as a timespan
+pub fn day_span(date: Date, zone: LocalZone) -> Timespan {
+	zone.day_span(&naive_date(date))
+}
+
+///the whole of the dates from `first` to `last` in `zone`, both included, as a timespan
+pub fn dates_span(first: Date, last: Date, zone: LocalZone) -> Timespan {
+	zone.dates_span(&naive_date(first), &naive_date(last))
+}
+
+///converts a weekday as the model stores it to one of the `time` crate the views work with
+pub fn time_weekday(weekday: chrono::Weekday) -> Weekday {
+	Weekday::Monday.nth_next(weekday.num_days_from_monday() as u8)
+}
+
+///the last day on or before `date` that falls on `week_start`, or the first date there is when the
+///week starts before it
+pub fn first_of_week(date: Date, week_start: WeekStart) -> Date {
+	let mut first = date;
+	while first.weekday() != time_weekday(week_start.0) {
+		match first.previous_day() {
+			Some(x) => first = x,
+			None => break,
+		}
+	}
+	first
+}
+
+///`count` dates in a row from `first`, fewer when they would run past the last date there is
+pub fn dates_from(first: Date, count: usize) -> Vec<Date> {
+	std::iter::successors(Some(first), |x| x.next_day()).take(count).collect()
+}
+
+/// What the calendar views need to know about the logged-in user.
+#[derive(Debug, Default, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub struct CalendarSettings {
+	pub week_start: WeekStart,
+	/// The zone days start and end in
+	pub zone: LocalZone,
+}
+
+///the logged-in user's calendar settings, Monday and UTC when there is no one logged in
+pub async fn calendar_settings(cx: Scope) -> CalendarSettings {
+	match get_current_user(cx).await {
+		Ok(Some(user)) => CalendarSettings { week_start: user.week_start, zone: user.time_zone },
+		_ => CalendarSettings::default(),
+	}
+}
+
+/// The logged-in user's time zone, for what has to know today's date outside of a resource, like
+/// the navigation.
+#[derive(Clone, Copy)]
+pub struct UserZone {
+	pub zone: Resource<(), LocalZone>,
+}
+
+impl UserZone {
+	///loads the time zone and makes it available to every view below `cx`
+	pub fn provide(cx: Scope) -> Self {
+		let zone = create_resource(cx, || (), move |_| async move { calendar_settings(cx).await.zone });
+		let user_zone = UserZone { zone };
+		provide_context(cx, user_zone);
+		user_zone
+	}
+}
+
+///the logged-in user's time zone, UTC while it is still loading
+pub fn use_zone(cx: Scope) -> LocalZone {
+	use_context::<UserZone>(cx).and_then(|x| x.zone.read(cx)).unwrap_or_default()
+}
+
+///short names of the days of the week, in order starting at `week_start`
+pub fn weekday_names(week_start: WeekStart) -> Vec<String> {
+	(0..7).map(|n| time_weekday(week_start.0).nth_next(n).to_string()[..3].to_string()).collect()
+}
+
+///whether an item is all-day or takes up all of `day`, such that it belongs above the timeline rather than on it
+pub fn is_all_day(item: &CalendarItem, day: &Timespan) -> bool {
+	item.all_day || item.timespan.covers(day)
+}
+
+///the current date in `zone`
+pub fn today(zone: LocalZone) -> Date {
+	// chrono rather than `time`, whose clock is not available in the browser
+	let now = zone.date_of(&Utc::now());
+	Date::from_calendar_date(now.year(), Month::try_from(now.month() as u8).unwrap(), now.day() as u8).unwrap()
+}
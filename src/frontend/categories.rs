@@ -0,0 +1,98 @@
+use leptos::*;
+use leptos_router::*;
+use surrealdb::sql::Uuid;
+
+use crate::common::api::calendar::CalendarItem;
+use crate::common::api::categories::{list_categories, set_category_hidden};
+use crate::common::api::flatten;
+use crate::common::model::Category;
+
+/// The logged-in user's categories, shared by the sidebar and the calendar views, which leave out
+/// the items of hidden categories.
+#[derive(Clone, Copy)]
+pub struct CategoryFilter {
+	pub categories: Resource<(), Vec<Category>>,
+}
+
+impl CategoryFilter {
+	///loads the categories and makes them available to every view below `cx`
+	pub fn provide(cx: Scope) -> Self {
+		let categories = create_resource(cx, || (), move |_| async move {
+			flatten(list_categories(cx).await).unwrap_or_default()
+		});
+		let filter = CategoryFilter { categories };
+		provide_context(cx, filter);
+		filter
+	}
+}
+
+///the value of a category's `<option>`, its bare uuid, which `Uuid` would display in quotes
+pub fn option_value(category: &Category) -> String {
+	category.uuid.0.to_string()
+}
+
+///the category picked in a `<select>` of categories, `None` for an option without a value
+pub fn picked_category(value: &str) -> Result<Option<Uuid>, String> {
+	if value.is_empty() {
+		return Ok(None);
+	}
+	uuid::Uuid::try_parse(value).map(|x| Some(Uuid(x))).map_err(|_| format!("{value} is not a category"))
+}
+
+///the items that do not belong to one of the hidden `categories`
+pub fn hide_categories(items: Vec<CalendarItem>, categories: &[Category]) -> Vec<CalendarItem> {
+	let hidden = categories.iter().filter(|x| x.hidden).map(|x| &x.uuid).collect::<Vec<_>>();
+	items.into_iter().filter(|x| !hidden.contains(&&x.category)).collect()
+}
+
+///the items the user has not hidden, all of them while the categories are still loading
+pub fn visible_items(cx: Scope, items: Vec<CalendarItem>) -> Vec<CalendarItem> {
+	match use_context::<CategoryFilter>(cx).and_then(|x| x.categories.read(cx)) {
+		Some(categories) => hide_categories(items, &categories),
+		None => items,
+	}
+}
+
+/// The user's categories with their colours, each with a box to show or hide its items.
+#[component]
+pub fn CategorySidebar(cx: Scope, filter: CategoryFilter) -> impl IntoView {
+	let toggle = create_action(cx, move |(category, hidden): &(Uuid, bool)| {
+		let (category, hidden) = (category.clone(), *hidden);
+		async move {
+			if flatten(set_category_hidden(cx, category, hidden).await).is_ok() {
+				filter.categories.refetch();
+			}
+		}
+	});
+
+	let rows = move || filter.categories.read(cx).map(|categories| categories
+		.into_iter()
+		.map(|category| {
+			let uuid = category.uuid.clone();
+			view! {cx,
+				<label class="sidebar-category">
+					<input
+						type="checkbox"
+						checked=!category.hidden
+						on:change=move |ev| toggle.dispatch((uuid.clone(), !event_target_checked(&ev)))
+					/>
+					<span class="sidebar-category-chip" style=format!("background-color: {}", category.css_color())></span>
+					<span class="sidebar-category-name">{category.name}</span>
+				</label>
+			}
+		})
+		.collect::<Vec<_>>()
+	);
+
+	view! {cx,
+		<div class="sidebar">
+			<div class="sidebar-heading">
+				<p>"Categories"</p>
+				<A href="/categories">"Edit"</A>
+			</div>
+			<Suspense fallback=move || ()>
+				{rows}
+			</Suspense>
+		</div>
+	}
+}
@@ -1,2 +1,5 @@
+pub mod calendar;
+pub mod categories;
+pub mod navigation;
 pub mod overlay;
 pub mod pages;
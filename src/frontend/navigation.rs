@@ -0,0 +1,142 @@
+use leptos::*;
+use leptos_router::*;
+use time::{Date, Duration, Month};
+
+use crate::frontend::calendar::{today, use_zone};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CalendarView {
+	Year,
+	Month,
+	Week,
+	Day,
+}
+
+impl CalendarView {
+	fn segment(&self) -> &'static str {
+		match self {
+			CalendarView::Year => "year",
+			CalendarView::Month => "month",
+			CalendarView::Week => "week",
+			CalendarView::Day => "day",
+		}
+	}
+}
+
+/// A calendar page: the view, and a date it shows.
+///
+/// Paths look like `/year/2023`, `/month/2023/5`, `/week/2023/5/14` (any day of the week) and
+/// `/day/2023/5/14`.
+/// A path without a date shows today, which depends on the user's time zone and is passed in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CalendarRoute {
+	pub view: CalendarView,
+	/// For years and months always their first day
+	pub date: Date,
+}
+
+impl CalendarRoute {
+	pub fn new(view: CalendarView, date: Date) -> Self {
+		let date = match view {
+			CalendarView::Year => date.replace_day(1).unwrap().replace_month(Month::January).unwrap(),
+			CalendarView::Month => date.replace_day(1).unwrap(),
+			_ => date,
+		};
+		CalendarRoute { view, date }
+	}
+
+	///the calendar page a path leads to, `None` for other pages
+	pub fn from_path(path: &str, today: Date) -> Option<Self> {
+		let mut segments = path.trim_matches('/').split('/');
+		let view = match segments.next()? {
+			"year" => CalendarView::Year,
+			"month" => CalendarView::Month,
+			"week" => CalendarView::Week,
+			"day" => CalendarView::Day,
+			_ => return None,
+		};
+		let year = segments.next();
+		let month = if view == CalendarView::Year { Some("1") } else { segments.next() };
+		let day = if matches!(view, CalendarView::Year | CalendarView::Month) { Some("1") } else { segments.next() };
+		Some(CalendarRoute::new(view, date_from_parts(year, month, day).unwrap_or(today)))
+	}
+
+	pub fn path(&self) -> String {
+		match self.view {
+			CalendarView::Year => format!("/year/{}", self.date.year()),
+			CalendarView::Month => format!("/month/{}/{}", self.date.year(), u8::from(self.date.month())),
+			view => format!("/{}/{}/{}/{}", view.segment(), self.date.year(), u8::from(self.date.month()), self.date.day()),
+		}
+	}
+
+	///the same date shown in another view
+	pub fn with_view(&self, view: CalendarView, today: Date) -> Self {
+		// switching from a year or month shows its first day, unless today lies in it
+		let date = match self.view {
+			CalendarView::Year if today.year() == self.date.year() => today,
+			CalendarView::Month if (today.year(), today.month()) == (self.date.year(), self.date.month()) => today,
+			_ => self.date,
+		};
+		CalendarRoute::new(view, date)
+	}
+
+	///the page one step back, `None` at the first date there is
+	pub fn previous(&self) -> Option<Self> {
+		let date = match self.view {
+			CalendarView::Year => self.date.replace_year(self.date.year() - 1).ok(),
+			CalendarView::Month | CalendarView::Day => self.date.previous_day(),
+			CalendarView::Week => self.date.checked_sub(Duration::weeks(1)),
+		};
+		date.map(|x| CalendarRoute::new(self.view, x))
+	}
+
+	///the page one step forward, `None` at the last date there is
+	pub fn next(&self) -> Option<Self> {
+		let date = match self.view {
+			CalendarView::Year => self.date.replace_year(self.date.year() + 1).ok(),
+			// the first of the month plus 31 days always lands in the next month
+			CalendarView::Month => self.date.checked_add(Duration::days(31)),
+			CalendarView::Week => self.date.checked_add(Duration::weeks(1)),
+			CalendarView::Day => self.date.next_day(),
+		};
+		date.map(|x| CalendarRoute::new(self.view, x))
+	}
+
+	///the same view, showing today
+	pub fn today(&self, today: Date) -> Self {
+		CalendarRoute::new(self.view, today)
+	}
+
+	///what the page shows, for the navigation bar
+	pub fn title(&self) -> String {
+		match self.view {
+			CalendarView::Year => self.date.year().to_string(),
+			CalendarView::Month => format!("{} {}", self.date.month(), self.date.year()),
+			CalendarView::Week => {
+				let (year, week, _) = self.date.to_iso_week_date();
+				format!("Week {week}, {year}")
+			}
+			CalendarView::Day => format!("{} {} {} {}", self.date.weekday(), self.date.day(), self.date.month(), self.date.year()),
+		}
+	}
+}
+
+///a date from the segments of a path, `None` if any is missing or the date does not exist
+pub fn date_from_parts(year: Option<&str>, month: Option<&str>, day: Option<&str>) -> Option<Date> {
+	let year = year?.parse().ok()?;
+	let month = Month::try_from(month?.parse::<u8>().ok()?).ok()?;
+	Date::from_calendar_date(year, month, day?.parse().ok()?).ok()
+}
+
+///the date in the parameters of the current route, today in the user's time zone if it has none
+///
+///years and months are routed without a day, and get their first
+pub fn use_route_date(cx: Scope) -> Memo<Date> {
+	let params = use_params_map(cx);
+	create_memo(cx, move |_| params.with(|x| {
+		let year = x.get("year").map(String::as_str);
+		let month = x.get("month").map(String::as_str).or(Some("1"));
+		let day = x.get("day").map(String::as_str).or(Some("1"));
+		date_from_parts(year, month, day)
+	}).unwrap_or_else(|| today(use_zone(cx))))
+}
@@ -1,24 +1,64 @@
 use leptos::*;
 use leptos_router::*;
 
+use crate::frontend::calendar::{today, use_zone, UserZone};
+use crate::frontend::categories::{CategoryFilter, CategorySidebar, CategorySidebarProps};
+use crate::frontend::navigation::{CalendarRoute, CalendarView};
+
 #[component]
 pub fn Overlay(cx: Scope, children: Children) -> impl IntoView {
+	let filter = CategoryFilter::provide(cx);
+	UserZone::provide(cx);
+	let location = use_location(cx);
+	// The categories can be toggled wherever their items are shown
+	let sidebar = move || {
+		let path = location.pathname.get();
+		(CalendarRoute::from_path(&path, today(use_zone(cx))).is_some() || path == "/agenda").then(|| view! {cx, <CategorySidebar filter/>})
+	};
+
 	view! {cx,
 		<div class="overlay-wrapper">
 			<Navbar/>
-			{ children(cx) }
+			<div class="overlay-body">
+				{sidebar}
+				<div class="overlay-content">
+					{ children(cx) }
+				</div>
+			</div>
 		</div>
 	}
 }
 
 #[component]
 pub fn Navbar(cx: Scope) -> impl IntoView {
+	let location = use_location(cx);
+	// The controls only show on calendar pages, and move through time in steps of the current view
+	let controls = move || CalendarRoute::from_path(&location.pathname.get(), today(use_zone(cx))).map(|route| {
+		let today = today(use_zone(cx));
+		let view_link = move |view: CalendarView, label: &'static str| view! {cx,
+			<A href=route.with_view(view, today).path() class=if route.view == view { "navbar-view active" } else { "navbar-view" }>{label}</A>
+		};
+		view! {cx,
+			// there is no step past the first or last date the calendar can show
+			{route.previous().map(|x| view! {cx, <A href=x.path() class="navbar-step">"‹"</A>})}
+			<A href=route.today(today).path() class="navbar-today">"Today"</A>
+			{route.next().map(|x| view! {cx, <A href=x.path() class="navbar-step">"›"</A>})}
+			<p class="navbar-title">{route.title()}</p>
+			{view_link(CalendarView::Year, "Year")}
+			{view_link(CalendarView::Month, "Month")}
+			{view_link(CalendarView::Week, "Week")}
+			{view_link(CalendarView::Day, "Day")}
+		}
+	});
+
 	view! {cx,
 		<div class="navbar">
 			<div class="navbar-left">
-				<p>"TODO:"</p>
+				{controls}
 			</div>
 			<div class="navbar-right">
+				<A href="/agenda" class="navbar-page">"Agenda"</A>
+				<A href="/tasks" class="navbar-page">"Tasks"</A>
 				<A href="/user">
 					<img id="usericon" src="/icons/user.svg"/>
 				</A>
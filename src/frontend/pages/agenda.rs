@@ -0,0 +1,155 @@
+use leptos::*;
+use time::*;
+
+use crate::common::api::calendar::{list_items, CalendarItem, ItemKind};
+use crate::common::api::flatten;
+use crate::common::api::tasks::set_task_completion;
+use crate::common::model::LocalZone;
+use crate::frontend::calendar::{calendar_settings, dates_span, day_span, is_all_day, naive_date, today, use_zone};
+use crate::frontend::categories::visible_items;
+use crate::frontend::navigation::{CalendarRoute, CalendarView};
+
+/// Number of days loaded at a time.
+pub const AGENDA_PAGE_DAYS: i64 = 14;
+
+/// How close to the bottom, in pixels, scrolling loads the next days.
+const LOAD_MARGIN: i32 = 300;
+
+///the items of `days` days in `zone` from `first` on, grouped under the days they overlap
+///
+///days without items are left out, as are tasks that are already done
+pub fn group_by_day(items: &[CalendarItem], first: Date, days: i64, zone: LocalZone) -> Vec<(Date, Vec<CalendarItem>)> {
+	let mut groups = (0..days).map(|n| (first + Duration::days(n), Vec::new())).collect::<Vec<_>>();
+	let range = dates_span(first, first + Duration::days(days - 1), zone);
+	for item in items.iter().filter(|x| x.kind != ItemKind::Task { completed: true }) {
+		let parts = item.timespan.intersection(&range).map(|x| x.split_days(&zone)).unwrap_or_default();
+		for (date, _) in parts {
+			if let Some(group) = usize::try_from((date - naive_date(first)).num_days()).ok().and_then(|n| groups.get_mut(n)) {
+				group.1.push(item.clone());
+			}
+		}
+	}
+	groups.retain(|x| !x.1.is_empty());
+	groups
+}
+
+/// Upcoming events and open tasks, from today on, loading more days as the list is scrolled.
+#[component]
+pub fn AgendaView(cx: Scope) -> impl IntoView {
+	// today moves when the user's time zone has loaded
+	let first = create_memo(cx, move |_| today(use_zone(cx)));
+	let (pages, set_pages) = create_signal(cx, 1);
+	let load_more = move || set_pages.update(|x| *x += 1);
+
+	let list = create_node_ref::<html::Div>(cx);
+	// Scrolling keeps firing while the next days load, so only load again once the list has grown
+	let loaded_at_height = create_rw_signal(cx, 0);
+	let on_scroll = move |_| {
+		if let Some(list) = list.get() {
+			let height = list.scroll_height();
+			if height != loaded_at_height.get_untracked() && list.scroll_top() + list.client_height() >= height - LOAD_MARGIN {
+				loaded_at_height.set(height);
+				load_more();
+			}
+		}
+	};
+
+	view! {cx,
+		<div class="agenda" node_ref=list on:scroll=on_scroll>
+			<For
+				each=move || 0..pages.get()
+				key=|page| *page
+				view=move |cx, page: i64| view! {cx, <AgendaPage first={first.get() + Duration::days(page * AGENDA_PAGE_DAYS)}/>}
+			/>
+			<button class="agenda-more" on:click=move |_| load_more()>"Show more"</button>
+		</div>
+	}
+}
+
+/// `AGENDA_PAGE_DAYS` days of the agenda, starting at `first`.
+#[component]
+pub fn AgendaPage(cx: Scope, first: Date) -> impl IntoView {
+	let items = create_resource(cx, || (), move |_| async move {
+		let zone = calendar_settings(cx).await.zone;
+		let span = dates_span(first, first + Duration::days(AGENDA_PAGE_DAYS - 1), zone);
+		(zone, flatten(list_items(cx, span.start, span.end).await))
+	});
+
+	let days = move || items.read(cx).map(|(zone, items)| match items {
+		Ok(items) => group_by_day(&visible_items(cx, items), first, AGENDA_PAGE_DAYS, zone)
+			.into_iter()
+			.map(|(date, items)| view! {cx, <AgendaDay date zone items/>})
+			.collect::<Vec<_>>()
+			.into_view(cx),
+		Err(x) => view! {cx, <p class="agenda-error">{x.to_string()}</p>}.into_view(cx),
+	});
+
+	view! {cx,
+		<Suspense fallback=move || view! {cx, <p class="agenda-loading">"Loading..."</p>}>
+			{days}
+		</Suspense>
+	}
+}
+
+#[component]
+pub fn AgendaDay(cx: Scope, date: Date, zone: LocalZone, items: Vec<CalendarItem>) -> impl IntoView {
+	let day = day_span(date, zone);
+	let heading = if date == today(zone) {
+		format!("Today, {} {}", date.day(), date.month())
+	} else {
+		format!("{} {} {}", date.weekday(), date.day(), date.month())
+	};
+	let rows = items.into_iter()
+		.map(|item| {
+			let time = if is_all_day(&item, &day) {
+				"All day".to_string()
+			} else {
+				// items running in from the previous day or on into the next only show the time on this one
+				let start = if item.timespan.start < day.start { "…".to_string() } else { zone.local(&item.timespan.start).format("%H:%M").to_string() };
+				let end = if item.timespan.end > day.end { "…".to_string() } else { zone.local(&item.timespan.end).format("%H:%M").to_string() };
+				if item.timespan.start == item.timespan.end { start } else { format!("{start} - {end}") }
+			};
+			view! {cx, <AgendaItem item time/>}
+		})
+		.collect::<Vec<_>>();
+
+	view! {cx,
+		<section class="agenda-day">
+			<a class="agenda-day-heading" href=CalendarRoute::new(CalendarView::Day, date).path()>{heading}</a>
+			{rows}
+		</section>
+	}
+}
+
+#[component]
+pub fn AgendaItem(cx: Scope, item: CalendarItem, time: String) -> impl IntoView {
+	let is_task = matches!(item.kind, ItemKind::Task { .. });
+	// A task ticked off stays in the list, crossed out, until the agenda is loaded again
+	let (done, set_done) = create_signal(cx, false);
+	let uuid = item.uuid.clone();
+	let toggle = create_action(cx, move |completed: &bool| {
+		let (uuid, completed) = (uuid.clone(), *completed);
+		async move {
+			if flatten(set_task_completion(cx, uuid, completed).await).is_ok() {
+				set_done.set(completed);
+			}
+		}
+	});
+
+	view! {cx,
+		<div class="agenda-item" class:completed=move || done.get()>
+			<span class="agenda-item-chip" style=format!("background-color: {}", item.css_color())></span>
+			<span class="agenda-item-time">{time}</span>
+			<span class="agenda-item-name">{item.name}</span>
+			{is_task.then(|| view! {cx,
+				<input
+					type="checkbox"
+					class="agenda-item-toggle"
+					title="Done"
+					prop:checked=move || done.get()
+					on:change=move |ev| toggle.dispatch(event_target_checked(&ev))
+				/>
+			})}
+		</div>
+	}
+}
@@ -0,0 +1,119 @@
+use leptos::*;
+use surrealdb::sql::Uuid;
+
+use crate::common::api::categories::{add_category, delete_category, edit_category};
+use crate::common::api::{flatten, ApiResult};
+use crate::common::model::{Category, UNCATEGORISED, UNCATEGORISED_COLOR};
+use crate::frontend::categories::{option_value, picked_category, CategoryFilter};
+
+///the colour picked in a colour input, which gives it as `#rrggbb`
+fn input_color(value: &str) -> Option<u32> {
+	u32::from_str_radix(value.strip_prefix('#')?, 16).ok()
+}
+
+///shows the error of a change, if any, and loads the categories again for every view
+fn report(filter: CategoryFilter, set_error: WriteSignal<Option<String>>, result: ApiResult<Category>) {
+	set_error.set(result.err().map(|x| x.to_string()));
+	filter.categories.refetch();
+}
+
+/// The user's categories, to add new ones and rename, recolour or delete existing ones.
+#[component]
+pub fn CategoriesPage(cx: Scope) -> impl IntoView {
+	let filter = use_context::<CategoryFilter>(cx).expect("the overlay provides the categories");
+	let (error, set_error) = create_signal(cx, None::<String>);
+	let (name, set_name) = create_signal(cx, String::new());
+	let (color, set_color) = create_signal(cx, UNCATEGORISED_COLOR);
+	let add = create_action(cx, move |_: &()| {
+		let (name, color) = (name.get(), color.get());
+		async move {
+			let result = flatten(add_category(cx, name, color).await);
+			if result.is_ok() {
+				set_name.set(String::new());
+			}
+			report(filter, set_error, result);
+		}
+	});
+
+	let rows = move || filter.categories.read(cx).map(|categories| categories
+		.iter()
+		.map(|category| {
+			let others = categories.iter().filter(|x| x.uuid != category.uuid).cloned().collect();
+			view! {cx, <CategoryRow category=category.clone() others filter set_error/>}
+		})
+		.collect::<Vec<_>>()
+	);
+
+	view! {cx,
+		<div class="categories">
+			<h1>"Categories"</h1>
+			{move || error.get().map(|x| view! {cx, <p class="categories-error">{x}</p>})}
+			<Suspense fallback=move || view! {cx, <p>"Loading..."</p>}>
+				{rows}
+			</Suspense>
+			<form class="categories-row" on:submit=move |ev| {
+				ev.prevent_default();
+				add.dispatch(());
+			}>
+				<input type="color" prop:value=move || format!("#{:06x}", color.get()) on:change=move |ev| {
+					if let Some(color) = input_color(&event_target_value(&ev)) {
+						set_color.set(color);
+					}
+				}/>
+				<input type="text" placeholder="New category" required prop:value=move || name.get() on:input=move |ev| set_name.set(event_target_value(&ev))/>
+				<button type="submit">"Add"</button>
+			</form>
+		</div>
+	}
+}
+
+/// One category, with the category its items move to when it is deleted picked from `others`.
+#[component]
+pub fn CategoryRow(cx: Scope, category: Category, others: Vec<Category>, filter: CategoryFilter, set_error: WriteSignal<Option<String>>) -> impl IntoView {
+	let (name, set_name) = create_signal(cx, category.name.clone());
+	let (color, set_color) = create_signal(cx, category.color);
+	let (replacement, set_replacement) = create_signal(cx, None::<Uuid>);
+	let uuid = category.uuid.clone();
+	let save = create_action(cx, {
+		let uuid = uuid.clone();
+		move |_: &()| {
+			let (uuid, name, color) = (uuid.clone(), name.get(), color.get());
+			async move { report(filter, set_error, flatten(edit_category(cx, uuid, name, color).await)) }
+		}
+	});
+	let delete = create_action(cx, move |_: &()| {
+		let (uuid, replacement) = (uuid.clone(), replacement.get());
+		async move { report(filter, set_error, flatten(delete_category(cx, uuid, replacement).await)) }
+	});
+
+	// Without a choice the items go to "Uncategorised", which the category itself may be
+	let default_label = if category.name == UNCATEGORISED { "Move items to..." } else { "Move items to Uncategorised" };
+	let options = others.into_iter()
+		.map(|x| view! {cx, <option value=option_value(&x)>{x.name}</option>})
+		.collect::<Vec<_>>();
+
+	view! {cx,
+		<form class="categories-row" on:submit=move |ev| {
+			ev.prevent_default();
+			save.dispatch(());
+		}>
+			<input type="color" value=category.css_color() on:change=move |ev| {
+				if let Some(color) = input_color(&event_target_value(&ev)) {
+					set_color.set(color);
+				}
+			}/>
+			<input type="text" required value=category.name on:input=move |ev| set_name.set(event_target_value(&ev))/>
+			<button type="submit">"Save"</button>
+			<select on:change=move |ev| {
+				match picked_category(&event_target_value(&ev)) {
+					Ok(category) => set_replacement.set(category),
+					Err(x) => set_error.set(Some(x)),
+				}
+			}>
+				<option value="">{default_label}</option>
+				{options}
+			</select>
+			<button type="button" on:click=move |_| delete.dispatch(())>"Delete"</button>
+		</form>
+	}
+}
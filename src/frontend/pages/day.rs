@@ -1,32 +1,209 @@
+use chrono::Utc;
 use leptos::*;
 use time::*;
 
+use crate::common::api::calendar::{list_items, CalendarItem, ItemKind};
+use crate::common::api::flatten;
+use crate::common::model::{LocalZone, Timespan};
+use crate::frontend::calendar::{calendar_settings, day_span, is_all_day};
+use crate::frontend::categories::visible_items;
+use crate::frontend::navigation::use_route_date;
+use crate::frontend::pages::month::{DayEvent, DayEventProps};
+
+/// Minutes covered by one row of the timeline.
+pub const MINUTES_PER_ROW: i64 = 15;
+
+/// An item positioned on the timeline of a single day.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlacedItem {
+	pub item: CalendarItem,
+	/// First grid row, 1 being the row starting at midnight
+	pub start_row: i64,
+	/// Grid line the item ends at, always after `start_row`
+	pub end_row: i64,
+	/// Column within the group of items it overlaps with, starting at 0
+	pub column: usize,
+	/// Number of columns of that group
+	pub columns: usize,
+	/// Whether the item started on an earlier day
+	pub clipped_start: bool,
+	/// Whether the item continues on a later day
+	pub clipped_end: bool,
+}
+
+///the grid row containing a minute of the day
+fn row_of(minute: i64) -> i64 {
+	minute / MINUTES_PER_ROW + 1
+}
+
+///places the items overlapping `day` on its timeline
+///
+///items are cut off at midnight on either side, and items that would share rows are put side by
+///side, each group of overlapping items dividing the width between them. The timeline has a row for
+///every `MINUTES_PER_ROW` of the day, more or fewer when the clocks change on it
+pub fn layout_day(items: &[CalendarItem], day: &Timespan) -> Vec<PlacedItem> {
+	let rows = day.duration().num_minutes() / MINUTES_PER_ROW;
+	let mut placed = items.iter()
+		.filter_map(|item| Some((item, item.timespan.intersection(day)?)))
+		.map(|(item, part)| {
+			let start_row = row_of((part.start - day.start).num_minutes()).min(rows);
+			// an item ending halfway through a row still takes up that row, and is at least one row high
+			let end_minute = (part.end - day.start).num_minutes();
+			let end_row = (row_of(end_minute - 1) + 1).clamp(start_row + 1, rows + 1);
+			PlacedItem {
+				item: item.clone(),
+				start_row,
+				end_row,
+				column: 0,
+				columns: 1,
+				clipped_start: item.timespan.start < day.start,
+				clipped_end: item.timespan.end > day.end,
+			}
+		})
+		.collect::<Vec<_>>();
+	// longer items first, so they end up in the leftmost columns
+	placed.sort_by_key(|x| (x.start_row, -x.end_row));
+
+	// every group of items that transitively overlap shares one set of columns
+	let mut group_start = 0;
+	let mut group_end = 0;
+	let mut column_ends: Vec<i64> = Vec::new();
+	for i in 0..placed.len() {
+		if placed[i].start_row >= group_end {
+			finish_group(&mut placed[group_start..i], column_ends.len());
+			group_start = i;
+			column_ends.clear();
+		}
+		let column = match column_ends.iter().position(|end| *end <= placed[i].start_row) {
+			Some(column) => column,
+			None => {
+				column_ends.push(0);
+				column_ends.len() - 1
+			}
+		};
+		column_ends[column] = placed[i].end_row;
+		placed[i].column = column;
+		group_end = group_end.max(placed[i].end_row);
+	}
+	let len = placed.len();
+	finish_group(&mut placed[group_start..len], column_ends.len());
+
+	placed
+}
+
+fn finish_group(group: &mut [PlacedItem], columns: usize) {
+	for item in group {
+		item.columns = columns;
+	}
+}
+
+/// The day of the route's date, `/day` showing today.
+#[component]
+pub fn DayPage(cx: Scope) -> impl IntoView {
+	let date = use_route_date(cx);
+	move || view! {cx, <DayView date=date.get()/>}
+}
+
 #[component]
 pub fn DayView(cx: Scope, date: Date) -> impl IntoView {
-	let subdivide_by_minutes = 15;
-	let num_rows = (24 * 60) / subdivide_by_minutes;
-
-	let mut fill_items = Vec::with_capacity(10);
-	let mut start = 0;
-	for i in 1..11 {
-		let end = start + i;
-		fill_items.push(view! {cx,
-			<p class="dayview-items" style="grid-row-start: {start}; grid-row-end: {end};">"TODO: {i}!"</p>
-		});
-		start += i + 1;
+	let items = create_resource(cx, || (), move |_| async move {
+		let zone = calendar_settings(cx).await.zone;
+		let day = day_span(date, zone);
+		(zone, flatten(list_items(cx, day.start, day.end).await))
+	});
+
+	let content = move || items.read(cx).map(|(zone, items)| {
+		let day = day_span(date, zone);
+		let (whole, timed): (Vec<_>, Vec<_>) = visible_items(cx, items.unwrap_or_default())
+			.into_iter()
+			.partition(|x| is_all_day(x, &day));
+		view! {cx,
+			{(!whole.is_empty()).then(|| view! {cx,
+				<div class="dayview-allday">
+					{whole.into_iter().map(|item| view! {cx, <DayEvent item/>}).collect::<Vec<_>>()}
+				</div>
+			})}
+			<DayTimeline date zone items=timed/>
+		}
+	});
+
+	view! {cx,
+		<div class="dayview">
+			<Suspense fallback=move || ()>
+				{content}
+			</Suspense>
+		</div>
 	}
+}
+
+/// The time grid of a single day in `zone`, with `items` laid out on it.
+#[component]
+pub fn DayTimeline(cx: Scope, date: Date, zone: LocalZone, items: Vec<CalendarItem>) -> impl IntoView {
+	let day = day_span(date, zone);
+	let num_rows = day.duration().num_minutes() / MINUTES_PER_ROW;
+
+	let fill_items = layout_day(&items, &day)
+		.into_iter()
+		.map(|placed| view! {cx, <DayItem placed zone/>})
+		.collect::<Vec<_>>();
+
+	// The indicator is only drawn when looking at today, and moves along every minute
+	let (now, set_now) = create_signal(cx, Utc::now());
+	create_effect(cx, move |_| {
+		if let Ok(handle) = set_interval_with_handle(move || set_now.set(Utc::now()), std::time::Duration::from_secs(60)) {
+			on_cleanup(cx, move || handle.clear());
+		}
+	});
+	let now_indicator = move || {
+		let now = now.get();
+		day.contains(&now).then(|| {
+			let minute = (now - day.start).num_minutes();
+			// the offset within the row, as a fraction of its height
+			let offset = (minute % MINUTES_PER_ROW) as f64 / MINUTES_PER_ROW as f64 * 100.0;
+			view! {cx,
+				<div class="dayview-now" style=format!("grid-row: {}; top: {offset}%;", row_of(minute))></div>
+			}
+		})
+	};
 
 	view! {cx,
-		<div class="dayview" style="grid-template-rows: repeat({num_rows}, 1fr);">
+		<div class="dayview-timeline" style=format!("grid-template-rows: repeat({num_rows}, 1fr);")>
 			{fill_items}
+			{now_indicator}
 		</div>
 	}
 }
 
 #[component]
-pub fn DayItem(cx: Scope, start: Time) -> impl IntoView {
-	
-	view!{cx,
-		
+pub fn DayItem(cx: Scope, placed: PlacedItem, zone: LocalZone) -> impl IntoView {
+	let width = 100.0 / placed.columns as f64;
+	let style = format!(
+		"grid-row: {} / {}; width: {width}%; margin-left: {}%; background-color: {};",
+		placed.start_row,
+		placed.end_row,
+		width * placed.column as f64,
+		placed.item.css_color(),
+	);
+	let time = format!(
+		"{}{} - {}{}",
+		if placed.clipped_start { "… " } else { "" },
+		zone.local(&placed.item.timespan.start).format("%H:%M"),
+		zone.local(&placed.item.timespan.end).format("%H:%M"),
+		if placed.clipped_end { " …" } else { "" },
+	);
+
+	view! {cx,
+		<div
+			class="dayview-items"
+			class:dayview-task={matches!(placed.item.kind, ItemKind::Task { .. })}
+			class:completed={placed.item.kind == ItemKind::Task { completed: true }}
+			class:clipped-start=placed.clipped_start
+			class:clipped-end=placed.clipped_end
+			style=style
+			title=placed.item.name.clone()
+		>
+			<p class="dayview-item-name">{placed.item.name}</p>
+			<p class="dayview-item-time">{time}</p>
+		</div>
 	}
-}
\ No newline at end of file
+}
@@ -0,0 +1,71 @@
+use chrono::{DateTime, Utc};
+use leptos::*;
+
+use crate::common::api::admin::list_jobs;
+use crate::common::api::flatten;
+use crate::common::model::Job;
+
+///a point in time as the table shows it, or a dash if there is none
+fn time(time: Option<DateTime::<Utc>>) -> String {
+	time.map(|x| x.format("%Y-%m-%d %H:%M:%S UTC").to_string()).unwrap_or_else(|| "-".to_string())
+}
+
+///what the job is doing at `now`
+fn status(job: &Job, now: &DateTime::<Utc>) -> &'static str {
+	if job.is_running(now) {
+		"Running"
+	} else if job.failures > 0 {
+		"Failing"
+	} else if job.last_finished_at.is_some() {
+		"OK"
+	} else {
+		"Not run yet"
+	}
+}
+
+/// The background jobs with their schedule and the outcome of their last run, for admins.
+#[component]
+pub fn JobsPage(cx: Scope) -> impl IntoView {
+	let jobs = create_resource(cx, || (), move |_| async move { flatten(list_jobs(cx).await) });
+
+	let rows = move || jobs.read(cx).map(|x| match x {
+		Ok(jobs) => {
+			let now = Utc::now();
+			view! {cx,
+				<table class="jobs-table">
+					<tr>
+						<th>"Job"</th>
+						<th>"Status"</th>
+						<th>"Last started"</th>
+						<th>"Last finished"</th>
+						<th>"Next run"</th>
+						<th>"Failures"</th>
+						<th>"Last error"</th>
+					</tr>
+					{jobs.into_iter().map(|job| view! {cx,
+						<tr>
+							<td>{job.name.clone()}</td>
+							<td>{status(&job, &now)}</td>
+							<td>{time(job.last_started_at)}</td>
+							<td>{time(job.last_finished_at)}</td>
+							<td>{time(Some(job.next_run))}</td>
+							<td>{job.failures}</td>
+							<td class="jobs-error">{job.last_error.unwrap_or_default()}</td>
+						</tr>
+					}).collect::<Vec<_>>()}
+				</table>
+			}.into_view(cx)
+		}
+		Err(x) => view! {cx, <p class="jobs-error">{x.to_string()}</p>}.into_view(cx),
+	});
+
+	view! {cx,
+		<div class="jobs">
+			<h1>"Background jobs"</h1>
+			<Suspense fallback=move || view! {cx, <p>"Loading..."</p>}>
+				{rows}
+			</Suspense>
+			<button on:click=move |_| jobs.refetch()>"Refresh"</button>
+		</div>
+	}
+}
@@ -0,0 +1,45 @@
+use leptos::*;
+use leptos_router::*;
+
+#[component]
+pub fn LoginPage(cx: Scope) -> impl IntoView {
+	let query = use_query_map(cx);
+	let error = move || query.with(|x| x.get("error").map(|_| "Wrong username or password."));
+
+	view! {cx,
+		<div class="auth">
+			<h1>"Log in"</h1>
+			// a plain form, so logging in also works before the app has hydrated
+			<form method="post" action="/auth/login">
+				<label>"Username" <input type="text" name="username" autocomplete="username" required/></label>
+				<label>"Password" <input type="password" name="password" autocomplete="current-password" required/></label>
+				{move || error().map(|x| view! {cx, <p class="auth-error">{x}</p>})}
+				<button type="submit">"Log in"</button>
+			</form>
+			<p>"No account yet? " <A href="/signup">"Sign up"</A></p>
+		</div>
+	}
+}
+
+#[component]
+pub fn SignupPage(cx: Scope) -> impl IntoView {
+	let query = use_query_map(cx);
+	let error = move || query.with(|x| x.get("error").map(|x| match x.as_str() {
+		"taken" => "That username is already taken.",
+		"password" => "Passwords need at least 8 characters.",
+		_ => "Pick a username.",
+	}));
+
+	view! {cx,
+		<div class="auth">
+			<h1>"Sign up"</h1>
+			<form method="post" action="/auth/signup">
+				<label>"Username" <input type="text" name="username" autocomplete="username" required/></label>
+				<label>"Password" <input type="password" name="password" autocomplete="new-password" minlength="8" required/></label>
+				{move || error().map(|x| view! {cx, <p class="auth-error">{x}</p>})}
+				<button type="submit">"Sign up"</button>
+			</form>
+			<p>"Already have an account? " <A href="/login">"Log in"</A></p>
+		</div>
+	}
+}
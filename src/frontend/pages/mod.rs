@@ -1,3 +1,11 @@
+pub mod agenda;
+pub mod categories;
 pub mod day;
 pub mod home;
+pub mod jobs;
+pub mod login;
 pub mod month;
+pub mod tasks;
+pub mod user;
+pub mod week;
+pub mod year;
@@ -1,67 +1,117 @@
 use leptos::*;
+use leptos_router::*;
 use time::*;
 
+use crate::common::api::calendar::{list_items, CalendarItem, ItemKind};
+use crate::common::api::flatten;
+use crate::frontend::calendar::{calendar_settings, dates_from, dates_span, day_span, first_of_week, is_all_day, weekday_names};
+use crate::frontend::categories::visible_items;
+use crate::frontend::navigation::{use_route_date, CalendarRoute, CalendarView};
+
+/// Number of items a day cell shows before the rest are summarised as "+N more".
+pub const MAX_ITEMS_PER_DAY: usize = 4;
+
+/// The month of the route's date, `/month` showing the current one.
+#[component]
+pub fn MonthPage(cx: Scope) -> impl IntoView {
+	let date = use_route_date(cx);
+	move || view! {cx, <MonthView year=date.get().year() month=date.get().month()/>}
+}
+
 #[component]
 pub fn MonthView(cx: Scope, year: i32, month: Month) -> impl IntoView {
 	// Get the Date of the first day of the month...
 	let first_of_month = Date::from_calendar_date(year, month, 1).unwrap();
-	// ...and the Date of the first day of the week...
-	let mut first_of_week = first_of_month;
-	//TODO: maybe create a setting for the first day of the week.
-	while first_of_week.weekday() != Weekday::Monday {
-		first_of_week = first_of_week.previous_day().unwrap();
-	}
-	// ...such that we can now fill a vec with 35 dates, starting from the first of the week.
-	let mut current_date = first_of_week;
-	let mut weeks = Vec::with_capacity(5);
-	for _rows in 0..6 {
-		let mut days_in_week = Vec::with_capacity(7);
-		for _days_in_row in 0..7 {
-			days_in_week.push(view! {cx, <Day date=current_date/>});
-			current_date = current_date.next_day().unwrap();
+
+	// ...and, once the user's first day of the week is known, the Date of the first day of the week,
+	// such that we can fill the grid with 42 dates. One request for the whole grid, the cells pick
+	// out their own items by the days of the user's time zone.
+	let grid = create_resource(cx, || (), move |_| async move {
+		let settings = calendar_settings(cx).await;
+		let first_of_week = first_of_week(first_of_month, settings.week_start);
+		let span = dates_span(first_of_week, first_of_week.checked_add(Duration::days(41)).unwrap_or(Date::MAX), settings.zone);
+		// When the items cannot be loaded the cells stay empty
+		let items = flatten(list_items(cx, span.start, span.end).await).unwrap_or_default();
+		(settings, items)
+	});
+
+	let weeks = move || grid.read(cx).map(|(settings, items)| {
+		let week_start = settings.week_start;
+		let items = visible_items(cx, items);
+		let first_of_week = first_of_week(first_of_month, week_start);
+		// the grid ends early when it reaches the last date there is
+		let mut dates = dates_from(first_of_week, 42).into_iter();
+		let mut weeks = Vec::with_capacity(6);
+		for _rows in 0..6 {
+			let mut days_in_week = Vec::with_capacity(7);
+			let mut last_date = None;
+			for current_date in dates.by_ref().take(7) {
+				let day = day_span(current_date, settings.zone);
+				// all-day items go first, as banners across the top of the cell
+				let (mut day_items, timed): (Vec<_>, Vec<_>) = items.iter()
+					.filter(|x| x.timespan.overlaps(&day))
+					.cloned()
+					.partition(|x| is_all_day(x, &day));
+				day_items.extend(timed);
+				days_in_week.push(view! {cx, <Day date=current_date in_month={current_date.month() == month} items=day_items/>});
+				last_date = Some(current_date);
+			}
+			let Some(last_date) = last_date else { break };
+			// weeks.push(view!{cx, <p class="empty"></p> {days_in_week}}); // Uncomment to disable week numbers. TODO: make config option.
+			weeks.push(view! {cx, <p class="weeknumber">{last_date.iso_week()}</p> {days_in_week}});
+			// Comment to disable week numbers.
 		}
-		// weeks.push(view!{cx, <p class="empty"></p> {days_in_week}}); // Uncomment to disable week numbers. TODO: make config option.
-		weeks.push(view! {cx, <p class="weeknumber">{current_date.iso_week()}</p> {days_in_week}});
-		// Comment to disable week numbers.
-	}
 
-	view! {cx,
-		<div class="monthview">
+		let names = weekday_names(week_start).into_iter().map(|x| view! {cx, <p>{x}</p>}).collect::<Vec<_>>();
+		view! {cx,
 			<p>"Week"</p> // Comment to disable week numbers.
 			// <p></p> // Uncomment to disable week numbers. TODO: make config option.
-			<p>"Mon"</p>
-			<p>"Tue"</p>
-			<p>"Wed"</p>
-			<p>"Thi"</p>
-			<p>"Fri"</p>
-			<p>"Sat"</p>
-			<p>"Sun"</p>
+			{names}
 			{weeks}
+		}
+	});
+
+	view! {cx,
+		<div class="monthview">
+			<Suspense fallback=move || ()>
+				{weeks}
+			</Suspense>
 		</div>
 	}
 }
 
 #[component]
-pub fn Day(cx: Scope, date: Date) -> impl IntoView {
-	let items_fill = (0..5)
+pub fn Day(cx: Scope, date: Date, in_month: bool, items: Vec<CalendarItem>) -> impl IntoView {
+	// A full cell keeps its last line for the overflow count
+	let shown = if items.len() > MAX_ITEMS_PER_DAY { MAX_ITEMS_PER_DAY - 1 } else { items.len() };
+	let hidden = items.len() - shown;
+	let items_fill = items
 		.into_iter()
-		.map(
-			|n| view! {cx, <DayEvent description={ format!("TODO: {n}")} color="#1E70F0".to_string()/> },
-		)
+		.take(shown)
+		.map(|item| view! {cx, <DayEvent item/> })
 		.collect::<Vec<_>>();
+
 	view! {cx,
-		<div class="monthview-day">
-			<p class="monthview-day-datum">{date.day()}</p>
+		<div class="monthview-day" class:other-month={!in_month}>
+			<A class="monthview-day-datum" href=CalendarRoute::new(CalendarView::Day, date).path()>{date.day()}</A>
 			<div class="monthview-day-items-wrapper">
 				{items_fill}
+				{(hidden > 0).then(|| view! {cx, <p class="monthview-day-more">{format!("+{hidden} more")}</p>})}
 			</div>
 		</div>
 	}
 }
 
 #[component]
-pub fn DayEvent(cx: Scope, description: String, color: String) -> impl IntoView {
+pub fn DayEvent(cx: Scope, item: CalendarItem) -> impl IntoView {
+	let completed = item.kind == ItemKind::Task { completed: true };
+	let class = match item.kind {
+		ItemKind::Event => "monthview-day-event",
+		ItemKind::Task { .. } => "monthview-day-event monthview-day-task",
+	};
 	view! {cx,
-		<p class="monthview-day-event" style=format!("background-color: {color}")>{description}</p>
+		<p class=class class:completed=completed class:monthview-day-banner=item.all_day style=format!("background-color: {}", item.css_color()) title=item.name.clone()>
+			{item.name}
+		</p>
 	}
 }
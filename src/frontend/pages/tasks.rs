@@ -0,0 +1,136 @@
+use chrono::{Duration, NaiveDate, Utc};
+use leptos::*;
+use surrealdb::sql::Uuid;
+
+use crate::common::api::categories::list_categories;
+use crate::common::api::flatten;
+use crate::common::api::tasks::{complete_tasks, is_overdue, list_open_tasks, reschedule_tasks, TaskFilter};
+use crate::common::model::Task;
+use crate::frontend::calendar::use_zone;
+use crate::frontend::categories::{option_value, picked_category};
+
+///the day picked in a date input, `None` while it is empty
+fn input_day(value: &str) -> Option<NaiveDate> {
+	NaiveDate::parse_from_str(value, "%Y-%m-%d").ok()
+}
+
+/// Every task that is not done yet, filtered by category and due date, with actions on several
+/// tasks at once.
+#[component]
+pub fn TasksPage(cx: Scope) -> impl IntoView {
+	let (category, set_category) = create_signal(cx, None::<Uuid>);
+	let (from, set_from) = create_signal(cx, None);
+	let (until, set_until) = create_signal(cx, None);
+	let (latest_first, set_latest_first) = create_signal(cx, false);
+	// Bumped after every bulk action, to load the tasks again
+	let (version, set_version) = create_signal(cx, 0);
+	let (error, set_error) = create_signal(cx, None::<String>);
+	let selected = create_rw_signal(cx, Vec::<Uuid>::new());
+
+	// the days picked start and end at midnight in the user's time zone
+	let filter = move || TaskFilter {
+		category: category.get(),
+		due_from: from.get().map(|x| use_zone(cx).start_of_day(&x)),
+		// the last day picked is included
+		due_until: until.get().map(|x: NaiveDate| use_zone(cx).start_of_day(&(x + Duration::days(1)))),
+		latest_first: latest_first.get(),
+	};
+	let categories = create_resource(cx, || (), move |_| async move {
+		flatten(list_categories(cx).await).unwrap_or_default()
+	});
+	let tasks = create_resource(cx, move || (filter(), version.get()), move |(filter, _)| async move {
+		flatten(list_open_tasks(cx, filter).await)
+	});
+
+	let finish = move |result: Result<Vec<Task>, String>| {
+		set_error.set(result.err());
+		selected.set(Vec::new());
+		set_version.update(|x| *x += 1);
+	};
+	let complete = create_action(cx, move |tasks: &Vec<Uuid>| {
+		let tasks = tasks.clone();
+		async move { finish(flatten(complete_tasks(cx, tasks).await).map_err(|x| x.to_string())) }
+	});
+	let reschedule = create_action(cx, move |(tasks, days): &(Vec<Uuid>, i32)| {
+		let (tasks, days) = (tasks.clone(), *days);
+		async move { finish(flatten(reschedule_tasks(cx, tasks, days).await).map_err(|x| x.to_string())) }
+	});
+	let nothing_selected = move || selected.with(|x| x.is_empty());
+
+	let category_options = move || categories.read(cx).map(|categories| categories
+		.into_iter()
+		.map(|x| view! {cx, <option value=option_value(&x)>{x.name}</option>})
+		.collect::<Vec<_>>()
+	);
+
+	let rows = move || tasks.read(cx).map(|tasks| match tasks {
+		Ok(tasks) if tasks.is_empty() => view! {cx, <p class="tasks-empty">"No unfinished tasks."</p>}.into_view(cx),
+		Ok(tasks) => {
+			let (zone, now) = (use_zone(cx), Utc::now());
+			tasks.into_iter()
+				.map(|task| view! {cx, <TaskRow overdue=is_overdue(&task, &zone, &now) task selected/>})
+				.collect::<Vec<_>>()
+				.into_view(cx)
+		}
+		Err(x) => view! {cx, <p class="tasks-error">{x.to_string()}</p>}.into_view(cx),
+	});
+
+	view! {cx,
+		<div class="tasks">
+			<div class="tasks-filters">
+				<select on:change=move |ev| {
+					match picked_category(&event_target_value(&ev)) {
+						Ok(category) => set_category.set(category),
+						Err(x) => set_error.set(Some(x)),
+					}
+				}>
+					<option value="">"All categories"</option>
+					<Suspense fallback=move || ()>{category_options}</Suspense>
+				</select>
+				<label>"Due from " <input type="date" on:change=move |ev| set_from.set(input_day(&event_target_value(&ev)))/></label>
+				<label>"until " <input type="date" on:change=move |ev| set_until.set(input_day(&event_target_value(&ev)))/></label>
+				<select on:change=move |ev| set_latest_first.set(event_target_value(&ev) == "latest")>
+					<option value="soonest">"Soonest due first"</option>
+					<option value="latest">"Latest due first"</option>
+				</select>
+			</div>
+			<div class="tasks-actions">
+				<button disabled=nothing_selected on:click=move |_| complete.dispatch(selected.get())>"Mark done"</button>
+				<button disabled=nothing_selected on:click=move |_| reschedule.dispatch((selected.get(), 1))>"Postpone a day"</button>
+				<button disabled=nothing_selected on:click=move |_| reschedule.dispatch((selected.get(), 7))>"Postpone a week"</button>
+				{move || error.get().map(|x| view! {cx, <p class="tasks-error">{x}</p>})}
+			</div>
+			<Suspense fallback=move || view! {cx, <p>"Loading..."</p>}>
+				{rows}
+			</Suspense>
+		</div>
+	}
+}
+
+#[component]
+pub fn TaskRow(cx: Scope, task: Task, overdue: bool, selected: RwSignal<Vec<Uuid>>) -> impl IntoView {
+	let uuid = task.uuid.clone();
+	// a task due on a date shows only its last date
+	let due = match task.timespan.dates() {
+		Some((_, last)) => last.format("%a %-d %b %Y").to_string(),
+		None => use_zone(cx).local(&task.timespan.end).format("%a %-d %b %Y, %H:%M").to_string(),
+	};
+	// The rows are built anew whenever the tasks load after an action, so the boxes start out unticked
+	let toggle = move |ev| {
+		let checked = event_target_checked(&ev);
+		selected.update(|x| {
+			x.retain(|y| *y != uuid);
+			if checked {
+				x.push(uuid.clone());
+			}
+		});
+	};
+
+	view! {cx,
+		<label class="tasks-row" class:overdue=overdue>
+			<input type="checkbox" on:change=toggle/>
+			<span class="tasks-row-name">{task.name}</span>
+			<span class="tasks-row-due">{due}</span>
+		</label>
+	}
+}
@@ -0,0 +1,123 @@
+use leptos::*;
+use leptos_router::*;
+
+use crate::common::api::{flatten, get_current_user};
+use crate::common::api::settings::{set_email, set_time_zone, set_week_start};
+use crate::common::model::WeekStart;
+use crate::frontend::calendar::UserZone;
+
+const WEEKDAYS: [chrono::Weekday; 7] = [
+	chrono::Weekday::Mon,
+	chrono::Weekday::Tue,
+	chrono::Weekday::Wed,
+	chrono::Weekday::Thu,
+	chrono::Weekday::Fri,
+	chrono::Weekday::Sat,
+	chrono::Weekday::Sun,
+];
+
+#[component]
+pub fn UserPage(cx: Scope) -> impl IntoView {
+	let user = create_resource(cx, || (), move |_| get_current_user(cx));
+	let query = use_query_map(cx);
+	let delete_error = move || query.with(|x| x.get("error").map(|_| "Wrong password, the account was not deleted."));
+	let change_week_start = create_action(cx, move |week_start: &WeekStart| set_week_start(cx, *week_start));
+	let change_time_zone = create_action(cx, move |time_zone: &String| {
+		let time_zone = time_zone.clone();
+		async move {
+			// the views pick the new zone up from the context
+			if flatten(set_time_zone(cx, time_zone).await).is_ok() {
+				if let Some(zone) = use_context::<UserZone>(cx) {
+					zone.zone.refetch();
+				}
+			}
+		}
+	});
+	let email_input = create_node_ref::<html::Input>(cx);
+	let change_email = create_action(cx, move |email: &String| {
+		let email = email.clone();
+		async move { flatten(set_email(cx, email).await) }
+	});
+	let email_status = move || change_email.value().get().map(|x| match x {
+		Ok(_) => view! {cx, <p>"Saved."</p>},
+		Err(x) => view! {cx, <p class="auth-error">{x.to_string()}</p>},
+	});
+
+	view! {cx,
+		<div class="auth">
+			<Suspense fallback=move || view! {cx, <p>"Loading..."</p>}>
+				{move || user.read(cx).map(|x| match x {
+					Ok(Some(user)) => view! {cx,
+						<h1>{user.name}</h1>
+						<p>"Member since " {user.joined_at.format("%B %-d, %Y").to_string()}</p>
+						<p>"Subscribe to your calendar from other apps with "
+							<a href={user.feed_url.clone()}>"this secret link"</a>
+							". Anyone with the link can read your agenda."
+						</p>
+						<label>"Weeks start on "
+							<select on:change=move |ev| {
+								if let Ok(weekday) = event_target_value(&ev).parse() {
+									change_week_start.dispatch(WeekStart(weekday));
+								}
+							}>
+								{WEEKDAYS.into_iter().map(|x| view! {cx,
+									<option value=x.to_string() selected={x == user.week_start.0}>{weekday_name(x)}</option>
+								}).collect::<Vec<_>>()}
+							</select>
+						</label>
+						<label>"Time zone "
+							<select on:change=move |ev| change_time_zone.dispatch(event_target_value(&ev))>
+								{chrono_tz::TZ_VARIANTS.iter().map(|x| view! {cx,
+									<option value=x.name() selected={*x == user.time_zone.0}>{x.name()}</option>
+								}).collect::<Vec<_>>()}
+							</select>
+						</label>
+						<form on:submit=move |ev| {
+							ev.prevent_default();
+							if let Some(input) = email_input.get() {
+								change_email.dispatch(input.value());
+							}
+						}>
+							<label>"E-mail address for reminders"
+								<input type="email" node_ref=email_input value=user.email.unwrap_or_default() placeholder="No reminders"/>
+							</label>
+							{email_status}
+							<button type="submit">"Save address"</button>
+						</form>
+						<form method="post" action="/calendar/feed/reset">
+							<button type="submit">"Replace calendar link"</button>
+						</form>
+						<form method="post" action="/auth/logout">
+							<button type="submit">"Log out"</button>
+						</form>
+						{user.admin.then(|| view! {cx, <p><A href="/admin/jobs">"Background jobs"</A></p>})}
+						<p><a href="/user/export.json" download>"Download all your data"</a>" as a JSON file."</p>
+						<form method="post" action="/auth/delete" class="auth-danger">
+							<label>"Delete your account with all its tasks, events and categories. This cannot be undone."
+								<input type="password" name="password" autocomplete="current-password" placeholder="Password" required/>
+							</label>
+							{move || delete_error().map(|x| view! {cx, <p class="auth-error">{x}</p>})}
+							<button type="submit">"Delete account"</button>
+						</form>
+					}.into_view(cx),
+					_ => view! {cx,
+						<p>"You are not logged in."</p>
+						<A href="/login">"Log in"</A>
+					}.into_view(cx),
+				})}
+			</Suspense>
+		</div>
+	}
+}
+
+fn weekday_name(weekday: chrono::Weekday) -> &'static str {
+	match weekday {
+		chrono::Weekday::Mon => "Monday",
+		chrono::Weekday::Tue => "Tuesday",
+		chrono::Weekday::Wed => "Wednesday",
+		chrono::Weekday::Thu => "Thursday",
+		chrono::Weekday::Fri => "Friday",
+		chrono::Weekday::Sat => "Saturday",
+		chrono::Weekday::Sun => "Sunday",
+	}
+}
@@ -0,0 +1,71 @@
+use leptos::*;
+use time::*;
+
+use crate::common::api::calendar::list_items;
+use crate::common::api::flatten;
+use crate::frontend::navigation::use_route_date;
+use crate::frontend::calendar::{calendar_settings, dates_from, dates_span, day_span, first_of_week, is_all_day, weekday_names};
+use crate::frontend::categories::visible_items;
+use crate::frontend::pages::day::{DayTimeline, DayTimelineProps};
+use crate::frontend::pages::month::{DayEvent, DayEventProps};
+
+/// The week containing the route's date, `/week` showing the current one.
+#[component]
+pub fn WeekPage(cx: Scope) -> impl IntoView {
+	let date = use_route_date(cx);
+	move || view! {cx, <WeekView date=date.get()/>}
+}
+
+/// The week containing `date`.
+#[component]
+pub fn WeekView(cx: Scope, date: Date) -> impl IntoView {
+	let data = create_resource(cx, || (), move |_| async move {
+		let settings = calendar_settings(cx).await;
+		let first = first_of_week(date, settings.week_start);
+		let span = dates_span(first, first.checked_add(Duration::days(6)).unwrap_or(Date::MAX), settings.zone);
+		// When the items cannot be loaded the week stays empty
+		let items = flatten(list_items(cx, span.start, span.end).await).unwrap_or_default();
+		(settings, items)
+	});
+
+	let days = move || data.read(cx).map(|(settings, items)| {
+		let (week_start, zone) = (settings.week_start, settings.zone);
+		let items = visible_items(cx, items);
+		let first = first_of_week(date, week_start);
+		let dates = dates_from(first, 7);
+
+		let headers = dates.iter().zip(weekday_names(week_start)).map(|(date, name)| view! {cx,
+			<p class="weekview-day-header">{format!("{name} {}", date.day())}</p>
+		}).collect::<Vec<_>>();
+
+		let mut all_day = Vec::with_capacity(7);
+		let mut timelines = Vec::with_capacity(7);
+		for date in dates {
+			let day = day_span(date, zone);
+			let (whole, timed): (Vec<_>, Vec<_>) = items.iter()
+				.filter(|x| x.timespan.overlaps(&day))
+				.cloned()
+				.partition(|x| is_all_day(x, &day));
+			all_day.push(view! {cx,
+				<div class="weekview-allday-day">
+					{whole.into_iter().map(|item| view! {cx, <DayEvent item/>}).collect::<Vec<_>>()}
+				</div>
+			});
+			timelines.push(view! {cx, <DayTimeline date zone items=timed/>});
+		}
+
+		view! {cx,
+			<div class="weekview-days">{headers}</div>
+			<div class="weekview-allday">{all_day}</div>
+			<div class="weekview-timelines">{timelines}</div>
+		}
+	});
+
+	view! {cx,
+		<div class="weekview">
+			<Suspense fallback=move || ()>
+				{days}
+			</Suspense>
+		</div>
+	}
+}
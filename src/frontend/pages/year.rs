@@ -0,0 +1,101 @@
+use leptos::*;
+use time::*;
+
+use crate::common::api::calendar::{list_day_activity, DayActivity};
+use crate::common::api::flatten;
+use crate::frontend::calendar::{calendar_settings, first_of_week, naive_date, weekday_names};
+use crate::frontend::navigation::{use_route_date, CalendarRoute, CalendarView};
+
+///how strongly a day with `count` items is shaded, from 0 to 1
+pub fn shade(count: usize) -> f64 {
+	match count {
+		0 => 0.0,
+		1 => 0.35,
+		2 => 0.55,
+		3 | 4 => 0.75,
+		_ => 1.0,
+	}
+}
+
+/// The year of the route's date, `/year` showing the current one.
+#[component]
+pub fn YearPage(cx: Scope) -> impl IntoView {
+	let date = use_route_date(cx);
+	move || view! {cx, <YearView year=date.get().year()/>}
+}
+
+/// Twelve small months, each day shaded by how busy it is.
+#[component]
+pub fn YearView(cx: Scope, year: i32) -> impl IntoView {
+	let first = Date::from_calendar_date(year, Month::January, 1).unwrap();
+	let days = if time::util::is_leap_year(year) { 366 } else { 365 };
+
+	let data = create_resource(cx, || (), move |_| async move {
+		let week_start = calendar_settings(cx).await.week_start;
+		// When the activity cannot be loaded the year is shown without shading
+		let activity = flatten(list_day_activity(cx, naive_date(first), days).await).unwrap_or_default();
+		(week_start, activity)
+	});
+
+	let months = move || data.read(cx).map(|(week_start, activity)| {
+		let mut month = Month::January;
+		let mut months = Vec::with_capacity(12);
+		for _ in 0..12 {
+			let first_of_month = Date::from_calendar_date(year, month, 1).unwrap();
+			// Leading blanks up to the first of the month, as in the month view
+			let blanks = (first_of_month - first_of_week(first_of_month, week_start)).whole_days();
+			let names = weekday_names(week_start).into_iter().map(|x| view! {cx, <p>{x[..1].to_string()}</p>}).collect::<Vec<_>>();
+			let cells = (0..31)
+				.map(|n| first_of_month + Duration::days(n))
+				.take_while(|x| x.month() == month)
+				.map(|date| {
+					let activity = activity.get(date.ordinal() as usize - 1).cloned().unwrap_or_default();
+					view! {cx, <YearDay date activity/>}
+				})
+				.collect::<Vec<_>>();
+
+			months.push(view! {cx,
+				<div class="yearview-month">
+					<a class="yearview-month-name" href=CalendarRoute::new(CalendarView::Month, first_of_month).path()>{month.to_string()}</a>
+					<div class="yearview-month-grid">
+						{names}
+						{(0..blanks).map(|_| view! {cx, <p></p>}).collect::<Vec<_>>()}
+						{cells}
+					</div>
+				</div>
+			});
+			month = month.next();
+		}
+		months
+	});
+
+	view! {cx,
+		<div class="yearview">
+			<Suspense fallback=move || ()>
+				{months}
+			</Suspense>
+		</div>
+	}
+}
+
+#[component]
+pub fn YearDay(cx: Scope, date: Date, activity: DayActivity) -> impl IntoView {
+	let style = activity.color.map(|color| format!(
+		"background-color: rgba({}, {}, {}, {});",
+		(color >> 16) & 0xFF,
+		(color >> 8) & 0xFF,
+		color & 0xFF,
+		shade(activity.count),
+	));
+	let title = match activity.count {
+		0 => "Nothing planned".to_string(),
+		1 => "1 item".to_string(),
+		n => format!("{n} items"),
+	};
+
+	view! {cx,
+		<a class="yearview-day" href=CalendarRoute::new(CalendarView::Day, date).path() style=style title=title>
+			{date.day()}
+		</a>
+	}
+}
@@ -1,76 +1,51 @@
-use std::env;
 use actix_files::Files;
 use actix_web::*;
-use chrono::Utc;
-use gressus::backend::database::db_requests::{add_event, add_user, user_id_from_name, add_task, get_tasks, get_events, change_username, delete_user};
-use gressus::common::model::User;
 use leptos::*;
 use leptos_actix::{generate_route_list, LeptosRoutes};
-use surrealdb::Surreal;
-use surrealdb::engine::remote::ws::Ws;
-use surrealdb::opt::auth::Root;
 use gressus::app::*;
-use surrealdb::sql::{Datetime, Uuid};
 
 #[cfg(feature = "ssr")]
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
     // Connect to the database server
 
-    use gressus::backend::database::db_requests::{add_category, change_password, task_edit_name, task_set_completion, task_edit_desc, event_edit_name, event_edit_desc, get_categories, get_category};
-    let db = Surreal::new::<Ws>("127.0.0.1:8000").await.unwrap();
+    use gressus::backend::database::connection::{connect, DatabaseEngine};
+    use gressus::backend::database::migrations::migrate;
+    use gressus::backend::database::store::Store;
+    use gressus::backend::auth::{delete_account, login, logout, signup};
+    use gressus::backend::jobs::{DeleteExpiredSessions, JobRunner};
+    use gressus::backend::reminders::{Mailer, SendReminders, SmtpConfig};
+    use gressus::backend::handlers::{calendar_feed, calendar_feed_reset, calendar_import, data_export, MAX_UPLOAD_BYTES};
+    use gressus::common::api::register_server_functions;
+    use std::sync::Arc;
+    // Pick the engine (remote server, embedded memory or embedded file) from the environment
+    let engine = DatabaseEngine::from_env().unwrap();
+    let db = connect(&engine).await.unwrap();
+    // Bring the schema up to date, refusing to run against a newer one
+    migrate(&db).await.unwrap();
+    
+    // Demo data is only added when asked for, and never to a database that outlives the server,
+    // where it would give the deployment a known login
+    if std::env::var("DEMO_DATA").is_ok_and(|x| x == "1") {
+        match engine {
+            DatabaseEngine::Memory => seed_demo(&db).await.unwrap(),
+            _ => log::warn!("DEMO_DATA is only used with DATABASE_ENGINE=memory, no demo account was added"),
+        }
+    }
 
-    // Signin to database
-    db.signin(Root {
-        username: &env::var("DATABASE_USER").unwrap(),
-        password: &env::var("DATABASE_PASS").unwrap(),
-    })
-    .await.unwrap();
+	// Handlers reach the database through the Store trait
+	let store: Arc<dyn Store> = Arc::new(db.clone());
 
-    // Select a specific namespace / database
-    db.use_ns("main").use_db("main").await.unwrap();
-    
-    add_user(&db, "micha", &"pass".as_bytes().to_vec()).await.unwrap();
-    add_user(&db, "heiko", &"pass".as_bytes().to_vec()).await.unwrap();
-    let micha_id = user_id_from_name(&db, "micha").await.unwrap();
-    let heiko_id = user_id_from_name(&db, "heiko").await.unwrap();
-    let start = &Datetime::from(Utc::now());
-    let end = &Datetime::from(Utc::now());
-    add_task(&db, "task1", "test1", start, end, &Uuid::new(), &micha_id).await.unwrap();
-    add_task(&db, "task2", "test2", start, end, &Uuid::new(), &micha_id).await.unwrap();
-    add_task(&db, "task3", "test3", start, end, &Uuid::new(), &heiko_id).await.unwrap();
-    add_task(&db, "task4", "test4", start, end, &Uuid::new(), &heiko_id).await.unwrap();
-    add_event(&db, "event1", "test5", start, end, &Uuid::new(), &micha_id).await.unwrap();
-    add_event(&db, "event2", "test6", start, end, &Uuid::new(), &micha_id).await.unwrap();
-    add_event(&db, "event3", "test7", start, end, &Uuid::new(), &heiko_id).await.unwrap();
-    add_event(&db, "event4", "test8", start, end, &Uuid::new(), &heiko_id).await.unwrap();
-    let mut micha_tasks = get_tasks(&db, &micha_id).await.unwrap();
-    let mut heiko_events = get_events(&db, &heiko_id).await.unwrap();
-    let test_task = micha_tasks.get(0).unwrap().uuid.clone();
-    let test_event = heiko_events.get(0).unwrap().uuid.clone();
-    task_edit_name(&db, &test_task, "new name!").await.unwrap();
-    task_set_completion(&db, &test_task, true).await.unwrap();
-    task_edit_desc(&db, &test_task, "now it has a description!").await.unwrap();
-    event_edit_name(&db, &test_event, "new event!").await.unwrap();
-    event_edit_desc(&db, &test_event, "event description!").await.unwrap();
-    micha_tasks = get_tasks(&db, &micha_id).await.unwrap();
-    let heiko_tasks = get_tasks(&db, &heiko_id).await.unwrap();
-    let micha_events = get_events(&db, &micha_id).await.unwrap();
-    heiko_events = get_events(&db, &heiko_id).await.unwrap();
-    println!("micha's tasks: {:#?}", micha_tasks);
-    println!("micha's events: {:#?}", micha_events);
-    println!("heiko's tasks: {:#?}", heiko_tasks);
-    println!("heiko's events: {:#?}", heiko_events);
-    change_username(&db, &micha_id, "michah").await.unwrap();
-    change_password(&db, &micha_id, &"new_pass".as_bytes().to_vec()).await.unwrap();
-    delete_user(&db, &heiko_id).await.unwrap();
-    add_category(&db, "category1", 20, &micha_id).await.unwrap();
-    add_category(&db, "category2", 56, &micha_id).await.unwrap();
-    let categories = get_categories(&db, &micha_id).await.unwrap();
-    let micha_categories = vec![get_category(&db, categories.get(0).unwrap()).await.unwrap(), get_category(&db, categories.get(1).unwrap()).await.unwrap()];
-    println!("micha's categories: {:#?}", micha_categories);
-    let users: Vec<User> = db.select("users").await.unwrap();
-    println!("users: {:#?}", users);
+	// Periodic work runs in the background for as long as the server runs
+	let mut jobs = JobRunner::new(store.clone()).with(DeleteExpiredSessions);
+	match SmtpConfig::from_env().unwrap() {
+		Some(config) => jobs = jobs.with(SendReminders { mailer: Mailer::new(&config).unwrap() }),
+		None => log::warn!("SMTP_HOST is not set, no reminders will be sent"),
+	}
+	actix_web::rt::spawn(jobs.run());
+
+	// Server functions have to be registered explicitly outside of wasm
+	register_server_functions();
 
 	let conf = get_configuration(None).await.unwrap();
 	let addr = conf.leptos_options.site_addr;
@@ -82,7 +57,17 @@ async fn main() -> std::io::Result<()> {
 		let site_root = &leptos_options.site_root;
 
 		App::new()
+			.app_data(web::Data::from(store.clone()))
+			.app_data(web::PayloadConfig::new(MAX_UPLOAD_BYTES))
 			.service(actix_web::web::redirect("/", "/month"))
+			.service(calendar_feed)
+			.service(calendar_import)
+			.service(calendar_feed_reset)
+			.service(signup)
+			.service(login)
+			.service(logout)
+			.service(delete_account)
+			.service(data_export)
 			.route("/api/{tail:.*}", leptos_actix::handle_server_fns())
 			.leptos_routes(
 				leptos_options.to_owned(),
@@ -97,6 +82,34 @@ async fn main() -> std::io::Result<()> {
 	.await
 }
 
+/// Name and password of the demo account.
+#[cfg(feature = "ssr")]
+const DEMO_LOGIN: (&str, &str) = ("demo", "demo password");
+
+///adds a demo account with a few categories, tasks and events, unless it exists already
+#[cfg(feature = "ssr")]
+async fn seed_demo<C: surrealdb::Connection>(db: &surrealdb::Surreal<C>) -> Result<(), gressus::backend::database::db_error::DBerror> {
+    use chrono::{Duration, Utc};
+    use gressus::backend::database::db_error::DBerror;
+    use gressus::backend::database::db_requests::{add_category, add_event, add_task, add_user};
+    use gressus::common::model::Timespan;
+
+    let user = match add_user(db, DEMO_LOGIN.0, DEMO_LOGIN.1).await {
+        Ok(user) => user.uuid,
+        Err(DBerror::UserAlreadyExists(_)) => return Ok(()),
+        Err(x) => return Err(x),
+    };
+    let work = add_category(db, "Work", 0x2E8B57, &user).await?.uuid;
+    let home = add_category(db, "Home", 0xE07020, &user).await?.uuid;
+    let now = Utc::now();
+    add_event(db, "Team meeting", "weekly planning", &Timespan::new(&(now + Duration::hours(2)), &(now + Duration::hours(3)))?, &work, None, &user).await?;
+    add_event(db, "Dinner", "", &Timespan::new(&(now + Duration::days(1)), &(now + Duration::days(1) + Duration::hours(2)))?, &home, None, &user).await?;
+    add_task(db, "Write report", "", &Timespan::new(&now, &(now + Duration::days(2)))?, &work, None, &user).await?;
+    add_task(db, "Water the plants", "", &Timespan::new(&now, &(now + Duration::hours(5)))?, &home, None, &user).await?;
+    log::info!("added the demo account {}", DEMO_LOGIN.0);
+    Ok(())
+}
+
 #[cfg(not(feature = "ssr"))]
 pub fn main() {
 	// no client-side main function
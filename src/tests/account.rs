@@ -0,0 +1,98 @@
+use std::sync::Arc;
+use actix_web::{http::{header, StatusCode}, test, web, App};
+use chrono::Utc;
+use crate::{backend::{auth::{delete_account, login, SESSION_COOKIE}, database::{db_error::DBerror, memory::MemoryStore, store::Store}, handlers::{data_export, export_user_data, DataExport}, password::hash_token}, common::model::{LocalZone, ReminderDelivery, Session, Timespan}};
+
+/// Gives `user` a category with a task and an event in it, a reminder sent for the task, and a session.
+async fn fill(db: &dyn Store, user: &surrealdb::sql::Uuid, token: &str) {
+    let now = Utc::now();
+    let category = db.add_category("work", 0, user).await.unwrap().uuid;
+    let task = db.add_task("write", "", &Timespan::new(&now, &now).unwrap(), &category, None, user).await.unwrap().uuid;
+    db.add_event("meet", "", &Timespan::new(&now, &now).unwrap(), &category, None, user).await.unwrap();
+    let delivery = ReminderDelivery { key: ReminderDelivery::key_for(&task, &now, 10), item: task, user: user.clone(), occurs_at: now, minutes_before: 10, claimed_at: now, sent_at: Some(now) };
+    assert!(db.claim_reminder(&delivery, &now).await.unwrap());
+    db.add_session(&Session { token_hash: hash_token(token), user: user.clone(), created_at: now, expires_at: now + chrono::Duration::days(1) }).await.unwrap();
+}
+
+/// Deletes one of two users from `db`, checking nothing of theirs is left and the other keeps everything.
+async fn check_delete_user(db: &dyn Store) {
+    let micha = db.add_user("micha", "long enough").await.unwrap().uuid;
+    let heiko = db.add_user("heiko", "long enough").await.unwrap().uuid;
+    fill(db, &micha, "micha").await;
+    fill(db, &heiko, "heiko").await;
+    let categories = db.get_categories(&micha).await.unwrap();
+
+    assert_eq!(db.delete_user(&micha).await.unwrap().uuid, micha);
+    assert!(matches!(db.get_user(&micha).await, Err(DBerror::UserNotFound(_))));
+    assert!(db.get_tasks(&micha).await.unwrap().is_empty());
+    assert!(db.get_events(&micha).await.unwrap().is_empty());
+    assert!(matches!(db.get_category(&categories[0]).await, Err(DBerror::CategoryNotFound(_))));
+    assert!(matches!(db.get_session(&hash_token("micha")).await, Err(DBerror::SessionNotFound)));
+    assert!(db.get_reminder_deliveries(&micha).await.unwrap().is_empty());
+    assert!(matches!(db.delete_user(&micha).await, Err(DBerror::UserNotFound(_))));
+
+    assert_eq!(db.get_tasks(&heiko).await.unwrap().len(), 1);
+    assert_eq!(db.get_events(&heiko).await.unwrap().len(), 1);
+    assert_eq!(db.get_categories(&heiko).await.unwrap().len(), 1);
+    assert!(db.get_session(&hash_token("heiko")).await.is_ok());
+    assert_eq!(db.get_reminder_deliveries(&heiko).await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn test_delete_user_memory_store() {
+    check_delete_user(&MemoryStore::new()).await;
+}
+
+#[cfg(feature = "kv-mem")]
+#[tokio::test]
+async fn test_delete_user_embedded_surreal() {
+    use crate::backend::database::{connection::{connect, DatabaseEngine}, migrations::migrate};
+
+    let db = connect(&DatabaseEngine::Memory).await.unwrap();
+    migrate(&db).await.unwrap();
+    check_delete_user(&db).await;
+}
+
+#[actix_web::test]
+async fn test_export_and_delete_account() {
+    let store: Arc<dyn Store> = Arc::new(MemoryStore::new());
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::from(store.clone()))
+            .service(login)
+            .service(delete_account)
+            .service(data_export),
+    ).await;
+    let user = store.add_user("micha", "long enough").await.unwrap();
+    fill(store.as_ref(), &user.uuid, "other browser").await;
+    store.set_email(&user.uuid, Some("micha@example.org".to_string())).await.unwrap();
+    store.set_time_zone(&user.uuid, LocalZone(chrono_tz::Europe::Berlin)).await.unwrap();
+    let response = test::call_service(&app, test::TestRequest::post().uri("/auth/login").set_form([("username", "micha"), ("password", "long enough")]).to_request()).await;
+    let cookie = response.response().cookies().find(|x| x.name() == SESSION_COOKIE).unwrap().into_owned();
+
+    assert_eq!(test::call_service(&app, test::TestRequest::get().uri("/user/export.json").to_request()).await.status(), StatusCode::UNAUTHORIZED);
+    let response = test::call_service(&app, test::TestRequest::get().uri("/user/export.json").cookie(cookie.clone()).to_request()).await;
+    assert!(response.headers().get(header::CONTENT_DISPOSITION).unwrap().to_str().unwrap().starts_with("attachment"));
+    // credentials stay out of the export
+    let body = String::from_utf8(test::read_body(response).await.to_vec()).unwrap();
+    assert!(!body.contains("hashed_password") && !body.contains(&user.feed_token));
+    let export: DataExport = test::call_and_read_body_json(&app, test::TestRequest::get().uri("/user/export.json").cookie(cookie.clone()).to_request()).await;
+    assert_eq!(export, DataExport { exported_at: export.exported_at, ..export_user_data(store.as_ref(), &user.uuid, &Utc::now()).await.unwrap() });
+    assert_eq!((export.user.name.as_str(), export.categories.len(), export.tasks.len(), export.events.len()), ("micha", 1, 1, 1));
+    assert_eq!(export.user.email.as_deref(), Some("micha@example.org"));
+    assert_eq!(export.user.time_zone, LocalZone(chrono_tz::Europe::Berlin));
+    assert_eq!(export.reminder_deliveries.iter().map(|x| &x.item).collect::<Vec<_>>(), vec![&export.tasks[0].uuid]);
+
+    let delete = |password: &str| test::TestRequest::post().uri("/auth/delete").cookie(cookie.clone()).set_form([("password", password)]).to_request();
+    let response = test::call_service(&app, delete("wrong")).await;
+    assert_eq!(response.headers().get(header::LOCATION).unwrap(), "/user?error=password");
+    assert!(store.get_user(&user.uuid).await.is_ok());
+
+    let response = test::call_service(&app, delete("long enough")).await;
+    assert_eq!(response.headers().get(header::LOCATION).unwrap(), "/signup");
+    assert_eq!(response.response().cookies().find(|x| x.name() == SESSION_COOKIE).unwrap().value(), "");
+    assert!(store.get_user(&user.uuid).await.is_err());
+    assert!(store.get_tasks(&user.uuid).await.unwrap().is_empty());
+    assert!(store.get_session(&hash_token("other browser")).await.is_err());
+    assert_eq!(test::call_service(&app, delete("long enough")).await.status(), StatusCode::UNAUTHORIZED);
+}
@@ -0,0 +1,41 @@
+use chrono::{DateTime, TimeZone, Utc};
+use surrealdb::sql::Uuid;
+use time::{Date, Month};
+use crate::{common::{api::calendar::{CalendarItem, ItemKind}, model::{LocalZone, Timespan}}, frontend::pages::agenda::group_by_day};
+
+fn item(name: &str, kind: ItemKind, start: DateTime<Utc>, end: DateTime<Utc>) -> CalendarItem {
+    CalendarItem {
+        kind,
+        uuid: Uuid::new(),
+        recurrence_id: None,
+        name: name.to_string(),
+        timespan: Timespan::new(&start, &end).unwrap(),
+        all_day: false,
+        category: Uuid::new(),
+        color: 0,
+    }
+}
+
+#[test]
+fn test_group_by_day() {
+    let at = |day, hour| Utc.with_ymd_and_hms(2023, 5, day, hour, 0, 0).unwrap();
+    let items = [
+        item("trip", ItemKind::Event, at(1, 18), at(3, 10)),
+        item("report", ItemKind::Task { completed: false }, at(1, 9), at(1, 9)),
+        item("done", ItemKind::Task { completed: true }, at(2, 9), at(2, 10)),
+        item("later", ItemKind::Event, at(9, 9), at(9, 10)),
+    ];
+    let first = Date::from_calendar_date(2023, Month::May, 1).unwrap();
+    let grouped = group_by_day(&items, first, 5, LocalZone::default())
+        .into_iter()
+        .map(|(date, items)| (date.day(), items.iter().map(|x| x.name.clone()).collect::<Vec<_>>()))
+        .collect::<Vec<_>>();
+    // the trip shows on every day it lasts, days without items and finished tasks are left out
+    assert_eq!(grouped, vec![
+        (1, vec!["trip".to_string(), "report".to_string()]),
+        (2, vec!["trip".to_string()]),
+        (3, vec!["trip".to_string()]),
+    ]);
+    assert_eq!(group_by_day(&items, first + time::Duration::days(8), 1, LocalZone::default()).len(), 1);
+    assert!(group_by_day(&items, first, 0, LocalZone::default()).is_empty());
+}
@@ -0,0 +1,362 @@
+use std::sync::Arc;
+use actix_web::{test, web};
+use chrono::{DateTime, Duration, NaiveDate, TimeZone, Utc, Weekday};
+use leptos::{create_runtime, provide_context, raw_scope_and_disposer, RuntimeId, Scope};
+use surrealdb::sql::Uuid;
+use crate::{backend::{auth::start_session, database::{db_error::DBerror, memory::MemoryStore, store::Store}}, common::{model::{Job, LocalZone, Timespan, WeekStart, UNCATEGORISED, UNCATEGORISED_COLOR}, api::{ApiError, get_current_user, admin::list_jobs, settings::{set_email, set_time_zone, set_week_start}, calendar::{list_day_activity, list_items, ItemKind}, categories::{add_category, delete_category, edit_category, list_categories, set_category_hidden}, events::{add_event, delete_event, edit_event, list_events, set_event_reminders}, tasks::{add_task, complete_tasks, delete_task, due, edit_task, is_overdue, list_open_tasks, list_tasks, reschedule_tasks, set_task_completion, set_task_reminders, TaskFilter}}, recurrence::{OccurrenceOverride, Recurrence}}};
+
+///a timed span from `start` to `end`, not checked so that invalid ones can be sent
+fn span(start: DateTime<Utc>, end: DateTime<Utc>) -> Timespan {
+    Timespan { start, end, all_day: false }
+}
+
+///a scope whose request carries the session cookie of `user`, or no cookie at all
+///
+///dispose of the runtime before the test ends, the request must not outlive actix's thread locals
+async fn scope(runtime: RuntimeId, store: &Arc<dyn Store>, user: Option<&Uuid>) -> Scope {
+    let mut request = test::TestRequest::default().app_data(web::Data::from(store.clone()));
+    if let Some(user) = user {
+        request = request.cookie(start_session(store.as_ref(), user).await.unwrap());
+    }
+    let (cx, _) = raw_scope_and_disposer(runtime);
+    provide_context(cx, request.to_http_request());
+    cx
+}
+
+#[actix_web::test]
+async fn test_tasks_scoped_to_user() {
+    let runtime = create_runtime();
+    let store: Arc<dyn Store> = Arc::new(MemoryStore::new());
+    let micha = store.add_user("micha", "long enough").await.unwrap().uuid;
+    let other = store.add_user("other", "long enough").await.unwrap().uuid;
+    let cx = scope(runtime, &store, Some(&micha)).await;
+    let other_cx = scope(runtime, &store, Some(&other)).await;
+    let category = add_category(cx, "work".to_string(), 0xFF0000).await.unwrap().unwrap();
+    let start = Utc.with_ymd_and_hms(2023, 5, 1, 9, 0, 0).unwrap();
+    let end = start + Duration::hours(1);
+
+    let task = add_task(cx, "write".to_string(), String::new(), span(start, end), category.uuid.clone(), None).await.unwrap().unwrap();
+    assert_eq!(task.user, micha);
+    let listed = list_tasks(cx, start - Duration::days(1), end + Duration::days(1)).await.unwrap().unwrap();
+    assert_eq!(listed, vec![task.clone()]);
+    assert!(list_tasks(other_cx, start - Duration::days(1), end + Duration::days(1)).await.unwrap().unwrap().is_empty());
+
+    // another user's items and categories look as if they do not exist
+    assert_eq!(set_task_completion(other_cx, task.uuid.clone(), true).await.unwrap(), Err(ApiError::NotFound("task".to_string())));
+    assert_eq!(delete_task(other_cx, task.uuid.clone()).await.unwrap(), Err(ApiError::NotFound("task".to_string())));
+    assert_eq!(
+        add_task(other_cx, "steal".to_string(), String::new(), span(start, end), category.uuid.clone(), None).await.unwrap(),
+        Err(ApiError::NotFound("category".to_string()))
+    );
+    assert_eq!(edit_category(other_cx, category.uuid.clone(), "mine".to_string(), 0).await.unwrap(), Err(ApiError::NotFound("category".to_string())));
+    assert_eq!(set_category_hidden(other_cx, category.uuid.clone(), true).await.unwrap(), Err(ApiError::NotFound("category".to_string())));
+
+    assert!(set_task_completion(cx, task.uuid.clone(), true).await.unwrap().unwrap().completed);
+    let recurrence: Recurrence = "FREQ=DAILY;COUNT=3".parse().unwrap();
+    let edited = edit_task(cx, task.uuid.clone(), "rewrite".to_string(), "again".to_string(), Timespan::new(&start, &end).unwrap(), category.uuid.clone(), Some(recurrence.clone())).await.unwrap().unwrap();
+    assert_eq!((edited.name.as_str(), edited.completed, edited.recurrence), ("rewrite", true, Some(recurrence.clone())));
+    // an overridden occurrence ending before it starts is refused on every write
+    let mut backwards = recurrence;
+    backwards.overrides.push(OccurrenceOverride { recurrence_id: start, timespan: Timespan { start: end, end: start, all_day: false }, name: None, description: None });
+    assert_eq!(
+        edit_task(cx, task.uuid.clone(), "rewrite".to_string(), String::new(), Timespan::new(&start, &end).unwrap(), category.uuid.clone(), Some(backwards.clone())).await.unwrap(),
+        Err(ApiError::Invalid("invalid overridden occurrence: the end lies before the start".to_string()))
+    );
+    assert!(matches!(add_task(cx, "again".to_string(), String::new(), span(start, end), category.uuid.clone(), Some(backwards.clone())).await.unwrap(), Err(ApiError::Invalid(_))));
+    assert!(matches!(store.task_set_recurrence(&task.uuid, Some(backwards)).await, Err(DBerror::InvalidRecurrence(_))));
+    assert_eq!(store.get_tasks(&micha).await.unwrap().len(), 1);
+    assert_eq!(delete_task(cx, task.uuid.clone()).await.unwrap().unwrap().uuid, task.uuid);
+    assert_eq!(delete_task(cx, task.uuid).await.unwrap(), Err(ApiError::NotFound("task".to_string())));
+    runtime.dispose();
+}
+
+#[actix_web::test]
+async fn test_events_and_categories() {
+    let runtime = create_runtime();
+    let store: Arc<dyn Store> = Arc::new(MemoryStore::new());
+    let micha = store.add_user("micha", "long enough").await.unwrap().uuid;
+    let cx = scope(runtime, &store, Some(&micha)).await;
+    let start = Utc.with_ymd_and_hms(2023, 5, 1, 9, 0, 0).unwrap();
+
+    assert_eq!(add_category(cx, " ".to_string(), 0).await.unwrap(), Err(ApiError::Invalid("the name is empty".to_string())));
+    let category = add_category(cx, "home".to_string(), 0x00FF00).await.unwrap().unwrap();
+    let edited = edit_category(cx, category.uuid.clone(), "house".to_string(), 0x0000FF).await.unwrap().unwrap();
+    assert_eq!((edited.name.as_str(), edited.color), ("house", 0x0000FF));
+    assert_eq!(list_categories(cx).await.unwrap().unwrap(), vec![edited]);
+    assert!(set_category_hidden(cx, category.uuid.clone(), true).await.unwrap().unwrap().hidden);
+    assert!(list_categories(cx).await.unwrap().unwrap()[0].hidden);
+
+    assert!(matches!(
+        add_event(cx, "party".to_string(), String::new(), span(start, start - Duration::hours(1)), category.uuid.clone(), None).await.unwrap(),
+        Err(ApiError::Invalid(_))
+    ));
+    let event = add_event(cx, "party".to_string(), String::new(), span(start, start + Duration::hours(3)), category.uuid.clone(), None).await.unwrap().unwrap();
+    assert_eq!(list_events(cx, start, start + Duration::days(1)).await.unwrap().unwrap(), vec![event.clone()]);
+    assert!(list_events(cx, start + Duration::days(1), start + Duration::days(2)).await.unwrap().unwrap().is_empty());
+    delete_event(cx, event.uuid).await.unwrap().unwrap();
+
+    // all-day items come from the app too, and keep being whole dates
+    let holiday = Timespan::all_day(&start.date_naive(), &(start.date_naive() + Duration::days(1))).unwrap();
+    let event = add_event(cx, "holiday".to_string(), String::new(), holiday.clone(), category.uuid.clone(), None).await.unwrap().unwrap();
+    assert_eq!(list_events(cx, start, start + Duration::days(1)).await.unwrap().unwrap()[0].timespan, holiday);
+    let task = add_task(cx, "pack".to_string(), String::new(), holiday.clone(), category.uuid.clone(), None).await.unwrap().unwrap();
+    assert!(task.timespan.all_day);
+    assert!(matches!(
+        add_task(cx, "half".to_string(), String::new(), Timespan { end: start, ..holiday }, category.uuid.clone(), None).await.unwrap(),
+        Err(ApiError::Invalid(_))
+    ));
+    delete_task(cx, task.uuid).await.unwrap().unwrap();
+    delete_event(cx, event.uuid).await.unwrap().unwrap();
+    delete_category(cx, category.uuid, None).await.unwrap().unwrap();
+    let left = list_categories(cx).await.unwrap().unwrap();
+    assert_eq!(left.iter().map(|x| x.name.as_str()).collect::<Vec<_>>(), vec![UNCATEGORISED]);
+    runtime.dispose();
+}
+
+#[actix_web::test]
+async fn test_delete_category() {
+    let runtime = create_runtime();
+    let store: Arc<dyn Store> = Arc::new(MemoryStore::new());
+    let micha = store.add_user("micha", "long enough").await.unwrap().uuid;
+    let other = store.add_user("other", "long enough").await.unwrap().uuid;
+    let cx = scope(runtime, &store, Some(&micha)).await;
+    let other_cx = scope(runtime, &store, Some(&other)).await;
+    let start = Utc.with_ymd_and_hms(2023, 5, 1, 9, 0, 0).unwrap();
+    let work = add_category(cx, "work".to_string(), 0xFF0000).await.unwrap().unwrap().uuid;
+    let home = add_category(cx, "home".to_string(), 0x00FF00).await.unwrap().unwrap().uuid;
+    let theirs = add_category(other_cx, "theirs".to_string(), 0).await.unwrap().unwrap().uuid;
+    let task = add_task(cx, "write".to_string(), String::new(), span(start, start), work.clone(), None).await.unwrap().unwrap().uuid;
+    let event = add_event(cx, "meet".to_string(), String::new(), span(start, start), work.clone(), None).await.unwrap().unwrap().uuid;
+
+    // the items can only move to another category of the same user
+    assert_eq!(delete_category(cx, work.clone(), Some(theirs)).await.unwrap(), Err(ApiError::NotFound("category".to_string())));
+    assert!(matches!(delete_category(cx, work.clone(), Some(work.clone())).await.unwrap(), Err(ApiError::Invalid(_))));
+    assert_eq!(store.get_task(&task).await.unwrap().category, work);
+
+    delete_category(cx, work.clone(), Some(home.clone())).await.unwrap().unwrap();
+    assert_eq!(store.get_task(&task).await.unwrap().category, home);
+    assert_eq!(store.get_event(&event).await.unwrap().category, home);
+    assert_eq!(store.get_categories(&micha).await.unwrap(), vec![home.clone()]);
+
+    // without a replacement the items go to "Uncategorised", added once and reused after
+    delete_category(cx, home, None).await.unwrap().unwrap();
+    let uncategorised = store.get_task(&task).await.unwrap().category;
+    assert_eq!(store.get_category(&uncategorised).await.unwrap().color, UNCATEGORISED_COLOR);
+    let garden = add_category(cx, "garden".to_string(), 0).await.unwrap().unwrap().uuid;
+    delete_category(cx, garden, None).await.unwrap().unwrap();
+    assert_eq!(store.get_categories(&micha).await.unwrap(), vec![uncategorised.clone()]);
+    assert!(matches!(delete_category(cx, uncategorised, None).await.unwrap(), Err(ApiError::Invalid(_))));
+    runtime.dispose();
+}
+
+#[actix_web::test]
+async fn test_calendar_items() {
+    let runtime = create_runtime();
+    let store: Arc<dyn Store> = Arc::new(MemoryStore::new());
+    let micha = store.add_user("micha", "long enough").await.unwrap().uuid;
+    let other = store.add_user("other", "long enough").await.unwrap().uuid;
+    let cx = scope(runtime, &store, Some(&micha)).await;
+    let other_cx = scope(runtime, &store, Some(&other)).await;
+    let start = Utc.with_ymd_and_hms(2023, 5, 1, 9, 0, 0).unwrap();
+    let category = add_category(cx, "work".to_string(), 0xFF8800).await.unwrap().unwrap();
+
+    let standup = add_event(cx, "standup".to_string(), String::new(), span(start, start + Duration::minutes(15)), category.uuid.clone(), Some("FREQ=DAILY;COUNT=3".parse().unwrap())).await.unwrap().unwrap();
+    let task = add_task(cx, "report".to_string(), String::new(), span(start, start + Duration::hours(2)), category.uuid.clone(), None).await.unwrap().unwrap();
+    set_task_completion(cx, task.uuid.clone(), true).await.unwrap().unwrap();
+    let orphan = store.add_task("orphan", "", &span(start + Duration::days(1), start + Duration::days(1)), &Uuid::new(), None, &micha).await.unwrap();
+    add_event(other_cx, "secret".to_string(), String::new(), span(start, start), add_category(other_cx, "x".to_string(), 0).await.unwrap().unwrap().uuid, None).await.unwrap().unwrap();
+
+    let items = list_items(cx, start - Duration::hours(9), start + Duration::days(7)).await.unwrap().unwrap();
+    let summary = items.iter().map(|x| (x.name.as_str(), x.timespan.start, x.kind, x.color)).collect::<Vec<_>>();
+    assert_eq!(summary, vec![
+        ("standup", start, ItemKind::Event, 0xFF8800),
+        ("report", start, ItemKind::Task { completed: true }, 0xFF8800),
+        ("standup", start + Duration::days(1), ItemKind::Event, 0xFF8800),
+        ("orphan", start + Duration::days(1), ItemKind::Task { completed: false }, UNCATEGORISED_COLOR),
+        ("standup", start + Duration::days(2), ItemKind::Event, 0xFF8800),
+    ]);
+    assert!(items.iter().filter(|x| x.uuid == standup.uuid).all(|x| x.recurrence_id == Some(x.timespan.start)));
+    assert_eq!(items[1].css_color(), "#FF8800");
+    assert_eq!(items[3].uuid, orphan.uuid);
+
+    assert_eq!(list_items(cx, start + Duration::days(3), start + Duration::days(7)).await.unwrap(), Ok(Vec::new()));
+    let activity = list_day_activity(cx, start.date_naive(), 4).await.unwrap().unwrap();
+    assert_eq!(activity.iter().map(|x| x.count).collect::<Vec<_>>(), vec![2, 2, 1, 0]);
+    assert!(matches!(list_day_activity(cx, start.date_naive(), 1000).await.unwrap(), Err(ApiError::Invalid(_))));
+    assert!(matches!(list_day_activity(cx, NaiveDate::MAX, 1).await.unwrap(), Err(ApiError::Invalid(_))));
+    assert!(matches!(list_day_activity(cx, NaiveDate::MIN, 1).await.unwrap(), Err(ApiError::Invalid(_))));
+    assert_eq!(list_day_activity(cx, NaiveDate::MAX - Duration::days(2), 1).await.unwrap().unwrap().len(), 1);
+    assert_eq!(list_day_activity(cx, NaiveDate::MIN + Duration::days(1), 1).await.unwrap().unwrap().len(), 1);
+    runtime.dispose();
+}
+
+#[actix_web::test]
+async fn test_open_tasks() {
+    let runtime = create_runtime();
+    let store: Arc<dyn Store> = Arc::new(MemoryStore::new());
+    let micha = store.add_user("micha", "long enough").await.unwrap().uuid;
+    let other = store.add_user("other", "long enough").await.unwrap().uuid;
+    let cx = scope(runtime, &store, Some(&micha)).await;
+    let other_cx = scope(runtime, &store, Some(&other)).await;
+    let work = add_category(cx, "work".to_string(), 0xFF0000).await.unwrap().unwrap().uuid;
+    let home = add_category(cx, "home".to_string(), 0x00FF00).await.unwrap().unwrap().uuid;
+    let other_category = add_category(other_cx, "other".to_string(), 0).await.unwrap().unwrap().uuid;
+    let at = |day| Utc.with_ymd_and_hms(2023, 5, day, 9, 0, 0).unwrap();
+    let add = |name: &str, day, category: &Uuid| add_task(cx, name.to_string(), String::new(), span(at(day), at(day) + Duration::hours(1)), category.clone(), None);
+
+    let report = add("report", 3, &work).await.unwrap().unwrap();
+    let dishes = add("dishes", 1, &home).await.unwrap().unwrap();
+    let review = add("review", 2, &work).await.unwrap().unwrap();
+    let done = add("done", 1, &work).await.unwrap().unwrap();
+    set_task_completion(cx, done.uuid.clone(), true).await.unwrap().unwrap();
+    let theirs = add_task(other_cx, "theirs".to_string(), String::new(), span(at(1), at(1)), other_category, None).await.unwrap().unwrap();
+
+    let names = |tasks: Vec<crate::common::model::Task>| tasks.into_iter().map(|x| x.name).collect::<Vec<_>>();
+    assert_eq!(names(list_open_tasks(cx, TaskFilter::default()).await.unwrap().unwrap()), vec!["dishes", "review", "report"]);
+    let filter = TaskFilter { category: Some(work.clone()), latest_first: true, ..Default::default() };
+    assert_eq!(names(list_open_tasks(cx, filter).await.unwrap().unwrap()), vec!["report", "review"]);
+    let filter = TaskFilter { due_from: Some(at(2)), due_until: Some(at(3)), ..Default::default() };
+    assert_eq!(names(list_open_tasks(cx, filter).await.unwrap().unwrap()), vec!["review"]);
+    let filter = TaskFilter { due_from: Some(at(3)), due_until: Some(at(2)), ..Default::default() };
+    assert!(matches!(list_open_tasks(cx, filter).await.unwrap(), Err(ApiError::Invalid(_))));
+    assert!(is_overdue(&dishes, &LocalZone::default(), &at(2)) && !is_overdue(&report, &LocalZone::default(), &at(2)));
+
+    // a batch with another user's task is refused as a whole
+    assert_eq!(complete_tasks(cx, vec![dishes.uuid.clone(), theirs.uuid.clone()]).await.unwrap(), Err(ApiError::NotFound("task".to_string())));
+    assert!(!store.get_task(&dishes.uuid).await.unwrap().completed);
+    assert_eq!(reschedule_tasks(cx, vec![theirs.uuid.clone()], 1).await.unwrap(), Err(ApiError::NotFound("task".to_string())));
+    assert!(matches!(reschedule_tasks(cx, vec![dishes.uuid.clone()], i32::MAX).await.unwrap(), Err(ApiError::Invalid(_))));
+
+    let moved = reschedule_tasks(cx, vec![dishes.uuid.clone(), review.uuid.clone()], 7).await.unwrap().unwrap();
+    assert_eq!(moved.iter().map(|x| x.timespan.start).collect::<Vec<_>>(), vec![at(8), at(9)]);
+    assert_eq!(names(list_open_tasks(cx, TaskFilter::default()).await.unwrap().unwrap()), vec!["report", "dishes", "review"]);
+    complete_tasks(cx, vec![report.uuid, review.uuid]).await.unwrap().unwrap();
+    assert_eq!(names(list_open_tasks(cx, TaskFilter::default()).await.unwrap().unwrap()), vec!["dishes"]);
+    runtime.dispose();
+}
+
+#[actix_web::test]
+async fn test_week_start() {
+    let runtime = create_runtime();
+    let store: Arc<dyn Store> = Arc::new(MemoryStore::new());
+    let micha = store.add_user("micha", "long enough").await.unwrap().uuid;
+    let cx = scope(runtime, &store, Some(&micha)).await;
+    assert_eq!(get_current_user(cx).await.unwrap().unwrap().week_start, WeekStart(Weekday::Mon));
+    assert_eq!(set_week_start(cx, WeekStart(Weekday::Sun)).await.unwrap(), Ok(WeekStart(Weekday::Sun)));
+    assert_eq!(get_current_user(cx).await.unwrap().unwrap().week_start, WeekStart(Weekday::Sun));
+    runtime.dispose();
+}
+
+#[actix_web::test]
+async fn test_time_zone() {
+    let runtime = create_runtime();
+    let store: Arc<dyn Store> = Arc::new(MemoryStore::new());
+    let micha = store.add_user("micha", "long enough").await.unwrap().uuid;
+    let cx = scope(runtime, &store, Some(&micha)).await;
+    let category = add_category(cx, "work".to_string(), 0).await.unwrap().unwrap();
+    // 01:30 on May 2nd in Berlin
+    let start = Utc.with_ymd_and_hms(2023, 5, 1, 23, 30, 0).unwrap();
+    add_event(cx, "late".to_string(), String::new(), span(start, start + Duration::minutes(30)), category.uuid, None).await.unwrap().unwrap();
+    let counts = || async { list_day_activity(cx, start.date_naive(), 2).await.unwrap().unwrap().iter().map(|x| x.count).collect::<Vec<_>>() };
+
+    assert_eq!(get_current_user(cx).await.unwrap().unwrap().time_zone, LocalZone::default());
+    assert_eq!(counts().await, vec![1, 0]);
+    assert_eq!(set_time_zone(cx, " Europe/Berlin ".to_string()).await.unwrap(), Ok(LocalZone(chrono_tz::Europe::Berlin)));
+    assert!(matches!(set_time_zone(cx, "Mars/Olympus_Mons".to_string()).await.unwrap(), Err(ApiError::Invalid(_))));
+    assert_eq!(get_current_user(cx).await.unwrap().unwrap().time_zone, LocalZone(chrono_tz::Europe::Berlin));
+    assert_eq!(counts().await, vec![0, 1]);
+    runtime.dispose();
+}
+
+#[actix_web::test]
+async fn test_all_day_items() {
+    let runtime = create_runtime();
+    let store: Arc<dyn Store> = Arc::new(MemoryStore::new());
+    let micha = store.add_user("micha", "long enough").await.unwrap().uuid;
+    let cx = scope(runtime, &store, Some(&micha)).await;
+    let category = add_category(cx, "home".to_string(), 0).await.unwrap().unwrap().uuid;
+    set_time_zone(cx, "Europe/Berlin".to_string()).await.unwrap().unwrap();
+    let at = |day, hour| Utc.with_ymd_and_hms(2023, 5, day, hour, 0, 0).unwrap();
+    let date = |day| chrono::NaiveDate::from_ymd_opt(2023, 5, day).unwrap();
+    let event = add_event(cx, "trip".to_string(), String::new(), span(at(1, 9), at(1, 10)), category.clone(), None).await.unwrap().unwrap();
+    let task = add_task(cx, "taxes".to_string(), String::new(), span(at(1, 9), at(1, 10)), category.clone(), None).await.unwrap().unwrap();
+
+    let mut half_day = Timespan::all_day(&date(2), &date(3)).unwrap();
+    half_day.end = at(3, 12);
+    assert!(matches!(edit_event(cx, event.uuid.clone(), "trip".to_string(), String::new(), half_day, category.clone(), None).await.unwrap(), Err(ApiError::Invalid(_))));
+    edit_event(cx, event.uuid.clone(), "trip".to_string(), String::new(), Timespan::all_day(&date(2), &date(3)).unwrap(), category.clone(), None).await.unwrap().unwrap();
+    let task = edit_task(cx, task.uuid.clone(), "taxes".to_string(), String::new(), Timespan::all_day(&date(3), &date(3)).unwrap(), category.clone(), None).await.unwrap().unwrap();
+
+    // the dates run from midnight to midnight in Berlin, which is 22:00 UTC the day before
+    let items = list_items(cx, at(1, 22), at(2, 22)).await.unwrap().unwrap();
+    assert_eq!(items.iter().map(|x| (x.name.as_str(), x.all_day, x.timespan.clone())).collect::<Vec<_>>(), vec![
+        ("trip", true, Timespan::new(&at(1, 22), &at(3, 22)).unwrap()),
+    ]);
+    assert_eq!(list_items(cx, at(3, 22), at(4, 22)).await.unwrap(), Ok(Vec::new()));
+    let zone = LocalZone(chrono_tz::Europe::Berlin);
+    assert_eq!(due(&task, &zone), at(3, 22));
+    assert!(!is_overdue(&task, &zone, &at(3, 21)) && is_overdue(&task, &zone, &at(3, 23)));
+
+    // postponing keeps it on whole dates
+    let moved = reschedule_tasks(cx, vec![task.uuid.clone()], 1).await.unwrap().unwrap();
+    assert_eq!(moved[0].timespan, Timespan::all_day(&date(4), &date(4)).unwrap());
+    runtime.dispose();
+}
+
+#[actix_web::test]
+async fn test_reminder_settings() {
+    let runtime = create_runtime();
+    let store: Arc<dyn Store> = Arc::new(MemoryStore::new());
+    let micha = store.add_user("micha", "long enough").await.unwrap().uuid;
+    let other = store.add_user("other", "long enough").await.unwrap().uuid;
+    let cx = scope(runtime, &store, Some(&micha)).await;
+    let other_cx = scope(runtime, &store, Some(&other)).await;
+    let category = add_category(cx, "work".to_string(), 0).await.unwrap().unwrap();
+    let start = Utc.with_ymd_and_hms(2023, 5, 1, 9, 0, 0).unwrap();
+    let task = add_task(cx, "write".to_string(), String::new(), span(start, start), category.uuid.clone(), None).await.unwrap().unwrap();
+    let event = add_event(cx, "meet".to_string(), String::new(), span(start, start), category.uuid.clone(), None).await.unwrap().unwrap();
+
+    assert_eq!(set_email(cx, " micha@example.com ".to_string()).await.unwrap(), Ok(Some("micha@example.com".to_string())));
+    assert_eq!(get_current_user(cx).await.unwrap().unwrap().email, Some("micha@example.com".to_string()));
+    assert!(matches!(set_email(cx, "not an address".to_string()).await.unwrap(), Err(ApiError::Invalid(_))));
+    assert_eq!(set_email(cx, String::new()).await.unwrap(), Ok(None));
+
+    // sorted and without duplicates
+    assert_eq!(set_task_reminders(cx, task.uuid.clone(), vec![60, 10, 60]).await.unwrap().unwrap().reminders, vec![10, 60]);
+    assert_eq!(set_event_reminders(cx, event.uuid.clone(), vec![24 * 60]).await.unwrap().unwrap().reminders, vec![24 * 60]);
+    assert!(matches!(set_event_reminders(cx, event.uuid.clone(), vec![60 * 24 * 365]).await.unwrap(), Err(ApiError::Invalid(_))));
+    assert_eq!(set_task_reminders(other_cx, task.uuid.clone(), vec![5]).await.unwrap(), Err(ApiError::NotFound("task".to_string())));
+    assert_eq!(store.get_task(&task.uuid).await.unwrap().reminders, vec![10, 60]);
+    runtime.dispose();
+}
+
+#[actix_web::test]
+async fn test_not_logged_in() {
+    let runtime = create_runtime();
+    let store: Arc<dyn Store> = Arc::new(MemoryStore::new());
+    let cx = scope(runtime, &store, None).await;
+    assert_eq!(list_categories(cx).await.unwrap(), Err(ApiError::NotLoggedIn));
+    assert_eq!(delete_task(cx, Uuid::new()).await.unwrap(), Err(ApiError::NotLoggedIn));
+    runtime.dispose();
+}
+
+#[actix_web::test]
+async fn test_list_jobs_admins_only() {
+    let runtime = create_runtime();
+    let store: Arc<dyn Store> = Arc::new(MemoryStore::new());
+    let admin = store.add_user("root", "long enough").await.unwrap().uuid;
+    let other = store.add_user("other", "long enough").await.unwrap().uuid;
+    std::env::set_var("ADMIN_USERS", "ops, root");
+    let start = Utc.with_ymd_and_hms(2023, 5, 1, 9, 0, 0).unwrap();
+    store.ensure_job(&Job::new("cleanup", &start)).await.unwrap();
+    let cx = scope(runtime, &store, Some(&admin)).await;
+    let other_cx = scope(runtime, &store, Some(&other)).await;
+    let anonymous = scope(runtime, &store, None).await;
+
+    assert!(get_current_user(cx).await.unwrap().unwrap().admin);
+    assert!(!get_current_user(other_cx).await.unwrap().unwrap().admin);
+    assert_eq!(list_jobs(cx).await.unwrap(), Ok(vec![Job::new("cleanup", &start)]));
+    assert_eq!(list_jobs(other_cx).await.unwrap(), Err(ApiError::NotAllowed));
+    assert_eq!(list_jobs(anonymous).await.unwrap(), Err(ApiError::NotLoggedIn));
+    runtime.dispose();
+}
@@ -0,0 +1,133 @@
+use std::sync::Arc;
+use actix_web::{cookie::Cookie, http::{header, StatusCode}, test, web, App};
+use chrono::{Duration, Utc};
+use crate::{backend::{auth::{login, logout, signup, SESSION_COOKIE}, database::{memory::MemoryStore, store::Store}, handlers::{calendar_feed, calendar_feed_reset, calendar_import}, password::hash_token}, common::model::Session};
+
+macro_rules! app {
+    ($store:expr) => {
+        test::init_service(
+            App::new()
+                .app_data(web::Data::from($store.clone()))
+                .service(signup)
+                .service(login)
+                .service(logout)
+                .service(calendar_feed)
+                .service(calendar_feed_reset)
+                .service(calendar_import),
+        )
+        .await
+    };
+}
+
+fn form(path: &str, username: &str, password: &str) -> test::TestRequest {
+    test::TestRequest::post().uri(path).set_form([("username", username), ("password", password)])
+}
+
+fn location(response: &actix_web::dev::ServiceResponse) -> &str {
+    response.headers().get(header::LOCATION).unwrap().to_str().unwrap()
+}
+
+#[actix_web::test]
+async fn test_signup_login_logout() {
+    let store: Arc<dyn Store> = Arc::new(MemoryStore::new());
+    let app = app!(store);
+
+    let response = test::call_service(&app, form("/auth/signup", "micha", "short").to_request()).await;
+    assert_eq!(location(&response), "/signup?error=password");
+    let response = test::call_service(&app, form("/auth/signup", "micha", "long enough").to_request()).await;
+    assert_eq!(response.status(), StatusCode::SEE_OTHER);
+    assert_eq!(location(&response), "/month");
+    let cookie = response.response().cookies().find(|x| x.name() == SESSION_COOKIE).unwrap().into_owned();
+    assert!(cookie.http_only().unwrap() && cookie.secure().unwrap());
+    let response = test::call_service(&app, form("/auth/signup", "micha", "long enough").to_request()).await;
+    assert_eq!(location(&response), "/signup?error=taken");
+
+    // the session cookie identifies the user, no user id is sent along
+    let request = test::TestRequest::post().uri("/calendar/import").cookie(cookie.clone()).set_payload("BEGIN:VCALENDAR\r\nEND:VCALENDAR\r\n");
+    assert_eq!(test::call_service(&app, request.to_request()).await.status(), StatusCode::OK);
+    let request = test::TestRequest::post().uri("/calendar/import").set_payload("BEGIN:VCALENDAR\r\nEND:VCALENDAR\r\n");
+    assert_eq!(test::call_service(&app, request.to_request()).await.status(), StatusCode::UNAUTHORIZED);
+    let forged = Cookie::new(SESSION_COOKIE, "0".repeat(64));
+    let request = test::TestRequest::post().uri("/calendar/import").cookie(forged).set_payload("");
+    assert_eq!(test::call_service(&app, request.to_request()).await.status(), StatusCode::UNAUTHORIZED);
+
+    let response = test::call_service(&app, form("/auth/login", "micha", "wrong").to_request()).await;
+    assert_eq!(location(&response), "/login?error=invalid");
+    let response = test::call_service(&app, form("/auth/login", "nobody", "long enough").to_request()).await;
+    assert_eq!(location(&response), "/login?error=invalid");
+    let response = test::call_service(&app, form("/auth/login", "micha", "long enough").to_request()).await;
+    assert_eq!(location(&response), "/month");
+    let second = response.response().cookies().find(|x| x.name() == SESSION_COOKIE).unwrap().into_owned();
+    assert_ne!(second.value(), cookie.value());
+
+    // logging out ends only that session
+    let response = test::call_service(&app, test::TestRequest::post().uri("/auth/logout").cookie(cookie.clone()).to_request()).await;
+    assert_eq!(location(&response), "/login");
+    assert!(store.get_session(&hash_token(cookie.value())).await.is_err());
+    assert!(store.get_session(&hash_token(second.value())).await.is_ok());
+}
+
+#[actix_web::test]
+async fn test_expired_session() {
+    let store: Arc<dyn Store> = Arc::new(MemoryStore::new());
+    let app = app!(store);
+    let user = store.add_user("micha", "long enough").await.unwrap().uuid;
+    let now = Utc::now();
+    store.add_session(&Session {
+        token_hash: hash_token("expired"),
+        user: user.clone(),
+        created_at: now - Duration::days(31),
+        expires_at: now - Duration::days(1),
+    }).await.unwrap();
+
+    let request = test::TestRequest::post().uri("/calendar/import").cookie(Cookie::new(SESSION_COOKIE, "expired")).set_payload("");
+    assert_eq!(test::call_service(&app, request.to_request()).await.status(), StatusCode::UNAUTHORIZED);
+    // the expired session is removed when it is used
+    assert!(store.get_session(&hash_token("expired")).await.is_err());
+    assert_eq!(store.delete_expired_sessions(&now).await.unwrap(), 0);
+}
+
+#[actix_web::test]
+async fn test_feed_token() {
+    let store: Arc<dyn Store> = Arc::new(MemoryStore::new());
+    let app = app!(store);
+    let user = store.add_user("micha", "long enough").await.unwrap();
+    assert_eq!(user.feed_token.len(), 64);
+
+    let request = test::TestRequest::get().uri(&format!("/calendar/{}.ics", user.feed_token));
+    assert_eq!(test::call_service(&app, request.to_request()).await.status(), StatusCode::OK);
+    // the user id no longer gives access to the feed
+    let request = test::TestRequest::get().uri(&format!("/calendar/{}.ics", user.uuid.to_raw()));
+    assert_eq!(test::call_service(&app, request.to_request()).await.status(), StatusCode::NOT_FOUND);
+
+    let response = test::call_service(&app, form("/auth/login", "micha", "long enough").to_request()).await;
+    let cookie = response.response().cookies().find(|x| x.name() == SESSION_COOKIE).unwrap().into_owned();
+    test::call_service(&app, test::TestRequest::post().uri("/calendar/feed/reset").cookie(cookie).to_request()).await;
+    let request = test::TestRequest::get().uri(&format!("/calendar/{}.ics", user.feed_token));
+    assert_eq!(test::call_service(&app, request.to_request()).await.status(), StatusCode::NOT_FOUND);
+    let new_token = store.get_user(&user.uuid).await.unwrap().feed_token;
+    let request = test::TestRequest::get().uri(&format!("/calendar/{new_token}.ics"));
+    assert_eq!(test::call_service(&app, request.to_request()).await.status(), StatusCode::OK);
+}
+
+#[cfg(feature = "kv-mem")]
+#[tokio::test]
+async fn test_sessions_embedded_surreal() {
+    use crate::backend::database::{connection::{connect, DatabaseEngine}, db_error::DBerror, migrations::migrate};
+
+    let db = connect(&DatabaseEngine::Memory).await.unwrap();
+    migrate(&db).await.unwrap();
+    let user = db.add_user("micha", "long enough").await.unwrap();
+    assert_eq!(db.user_id_from_feed_token(&user.feed_token).await.unwrap(), user.uuid);
+    assert!(matches!(db.user_id_from_feed_token("").await, Err(DBerror::FeedNotFound)));
+
+    let now = Utc::now();
+    for (token, expires_at) in [("old", now - Duration::hours(1)), ("new", now + Duration::hours(1))] {
+        db.add_session(&Session { token_hash: hash_token(token), user: user.uuid.clone(), created_at: now, expires_at }).await.unwrap();
+    }
+    assert_eq!(db.get_session(&hash_token("new")).await.unwrap().user, user.uuid);
+    assert_eq!(db.delete_expired_sessions(&now).await.unwrap(), 1);
+    assert!(matches!(db.get_session(&hash_token("old")).await, Err(DBerror::SessionNotFound)));
+    db.delete_session(&hash_token("new")).await.unwrap();
+    assert!(db.get_session(&hash_token("new")).await.is_err());
+}
@@ -0,0 +1,94 @@
+use chrono::{TimeZone, Utc};
+use surrealdb::sql::Uuid;
+use crate::{backend::database::{db_error::DBerror, memory::MemoryStore, store::Store}, common::{api::calendar::{CalendarItem, ItemKind}, model::{Category, Timespan, UNCATEGORISED, UNCATEGORISED_COLOR}}, frontend::categories::{hide_categories, option_value, picked_category}};
+
+fn category(name: &str, hidden: bool) -> Category {
+    Category { name: name.to_string(), color: 0, hidden, user: Uuid::new(), uuid: Uuid::new() }
+}
+
+fn item(name: &str, category: &Uuid) -> CalendarItem {
+    let at = Utc.with_ymd_and_hms(2023, 5, 1, 9, 0, 0).unwrap();
+    CalendarItem {
+        kind: ItemKind::Event,
+        uuid: Uuid::new(),
+        recurrence_id: None,
+        name: name.to_string(),
+        timespan: Timespan::new(&at, &at).unwrap(),
+        all_day: false,
+        category: category.clone(),
+        color: 0,
+    }
+}
+
+#[test]
+fn test_hide_categories() {
+    let work = category("work", true);
+    let home = category("home", false);
+    let items = vec![item("meeting", &work.uuid), item("dishes", &home.uuid), item("orphan", &Uuid::new())];
+    // items of categories that no longer exist cannot be hidden, so they stay
+    let names = |items: Vec<CalendarItem>| items.into_iter().map(|x| x.name).collect::<Vec<_>>();
+    assert_eq!(names(hide_categories(items.clone(), &[work, home.clone()])), vec!["dishes", "orphan"]);
+    assert_eq!(names(hide_categories(items, &[home])), vec!["meeting", "dishes", "orphan"]);
+}
+
+/// Edits and deletes categories in `db`, checking the changes stick and no item is left without one.
+async fn check_category_lifecycle(db: &dyn Store) {
+    let at = Utc.with_ymd_and_hms(2023, 5, 1, 9, 0, 0).unwrap();
+    let user = db.add_user("micha", "long enough").await.unwrap().uuid;
+    let other = db.add_user("heiko", "long enough").await.unwrap().uuid;
+    let work = db.add_category("work", 0, &user).await.unwrap().uuid;
+    let home = db.add_category("home", 0, &user).await.unwrap().uuid;
+    let theirs = db.add_category("theirs", 0, &other).await.unwrap().uuid;
+    let task = db.add_task("write", "", &Timespan::new(&at, &at).unwrap(), &work, None, &user).await.unwrap().uuid;
+    let event = db.add_event("meet", "", &Timespan::new(&at, &at).unwrap(), &home, None, &user).await.unwrap().uuid;
+    let their_task = db.add_task("theirs", "", &Timespan::new(&at, &at).unwrap(), &theirs, None, &other).await.unwrap().uuid;
+
+    assert!(!db.get_category(&work).await.unwrap().hidden);
+    db.category_edit_name(&work, "job").await.unwrap();
+    db.category_change_color(&work, 0xFF0000).await.unwrap();
+    db.category_set_hidden(&work, true).await.unwrap();
+    let edited = db.get_category(&work).await.unwrap();
+    assert_eq!((edited.name.as_str(), edited.color, edited.hidden), ("job", 0xFF0000, true));
+    db.event_change_category(&event, &work).await.unwrap();
+    assert_eq!(db.get_event(&event).await.unwrap().category, work);
+
+    assert!(matches!(db.delete_category(&work, &Uuid::new()).await, Err(DBerror::CategoryNotFound(_))));
+    assert!(matches!(db.delete_category(&work, &work).await, Err(DBerror::InvalidReplacement(_))));
+    assert!(matches!(db.delete_category(&work, &theirs).await, Err(DBerror::InvalidReplacement(_))));
+    assert_eq!(db.get_task(&task).await.unwrap().category, work);
+    assert_eq!(db.delete_category(&work, &home).await.unwrap().uuid, work);
+    assert!(matches!(db.get_category(&work).await, Err(DBerror::CategoryNotFound(_))));
+    assert_eq!(db.get_task(&task).await.unwrap().category, home);
+    assert_eq!(db.get_event(&event).await.unwrap().category, home);
+    assert_eq!(db.get_categories(&user).await.unwrap(), vec![home.clone()]);
+    assert_eq!(db.get_task(&their_task).await.unwrap().category, theirs);
+
+    let uncategorised = db.uncategorised_category(&user).await.unwrap();
+    assert_eq!((uncategorised.name.as_str(), uncategorised.color), (UNCATEGORISED, UNCATEGORISED_COLOR));
+    assert_eq!(db.uncategorised_category(&user).await.unwrap(), uncategorised);
+    assert_ne!(db.uncategorised_category(&other).await.unwrap().uuid, uncategorised.uuid);
+}
+
+#[tokio::test]
+async fn test_category_lifecycle_memory_store() {
+    check_category_lifecycle(&MemoryStore::new()).await;
+}
+
+#[cfg(feature = "kv-mem")]
+#[tokio::test]
+async fn test_category_lifecycle_embedded_surreal() {
+    use crate::backend::database::{connection::{connect, DatabaseEngine}, migrations::migrate};
+
+    let db = connect(&DatabaseEngine::Memory).await.unwrap();
+    migrate(&db).await.unwrap();
+    check_category_lifecycle(&db).await;
+}
+
+#[test]
+fn test_option_values() {
+    let work = category("work", false);
+    assert_eq!(picked_category(&option_value(&work)), Ok(Some(work.uuid.clone())));
+    assert_eq!(picked_category(""), Ok(None));
+    // the quoted form `Uuid` displays is not a category
+    assert!(picked_category(&work.uuid.to_string()).is_err());
+}
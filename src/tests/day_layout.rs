@@ -0,0 +1,62 @@
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use surrealdb::sql::Uuid;
+use crate::{common::{api::calendar::{CalendarItem, ItemKind}, model::Timespan}, frontend::pages::day::layout_day};
+
+fn at(hour: u32, minute: u32) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2023, 5, 1, hour, minute, 0).unwrap()
+}
+
+fn item(name: &str, start: DateTime<Utc>, end: DateTime<Utc>) -> CalendarItem {
+    CalendarItem {
+        kind: ItemKind::Event,
+        uuid: Uuid::new(),
+        recurrence_id: None,
+        name: name.to_string(),
+        timespan: Timespan::new(&start, &end).unwrap(),
+        all_day: false,
+        category: Uuid::new(),
+        color: 0,
+    }
+}
+
+fn day() -> Timespan {
+    Timespan::new(&at(0, 0), &(at(0, 0) + Duration::days(1))).unwrap()
+}
+
+#[test]
+fn test_rows() {
+    let placed = layout_day(&[
+        item("breakfast", at(8, 0), at(8, 30)),
+        item("call", at(9, 10), at(9, 20)),
+        item("deadline", at(12, 0), at(12, 0)),
+    ], &day());
+    let rows = placed.iter().map(|x| (x.item.name.as_str(), x.start_row, x.end_row, x.columns)).collect::<Vec<_>>();
+    // 15 minute rows, partial rows are taken up whole and nothing is less than a row high, so the
+    // call covers the rows of 9:00 and 9:15
+    assert_eq!(rows, vec![("breakfast", 33, 35, 1), ("call", 37, 39, 1), ("deadline", 49, 50, 1)]);
+}
+
+#[test]
+fn test_midnight_clipping() {
+    let placed = layout_day(&[
+        item("night shift", at(22, 0) - Duration::days(1), at(6, 0)),
+        item("party", at(23, 0), at(2, 0) + Duration::days(1)),
+        item("yesterday", at(10, 0) - Duration::days(1), at(0, 0)),
+    ], &day());
+    let rows = placed.iter().map(|x| (x.item.name.as_str(), x.start_row, x.end_row, x.clipped_start, x.clipped_end)).collect::<Vec<_>>();
+    assert_eq!(rows, vec![("night shift", 1, 25, true, false), ("party", 93, 97, false, true)]);
+}
+
+#[test]
+fn test_overlap_columns() {
+    let placed = layout_day(&[
+        item("a", at(9, 0), at(10, 0)),
+        item("b", at(9, 30), at(11, 0)),
+        item("c", at(10, 0), at(10, 30)),
+        item("long", at(8, 0), at(12, 0)),
+        item("lunch", at(12, 0), at(13, 0)),
+    ], &day());
+    let columns = placed.iter().map(|x| (x.item.name.as_str(), x.column, x.columns)).collect::<Vec<_>>();
+    // c starts when a ends, so it takes over a's column; lunch overlaps nothing and gets the whole width
+    assert_eq!(columns, vec![("long", 0, 3), ("a", 1, 3), ("b", 2, 3), ("c", 1, 3), ("lunch", 0, 1)]);
+}
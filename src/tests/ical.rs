@@ -0,0 +1,137 @@
+use chrono::{DateTime, TimeZone, Utc};
+use chrono_tz::{America::New_York, Asia::Kolkata, Europe::Berlin, Tz};
+use surrealdb::sql::Uuid;
+use crate::{backend::ical::{export::{export_calendar, uid}, CalendarWriter}, common::{model::{Category, Event, LocalZone, Task, Timespan}, recurrence::{Frequency, OccurrenceOverride, Recurrence}}};
+
+fn at(day: u32, hour: u32) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2023, 4, day, hour, 0, 0).unwrap()
+}
+
+fn lines(calendar: &str) -> Vec<&str> {
+    calendar.split("\r\n").collect()
+}
+
+#[test]
+fn test_folding_and_escaping() {
+    let mut writer = CalendarWriter::new();
+    writer.text("SUMMARY", "lunch; with Jan, Piet\nand \\ Klaas");
+    writer.text("DESCRIPTION", &"é".repeat(60));
+    let output = writer.finish();
+
+    assert!(output.starts_with(r"SUMMARY:lunch\; with Jan\, Piet\nand \\ Klaas"));
+    for line in lines(&output) {
+        assert!(line.len() <= 75, "line too long: {line}");
+    }
+    let unfolded = output.replace("\r\n ", "");
+    assert!(unfolded.contains(&format!("DESCRIPTION:{}\r\n", "é".repeat(60))));
+}
+
+#[test]
+fn test_export_events_and_tasks() {
+    let user = Uuid::new();
+    let category = Category { name: "work".to_string(), color: 0x1E70F0, hidden: false, user: user.clone(), uuid: Uuid::new() };
+    let mut recurrence = Recurrence::new(Frequency::Weekly);
+    recurrence.count = Some(4);
+    recurrence.exdates = vec![at(10, 9)];
+    recurrence.overrides = vec![OccurrenceOverride {
+        recurrence_id: at(17, 9),
+        timespan: Timespan::new(&at(17, 13), &at(17, 14)).unwrap(),
+        name: Some("moved standup".to_string()),
+        description: None,
+    }];
+    let event = Event {
+        name: "standup".to_string(),
+        description: String::new(),
+        timespan: Timespan::new(&at(3, 9), &at(3, 10)).unwrap(),
+        category: category.uuid.clone(),
+        recurrence: Some(recurrence),
+        ical_uid: None,
+        reminders: Vec::new(),
+        user: user.clone(),
+        uuid: Uuid::new(),
+    };
+    let task = Task {
+        name: "report".to_string(),
+        description: "quarterly".to_string(),
+        timespan: Timespan::new(&at(5, 12), &at(5, 17)).unwrap(),
+        category: Uuid::new(),
+        completed: true,
+        recurrence: None,
+        ical_uid: None,
+        reminders: Vec::new(),
+        user: user.clone(),
+        uuid: Uuid::new(),
+    };
+
+    let calendar = export_calendar(std::slice::from_ref(&event), std::slice::from_ref(&task), &[category], &at(1, 0));
+    let lines = lines(&calendar);
+
+    assert_eq!(lines[0], "BEGIN:VCALENDAR");
+    assert!(lines.contains(&"VERSION:2.0"));
+    let event_uid = format!("UID:{}", uid(&event.uuid));
+    assert_eq!(lines.iter().filter(|x| **x == event_uid).count(), 2);
+    assert!(lines.contains(&"DTSTART:20230403T090000Z"));
+    assert!(lines.contains(&"DTEND:20230403T100000Z"));
+    assert!(lines.contains(&"RRULE:FREQ=WEEKLY;COUNT=4"));
+    assert!(lines.contains(&"EXDATE:20230410T090000Z"));
+    assert!(lines.contains(&"CATEGORIES:work"));
+    assert!(lines.contains(&"RECURRENCE-ID:20230417T090000Z"));
+    assert!(lines.contains(&"SUMMARY:moved standup"));
+    assert!(lines.contains(&"DTSTART:20230417T130000Z"));
+
+    assert!(lines.contains(&"BEGIN:VTODO"));
+    assert!(lines.contains(&format!("UID:{}", uid(&task.uuid)).as_str()));
+    assert!(lines.contains(&"DUE:20230405T170000Z"));
+    assert!(lines.contains(&"STATUS:COMPLETED"));
+    assert!(lines.contains(&"DESCRIPTION:quarterly"));
+    // the task's category does not exist, so it is exported without one
+    assert_eq!(lines.iter().filter(|x| x.starts_with("CATEGORIES")).count(), 2);
+    assert_eq!(lines[lines.len() - 2], "END:VCALENDAR");
+}
+
+#[test]
+fn test_export_time_zones() {
+    let weekly = |zone: Tz, timespan: Timespan| {
+        let mut recurrence = Recurrence::new(Frequency::Weekly);
+        recurrence.time_zone = LocalZone(zone);
+        Event {
+            name: "standup".to_string(),
+            description: String::new(),
+            timespan,
+            category: Uuid::new(),
+            recurrence: Some(recurrence),
+            ical_uid: None,
+            reminders: Vec::new(),
+            user: Uuid::new(),
+            uuid: Uuid::new(),
+        }
+    };
+    let timed = Timespan::new(&at(3, 9), &at(3, 10)).unwrap();
+    let events = [
+        weekly(Berlin, timed.clone()),
+        weekly(Kolkata, timed.clone()),
+        weekly(Berlin, timed),
+        // dates are written without a zone
+        weekly(New_York, Timespan { start: at(3, 0), end: at(4, 0), all_day: true }),
+    ];
+
+    let calendar = export_calendar(&events, &[], &[], &at(1, 0));
+    let lines = lines(&calendar);
+    assert_eq!(lines.iter().filter(|x| **x == "BEGIN:VTIMEZONE").count(), 2);
+    assert!(!lines.contains(&"TZID:America/New_York"));
+    // the clocks change on the last Sunday of March and October, given as the first of those from 1970
+    let berlin = "BEGIN:VTIMEZONE\r\nTZID:Europe/Berlin\r\n\
+        BEGIN:DAYLIGHT\r\nDTSTART:19700329T020000\r\nRRULE:FREQ=YEARLY;BYMONTH=3;BYDAY=-1SU\r\n\
+        TZOFFSETFROM:+0100\r\nTZOFFSETTO:+0200\r\nTZNAME:CEST\r\nEND:DAYLIGHT\r\n\
+        BEGIN:STANDARD\r\nDTSTART:19701025T030000\r\nRRULE:FREQ=YEARLY;BYMONTH=10;BYDAY=-1SU\r\n\
+        TZOFFSETFROM:+0200\r\nTZOFFSETTO:+0100\r\nTZNAME:CET\r\nEND:STANDARD\r\nEND:VTIMEZONE\r\n";
+    assert!(calendar.contains(berlin));
+    let kolkata = "BEGIN:VTIMEZONE\r\nTZID:Asia/Kolkata\r\n\
+        BEGIN:STANDARD\r\nDTSTART:19700101T000000\r\nTZOFFSETFROM:+0530\r\nTZOFFSETTO:+0530\r\nTZNAME:IST\r\nEND:STANDARD\r\n\
+        END:VTIMEZONE\r\n";
+    assert!(calendar.contains(kolkata));
+    assert!(lines.contains(&"DTSTART;TZID=Europe/Berlin:20230403T110000"));
+    assert!(lines.contains(&"DTSTART;TZID=Asia/Kolkata:20230403T143000"));
+    // the zones come before the items using them
+    assert!(calendar.find("BEGIN:VTIMEZONE").unwrap() < calendar.find("BEGIN:VEVENT").unwrap());
+}
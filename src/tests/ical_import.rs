@@ -0,0 +1,327 @@
+use chrono::{DateTime, NaiveDate, TimeZone, Utc};
+use chrono_tz::Europe::Berlin;
+use crate::{backend::{database::{memory::MemoryStore, store::Store}, ical::{export::export_calendar, import::import_calendar, parse_components}}, common::model::{LocalZone, Timespan}};
+
+fn at(day: u32, hour: u32) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2023, 4, day, hour, 0, 0).unwrap()
+}
+
+const CALENDAR: &str = "BEGIN:VCALENDAR\r
+VERSION:2.0\r
+PRODID:-//other//calendar//EN\r
+BEGIN:VTIMEZONE\r
+TZID:Europe/Amsterdam\r
+END:VTIMEZONE\r
+BEGIN:VEVENT\r
+UID:standup@other\r
+DTSTART:20230403T090000Z\r
+DURATION:PT30M\r
+SUMMARY:stand\r
+  up\r
+CATEGORIES:Work,Meetings\r
+RRULE:FREQ=WEEKLY;COUNT=4\r
+EXDATE:20230410T090000Z,20230424T090000Z\r
+BEGIN:VALARM\r
+ACTION:DISPLAY\r
+TRIGGER:-PT5M\r
+END:VALARM\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+UID:standup@other\r
+RECURRENCE-ID:20230417T090000Z\r
+DTSTART:20230417T130000Z\r
+DTEND:20230417T133000Z\r
+SUMMARY:moved standup\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+UID:holiday@other\r
+DTSTART;VALUE=DATE:20230427\r
+SUMMARY:King's day\\, no work\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+UID:broken@other\r
+SUMMARY:no start\r
+END:VEVENT\r
+BEGIN:VTODO\r
+UID:report@other\r
+DUE:20230405T170000Z\r
+SUMMARY:report\r
+DESCRIPTION:first line\\nsecond line\r
+CATEGORIES:work\r
+STATUS:COMPLETED\r
+END:VTODO\r
+END:VCALENDAR\r
+";
+
+#[test]
+fn test_parse_components() {
+    let calendars = parse_components(CALENDAR).unwrap();
+    assert_eq!(calendars.len(), 1);
+    let event = &calendars[0].components[1];
+    assert_eq!(event.name, "VEVENT");
+    assert_eq!(event.line, 7);
+    assert_eq!(event.property("SUMMARY").unwrap().value, "stand up");
+    assert_eq!(event.components[0].name, "VALARM");
+    let holiday = &calendars[0].components[3];
+    assert_eq!(holiday.property("DTSTART").unwrap().param("VALUE"), Some("DATE"));
+
+    assert!(parse_components("BEGIN:VCALENDAR\r\nBEGIN:VEVENT\r\nEND:VCALENDAR\r\n").is_err());
+    assert!(parse_components("BEGIN:VCALENDAR\r\n").is_err());
+    assert!(parse_components("no colon here").is_err());
+}
+
+/// Imports `CALENDAR` into `db` twice and checks what ends up in the store.
+async fn check_import(db: &dyn Store) {
+    let user = db.add_user("micha", "pass").await.unwrap().uuid;
+    let work = db.add_category("work", 0, &user).await.unwrap().uuid;
+
+    let report = import_calendar(db, &user, CALENDAR).await.unwrap();
+    assert_eq!(report.created, 3);
+    assert_eq!(report.updated, 0);
+    // "Work" matches the existing "work" category
+    assert!(report.categories_created.is_empty());
+    assert_eq!(report.errors.len(), 1);
+    assert_eq!(report.errors[0].uid.as_deref(), Some("broken@other"));
+    assert_eq!(report.errors[0].line, 33);
+
+    let events = db.get_events(&user).await.unwrap();
+    let standup = events.iter().find(|x| x.name == "stand up").unwrap();
+    assert_eq!(standup.timespan, Timespan::new(&at(3, 9), &Utc.with_ymd_and_hms(2023, 4, 3, 9, 30, 0).unwrap()).unwrap());
+    assert_eq!(standup.category, work);
+    assert_eq!(standup.ical_uid.as_deref(), Some("standup@other"));
+    let occurrences = standup.occurrences(&Timespan::new(&at(1, 0), &at(30, 0)).unwrap());
+    assert_eq!(occurrences.len(), 2);
+    assert_eq!(occurrences[1].timespan.start, at(17, 13));
+    assert_eq!(occurrences[1].name.as_deref(), Some("moved standup"));
+
+    let holiday = events.iter().find(|x| x.ical_uid.as_deref() == Some("holiday@other")).unwrap();
+    assert_eq!(holiday.name, "King's day, no work");
+    // a date is an all-day span
+    let kings_day = NaiveDate::from_ymd_opt(2023, 4, 27).unwrap();
+    assert_eq!(holiday.timespan, Timespan::all_day(&kings_day, &kings_day).unwrap());
+    // an item without CATEGORIES goes to "Uncategorised"
+    assert_eq!(holiday.category, db.uncategorised_category(&user).await.unwrap().uuid);
+
+    let tasks = db.get_tasks(&user).await.unwrap();
+    assert_eq!(tasks.len(), 1);
+    assert_eq!(tasks[0].description, "first line\nsecond line");
+    assert_eq!(tasks[0].timespan, Timespan::new(&at(5, 17), &at(5, 17)).unwrap());
+    assert!(tasks[0].completed);
+
+    // importing the same file again updates the items instead of duplicating them
+    db.event_edit_name(&standup.uuid, "renamed").await.unwrap();
+    db.event_change_category(&holiday.uuid, &work).await.unwrap();
+    let report = import_calendar(db, &user, CALENDAR).await.unwrap();
+    assert_eq!(report.created, 0);
+    assert_eq!(report.updated, 3);
+    let events = db.get_events(&user).await.unwrap();
+    assert_eq!(events.len(), 2);
+    assert!(events.iter().any(|x| x.name == "stand up" && x.uuid == standup.uuid));
+    // and keeps the category of one without CATEGORIES
+    assert!(events.iter().any(|x| x.uuid == holiday.uuid && x.category == work));
+    assert_eq!(db.get_tasks(&user).await.unwrap().len(), 1);
+
+    // a missing category is created once and reused
+    let other = db.add_user("heiko", "pass").await.unwrap().uuid;
+    let report = import_calendar(db, &other, CALENDAR).await.unwrap();
+    assert_eq!(report.categories_created, vec!["Work"]);
+    // next to "Uncategorised" for the holiday
+    assert_eq!(db.get_categories(&other).await.unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn test_import_memory_store() {
+    check_import(&MemoryStore::new()).await;
+}
+
+#[cfg(feature = "kv-mem")]
+#[tokio::test]
+async fn test_import_embedded_surreal() {
+    use crate::backend::database::{connection::{connect, DatabaseEngine}, migrations::migrate};
+
+    let db = connect(&DatabaseEngine::Memory).await.unwrap();
+    migrate(&db).await.unwrap();
+    check_import(&db).await;
+}
+
+#[tokio::test]
+async fn test_reimport_export() {
+    let db = MemoryStore::new();
+    let user = db.add_user("micha", "pass").await.unwrap().uuid;
+    let category = db.add_category("work", 0, &user).await.unwrap();
+    db.add_event("standup", "", &Timespan::new(&at(3, 9), &at(3, 10)).unwrap(), &category.uuid, None, &user).await.unwrap();
+    db.add_task("report", "", &Timespan::new(&at(5, 12), &at(5, 17)).unwrap(), &category.uuid, None, &user).await.unwrap();
+
+    let events = db.get_events(&user).await.unwrap();
+    let tasks = db.get_tasks(&user).await.unwrap();
+    let exported = export_calendar(&events, &tasks, &[category], &at(1, 0));
+    let report = import_calendar(&db, &user, &exported).await.unwrap();
+
+    assert_eq!(report.created, 0);
+    assert_eq!(report.updated, 2);
+    assert!(report.errors.is_empty());
+    assert_eq!(db.get_events(&user).await.unwrap(), events);
+    assert_eq!(db.get_tasks(&user).await.unwrap(), tasks);
+}
+
+#[tokio::test]
+async fn test_import_time_zones() {
+    let db = MemoryStore::new();
+    let user = db.add_user("micha", "pass").await.unwrap().uuid;
+    db.set_time_zone(&user, LocalZone(Berlin)).await.unwrap();
+    let calendar = "BEGIN:VCALENDAR\r
+BEGIN:VEVENT\r
+UID:standup@other\r
+DTSTART;TZID=Europe/Berlin:20230324T090000\r
+DTEND;TZID=Europe/Berlin:20230324T093000\r
+RRULE:FREQ=DAILY;COUNT=3\r
+EXDATE;TZID=Europe/Berlin:20230325T090000\r
+SUMMARY:standup\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+UID:lunch@other\r
+DTSTART;TZID=\"America/New_York\":20230324T120000\r
+DURATION:PT1H\r
+SUMMARY:lunch\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+UID:floating@other\r
+DTSTART:20230324T180000\r
+DTEND;TZID=Somewhere/Else:20230324T190000\r
+SUMMARY:floating\r
+END:VEVENT\r
+END:VCALENDAR\r
+";
+    let report = import_calendar(&db, &user, calendar).await.unwrap();
+    assert_eq!((report.created, report.errors.len()), (3, 0));
+    let events = db.get_events(&user).await.unwrap();
+    let event = |name| events.iter().find(|x| x.name == name).unwrap();
+    let utc = |day, hour, minute| Utc.with_ymd_and_hms(2023, 3, day, hour, minute, 0).unwrap();
+
+    let standup = event("standup");
+    assert_eq!(standup.timespan, Timespan::new(&utc(24, 8, 0), &utc(24, 8, 30)).unwrap());
+    assert_eq!(standup.recurrence.as_ref().unwrap().time_zone, LocalZone(Berlin));
+    // the third day is after the clocks went forward, and still at 09:00 in Berlin
+    let starts = standup.occurrences(&Timespan::new(&utc(1, 0, 0), &utc(31, 0, 0)).unwrap()).into_iter().map(|x| x.timespan.start).collect::<Vec<_>>();
+    assert_eq!(starts, vec![utc(24, 8, 0), utc(26, 7, 0)]);
+    assert_eq!(event("lunch").timespan.start, utc(24, 16, 0));
+    // times without a known zone are in the user's
+    assert_eq!(event("floating").timespan, Timespan::new(&utc(24, 17, 0), &utc(24, 18, 0)).unwrap());
+
+    // exporting writes the recurring event in its zone again
+    let exported = export_calendar(&events, &[], &[], &at(1, 0));
+    assert!(exported.contains("DTSTART;TZID=Europe/Berlin:20230324T090000\r\n"));
+    assert!(exported.contains("EXDATE;TZID=Europe/Berlin:20230325T090000\r\n"));
+    assert!(exported.contains("DTSTART:20230324T160000Z\r\n"));
+    let report = import_calendar(&db, &user, &exported).await.unwrap();
+    assert_eq!((report.updated, report.errors.len()), (3, 0));
+    assert_eq!(db.get_events(&user).await.unwrap(), events);
+}
+
+#[tokio::test]
+async fn test_import_all_day() {
+    let db = MemoryStore::new();
+    let user = db.add_user("micha", "pass").await.unwrap().uuid;
+    // dates are the same in every zone
+    db.set_time_zone(&user, LocalZone(Berlin)).await.unwrap();
+    let calendar = "BEGIN:VCALENDAR\r
+BEGIN:VEVENT\r
+UID:holiday@other\r
+DTSTART;VALUE=DATE:20230324\r
+DTEND;VALUE=DATE:20230327\r
+SUMMARY:holiday\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+UID:birthday@other\r
+DTSTART;VALUE=DATE:20230325\r
+RRULE:FREQ=YEARLY\r
+EXDATE;VALUE=DATE:20240325\r
+SUMMARY:birthday\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+UID:birthday@other\r
+RECURRENCE-ID;VALUE=DATE:20250325\r
+DTSTART;VALUE=DATE:20250326\r
+SUMMARY:late birthday\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+UID:week@other\r
+DTSTART;VALUE=DATE:20230327\r
+DURATION:P1W\r
+SUMMARY:week off\r
+END:VEVENT\r
+BEGIN:VTODO\r
+UID:taxes@other\r
+DUE;VALUE=DATE:20230331\r
+SUMMARY:taxes\r
+END:VTODO\r
+BEGIN:VTODO\r
+UID:move@other\r
+DTSTART;VALUE=DATE:20230328\r
+DUE;VALUE=DATE:20230329\r
+SUMMARY:move\r
+END:VTODO\r
+END:VCALENDAR\r
+";
+    let report = import_calendar(&db, &user, calendar).await.unwrap();
+    assert_eq!((report.created, report.errors.len()), (5, 0));
+    let date = |year, month, day| NaiveDate::from_ymd_opt(year, month, day).unwrap();
+    let events = db.get_events(&user).await.unwrap();
+    let event = |name| events.iter().find(|x| x.name == name).unwrap();
+    assert_eq!(event("holiday").timespan, Timespan::all_day(&date(2023, 3, 24), &date(2023, 3, 26)).unwrap());
+    assert_eq!(event("week off").timespan, Timespan::all_day(&date(2023, 3, 27), &date(2023, 4, 2)).unwrap());
+    let birthday = event("birthday");
+    assert_eq!(birthday.recurrence.as_ref().unwrap().time_zone, LocalZone::default());
+    let years = Timespan::new(&Utc.with_ymd_and_hms(2023, 1, 1, 0, 0, 0).unwrap(), &Utc.with_ymd_and_hms(2026, 1, 1, 0, 0, 0).unwrap()).unwrap();
+    let spans = birthday.occurrences(&years).into_iter().map(|x| x.timespan).collect::<Vec<_>>();
+    assert_eq!(spans, vec![Timespan::all_day(&date(2023, 3, 25), &date(2023, 3, 25)).unwrap(), Timespan::all_day(&date(2025, 3, 26), &date(2025, 3, 26)).unwrap()]);
+    let tasks = db.get_tasks(&user).await.unwrap();
+    let task = |name| tasks.iter().find(|x| x.name == name).unwrap();
+    assert_eq!(task("taxes").timespan, Timespan::all_day(&date(2023, 3, 31), &date(2023, 3, 31)).unwrap());
+    assert_eq!(task("move").timespan, Timespan::all_day(&date(2023, 3, 28), &date(2023, 3, 29)).unwrap());
+
+    let exported = export_calendar(&events, &tasks, &[], &at(1, 0));
+    for line in ["DTSTART;VALUE=DATE:20230324", "DTEND;VALUE=DATE:20230327", "EXDATE;VALUE=DATE:20240325", "RECURRENCE-ID;VALUE=DATE:20250325", "DUE;VALUE=DATE:20230331"] {
+        assert!(exported.contains(&format!("{line}\r\n")), "{line} missing");
+    }
+    let report = import_calendar(&db, &user, &exported).await.unwrap();
+    assert_eq!((report.updated, report.errors.len()), (5, 0));
+    assert_eq!(db.get_events(&user).await.unwrap(), events);
+    assert_eq!(db.get_tasks(&user).await.unwrap(), tasks);
+}
+
+#[tokio::test]
+async fn test_import_unreadable_file() {
+    let db = MemoryStore::new();
+    let user = db.add_user("micha", "pass").await.unwrap().uuid;
+
+    let report = import_calendar(&db, &user, "BEGIN:VCALENDAR\r\nBEGIN:VEVENT\r\n").await.unwrap();
+    assert_eq!(report.created, 0);
+    assert_eq!(report.errors.len(), 1);
+    assert_eq!(report.errors[0].line, 0);
+    assert!(db.get_events(&user).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_import_huge_values() {
+    let db = MemoryStore::new();
+    let user = db.add_user("micha", "pass").await.unwrap().uuid;
+
+    let event = |uid: &str, lines: &str| format!("BEGIN:VEVENT\r\nUID:{uid}\r\n{lines}SUMMARY:{uid}\r\nEND:VEVENT\r\n");
+    let input = [
+        "BEGIN:VCALENDAR\r\n".to_string(),
+        event("weeks", "DTSTART:20230403T090000Z\r\nDURATION:P99999999999999W\r\n"),
+        event("seconds", "DTSTART:20230403T090000Z\r\nDURATION:PT9223372036854775807S\r\n"),
+        event("sum", "DTSTART:20230403T090000Z\r\nDURATION:P1000000000000000DT9000000000000000H\r\n"),
+        event("far", "DTSTART:99991231T090000Z\r\nDURATION:P100000000D\r\n"),
+        event("far-dates", "DTSTART;VALUE=DATE:20230403\r\nDURATION:P100000000D\r\n"),
+        event("year", "DTSTART:+1000000101T090000Z\r\n"),
+        event("last", "DTSTART;VALUE=DATE:99991231\r\n"),
+        "END:VCALENDAR\r\n".to_string(),
+    ].concat();
+    let report = import_calendar(&db, &user, &input).await.unwrap();
+    let failed = report.errors.iter().map(|x| x.uid.clone().unwrap()).collect::<Vec<_>>();
+    assert_eq!(failed, vec!["weeks", "seconds", "sum", "far", "far-dates", "year"]);
+    assert_eq!(report.created, 1);
+    assert_eq!(db.get_events(&user).await.unwrap()[0].timespan, Timespan::all_day(&NaiveDate::from_ymd_opt(9999, 12, 31).unwrap(), &NaiveDate::from_ymd_opt(9999, 12, 31).unwrap()).unwrap());
+}
@@ -0,0 +1,174 @@
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use crate::{backend::{database::{db_error::DBerror, memory::MemoryStore, store::Store}, jobs::{retry_delay, BackgroundJob, JobResult, JobRunner}}, common::model::Job};
+
+fn at(minute: u32) -> DateTime::<Utc> {
+    Utc.with_ymd_and_hms(2023, 5, 1, 9, minute, 0).unwrap()
+}
+
+/// Locks and finishes jobs in `db`, checking only one runner holds a job at a time.
+async fn check_job_locks(db: &dyn Store) {
+    let now = at(0);
+    let until = now + Duration::minutes(10);
+    assert!(matches!(db.lock_job("cleanup", "a", &now, &until).await, Err(DBerror::JobNotFound(_))));
+
+    assert_eq!(db.ensure_job(&Job::new("cleanup", &now)).await.unwrap().next_run, now);
+    // a job that exists already keeps its schedule
+    assert_eq!(db.ensure_job(&Job::new("cleanup", &at(5))).await.unwrap().next_run, now);
+    db.ensure_job(&Job::new("archive", &at(30))).await.unwrap();
+    let names = db.get_jobs().await.unwrap().into_iter().map(|x| x.name).collect::<Vec<_>>();
+    assert_eq!(names, vec!["archive".to_string(), "cleanup".to_string()]);
+
+    // not due yet
+    assert_eq!(db.lock_job("archive", "a", &now, &until).await.unwrap(), None);
+
+    let mut locked = db.lock_job("cleanup", "a", &now, &until).await.unwrap().unwrap();
+    assert_eq!(locked.locked_by, Some("a".to_string()));
+    assert_eq!(locked.last_started_at, Some(now));
+    assert!(locked.is_running(&now));
+    assert_eq!(db.lock_job("cleanup", "b", &at(1), &(at(1) + Duration::minutes(10))).await.unwrap(), None);
+
+    // runner a took too long, so b takes the job over and a's outcome is dropped
+    let taken = db.lock_job("cleanup", "b", &at(11), &(at(11) + Duration::minutes(10))).await.unwrap().unwrap();
+    assert_eq!(taken.locked_by, Some("b".to_string()));
+    locked.locked_by = None;
+    locked.locked_until = None;
+    locked.next_run = at(59);
+    assert!(!db.finish_job(&locked, "a").await.unwrap());
+    assert_eq!(db.get_job("cleanup").await.unwrap().locked_by, Some("b".to_string()));
+
+    let mut done = taken.clone();
+    done.locked_by = None;
+    done.locked_until = None;
+    done.last_finished_at = Some(at(12));
+    done.next_run = at(40);
+    assert!(db.finish_job(&done, "b").await.unwrap());
+    assert_eq!(db.get_job("cleanup").await.unwrap(), done);
+    assert_eq!(db.lock_job("cleanup", "a", &at(20), &(at(20) + Duration::minutes(10))).await.unwrap(), None);
+    assert!(db.lock_job("cleanup", "a", &at(40), &(at(40) + Duration::minutes(10))).await.unwrap().is_some());
+    assert!(matches!(db.get_job("missing").await, Err(DBerror::JobNotFound(_))));
+}
+
+#[tokio::test]
+async fn test_job_locks_memory_store() {
+    check_job_locks(&MemoryStore::new()).await;
+}
+
+#[cfg(feature = "kv-mem")]
+#[tokio::test]
+async fn test_job_locks_embedded_surreal() {
+    use crate::backend::database::{connection::{connect, DatabaseEngine}, migrations::migrate};
+
+    let db = connect(&DatabaseEngine::Memory).await.unwrap();
+    migrate(&db).await.unwrap();
+    check_job_locks(&db).await;
+}
+
+#[test]
+fn test_retry_delay() {
+    assert_eq!(retry_delay(1), Duration::seconds(30));
+    assert_eq!(retry_delay(2), Duration::minutes(1));
+    assert_eq!(retry_delay(4), Duration::minutes(4));
+    assert_eq!(retry_delay(8), Duration::hours(1));
+    assert_eq!(retry_delay(u32::MAX), Duration::hours(1));
+}
+
+/// A job counting its runs, failing while `fail` is set.
+#[derive(Clone)]
+struct Counter {
+    runs: Arc<AtomicUsize>,
+    fail: Arc<AtomicBool>,
+}
+
+#[async_trait]
+impl BackgroundJob for Counter {
+    fn name(&self) -> &'static str {
+        "counter"
+    }
+
+    fn interval(&self) -> Duration {
+        Duration::minutes(5)
+    }
+
+    async fn run(&self, _: &dyn Store, _: &DateTime::<Utc>) -> JobResult {
+        self.runs.fetch_add(1, Ordering::SeqCst);
+        if self.fail.load(Ordering::SeqCst) {
+            return Err("broken".into());
+        }
+        Ok(())
+    }
+}
+
+/// A job that panics on every run.
+struct Panicking;
+
+#[async_trait]
+impl BackgroundJob for Panicking {
+    fn name(&self) -> &'static str {
+        "panicking"
+    }
+
+    fn interval(&self) -> Duration {
+        Duration::minutes(5)
+    }
+
+    async fn run(&self, _: &dyn Store, _: &DateTime::<Utc>) -> JobResult {
+        panic!("boom");
+    }
+}
+
+#[tokio::test]
+async fn test_job_runner() {
+    let db: Arc<dyn Store> = Arc::new(MemoryStore::new());
+    let counter = Counter { runs: Arc::new(AtomicUsize::new(0)), fail: Arc::new(AtomicBool::new(false)) };
+    // two server instances sharing the store
+    let first = JobRunner::new(db.clone()).with(counter.clone());
+    let second = JobRunner::new(db.clone()).with(counter.clone());
+
+    assert_eq!(first.run_due(&at(0)).await.unwrap(), 1);
+    assert_eq!(second.run_due(&at(0)).await.unwrap(), 0);
+    let job = db.get_job("counter").await.unwrap();
+    assert_eq!(job.next_run, at(5));
+    assert!(!job.is_running(&at(0)));
+    assert_eq!(second.run_due(&at(4)).await.unwrap(), 0);
+    assert_eq!(second.run_due(&at(5)).await.unwrap(), 1);
+    assert_eq!(counter.runs.load(Ordering::SeqCst), 2);
+
+    // failures are retried sooner than the interval at first, then less and less often
+    counter.fail.store(true, Ordering::SeqCst);
+    assert_eq!(first.run_due(&at(10)).await.unwrap(), 1);
+    let job = db.get_job("counter").await.unwrap();
+    assert_eq!((job.failures, job.last_error.as_deref()), (1, Some("broken")));
+    assert_eq!(job.next_run, at(10) + Duration::seconds(30));
+    assert_eq!(first.run_due(&job.next_run).await.unwrap(), 1);
+    let job = db.get_job("counter").await.unwrap();
+    assert_eq!(job.failures, 2);
+    assert_eq!(job.next_run, at(10) + Duration::seconds(90));
+
+    counter.fail.store(false, Ordering::SeqCst);
+    assert_eq!(second.run_due(&job.next_run).await.unwrap(), 1);
+    let job = db.get_job("counter").await.unwrap();
+    assert_eq!((job.failures, job.last_error), (0, None));
+    assert_eq!(counter.runs.load(Ordering::SeqCst), 5);
+}
+
+#[tokio::test]
+async fn test_panicking_job() {
+    let db: Arc<dyn Store> = Arc::new(MemoryStore::new());
+    let counter = Counter { runs: Arc::new(AtomicUsize::new(0)), fail: Arc::new(AtomicBool::new(false)) };
+    let runner = JobRunner::new(db.clone()).with(Panicking).with(counter.clone());
+
+    // the panic fails the job like an error, and the jobs after it still run
+    assert_eq!(runner.run_due(&at(0)).await.unwrap(), 2);
+    assert_eq!(counter.runs.load(Ordering::SeqCst), 1);
+    let job = db.get_job("panicking").await.unwrap();
+    assert_eq!(job.failures, 1);
+    assert!(job.last_error.as_deref().is_some_and(|x| x.contains("panicked")));
+    assert!(!job.is_running(&at(0)));
+    assert_eq!(job.next_run, at(0) + retry_delay(1));
+    assert_eq!(runner.run_due(&job.next_run).await.unwrap(), 1);
+    assert_eq!(db.get_job("panicking").await.unwrap().failures, 2);
+}
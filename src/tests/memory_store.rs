@@ -0,0 +1,131 @@
+use chrono::{Utc, Duration};
+use surrealdb::sql::Uuid;
+use crate::{backend::database::{db_error::DBerror, memory::MemoryStore, store::Store}, common::{model::{Category, Event, User, Task, Timespan, TimespanError, WeekStart, LocalZone}, recurrence::{Frequency, Recurrence}}};
+
+#[tokio::test]
+async fn test_create() {
+    let db = MemoryStore::new();
+    let time = Utc::now();
+    let user = db.add_user("micha", "pass").await.unwrap().uuid;
+    let category = db.add_category("category1", 0, &user).await.unwrap().uuid;
+    let task = db.add_task("test_task", "task_description", &Timespan::new(&time, &time).unwrap(), &category, None, &user).await.unwrap().uuid;
+    let event = db.add_event("test_event", "event_description", &Timespan::new(&time, &time).unwrap(), &category, None, &user).await.unwrap().uuid;
+
+    let user_test = User {
+        name: "micha".to_string(),
+        hashed_password: db.get_user(&user).await.unwrap().hashed_password,
+        joined_at: db.get_user(&user).await.unwrap().joined_at,
+        categories: vec![category.clone()],
+        feed_token: db.get_user(&user).await.unwrap().feed_token,
+        week_start: WeekStart::default(),
+        email: None,
+        time_zone: LocalZone::default(),
+        uuid: user.clone(),
+    };
+
+    let category_test = Category {
+        name: "category1".to_string(),
+        color: 0,
+        hidden: false,
+        user: user.clone(),
+        uuid: category.clone(),
+    };
+
+    let task_test = Task {
+        name: "test_task".to_string(),
+        description: "task_description".to_string(),
+        timespan: Timespan::new(&time, &time).unwrap(),
+        category: category.clone(),
+        completed: false,
+        recurrence: None,
+        ical_uid: None,
+        reminders: Vec::new(),
+        user: user.clone(),
+        uuid: task.clone(),
+    };
+
+    let event_test = Event {
+        name: "test_event".to_string(),
+        description: "event_description".to_string(),
+        timespan: Timespan::new(&time, &time).unwrap(),
+        category: category.clone(),
+        recurrence: None,
+        ical_uid: None,
+        reminders: Vec::new(),
+        user: user.clone(),
+        uuid: event.clone(),
+    };
+
+    assert_eq!(db.get_user(&user).await.unwrap(), user_test);
+    assert_eq!(db.get_category(&category).await.unwrap(), category_test);
+    assert_eq!(db.get_task(&task).await.unwrap(), task_test);
+    assert_eq!(db.get_event(&event).await.unwrap(), event_test);
+    assert!(matches!(db.add_user("micha", "pass").await, Err(DBerror::UserAlreadyExists(_))));
+}
+
+#[tokio::test]
+async fn test_update() {
+    let db = MemoryStore::new();
+    let time = Utc::now();
+    let user = db.add_user("micha", "pass").await.unwrap().uuid;
+    let category = db.add_category("category1", 0, &user).await.unwrap().uuid;
+    let task = db.add_task("test_task", "", &Timespan::new(&time, &time).unwrap(), &category, None, &user).await.unwrap().uuid;
+    let event = db.add_event("test_event", "", &Timespan::new(&time, &time).unwrap(), &category, None, &user).await.unwrap().uuid;
+
+    db.change_username(&user, "michah").await.unwrap();
+    db.task_edit_name(&task, "new name!").await.unwrap();
+    db.task_set_completion(&task, true).await.unwrap();
+    let later = Timespan::new(&time, &(time + Duration::hours(1))).unwrap();
+    db.event_edit_timespan(&event, &later).await.unwrap();
+    db.category_change_color(&category, 0x1E70F0).await.unwrap();
+
+    assert_eq!(db.user_id_from_name("michah").await.unwrap(), user);
+    assert!(db.user_id_from_name("micha").await.is_err());
+    let updated_task = db.get_task(&task).await.unwrap();
+    assert_eq!(updated_task.name, "new name!");
+    assert!(updated_task.completed);
+    assert_eq!(db.get_event(&event).await.unwrap().timespan, later);
+    assert_eq!(db.get_category(&category).await.unwrap().color, 0x1E70F0);
+}
+
+#[tokio::test]
+async fn test_invalid_timespans() {
+    let db = MemoryStore::new();
+    let time = Utc::now();
+    let earlier = time - Duration::hours(1);
+    let user = db.add_user("micha", "pass").await.unwrap().uuid;
+    let category = db.add_category("category1", 0, &user).await.unwrap().uuid;
+
+    let backwards = Timespan { start: time, end: earlier, all_day: false };
+    assert!(matches!(db.add_task("task", "", &backwards, &category, None, &user).await, Err(DBerror::InvalidTimespan(TimespanError::EndBeforeStart))));
+    assert!(matches!(db.add_event("event", "", &backwards, &category, None, &user).await, Err(DBerror::InvalidTimespan(TimespanError::EndBeforeStart))));
+    let mut never = Recurrence::new(Frequency::Daily);
+    never.interval = 0;
+    assert!(matches!(db.add_task("task", "", &Timespan::new(&time, &time).unwrap(), &category, Some(never), &user).await, Err(DBerror::InvalidRecurrence(_))));
+    assert!(db.get_tasks(&user).await.unwrap().is_empty());
+    assert!(db.get_events(&user).await.unwrap().is_empty());
+
+    let mut event = db.add_event("event", "", &Timespan::new(&time, &time).unwrap(), &category, None, &user).await.unwrap();
+    assert!(matches!(db.event_edit_timespan(&event.uuid, &backwards).await, Err(DBerror::InvalidTimespan(_))));
+    event.timespan = Timespan { start: time, end: time + Duration::hours(1), all_day: true };
+    assert!(matches!(db.put_event(&event).await, Err(DBerror::InvalidTimespan(TimespanError::NotWholeDates))));
+    assert_eq!(db.get_event(&event.uuid).await.unwrap().timespan, Timespan::new(&time, &time).unwrap());
+}
+
+#[tokio::test]
+async fn test_delete() {
+    let db = MemoryStore::new();
+    let time = Utc::now();
+    let micha = db.add_user("micha", "pass").await.unwrap().uuid;
+    let heiko = db.add_user("heiko", "pass").await.unwrap().uuid;
+    let task = db.add_task("task1", "", &Timespan::new(&time, &time).unwrap(), &Uuid::new(), None, &micha).await.unwrap().uuid;
+    db.add_task("task2", "", &Timespan::new(&time, &time).unwrap(), &Uuid::new(), None, &heiko).await.unwrap();
+
+    assert_eq!(db.get_tasks(&micha).await.unwrap().len(), 1);
+    db.delete_task(&task).await.unwrap();
+    assert!(db.get_tasks(&micha).await.unwrap().is_empty());
+    assert_eq!(db.get_tasks(&heiko).await.unwrap().len(), 1);
+    assert!(matches!(db.delete_task(&task).await, Err(DBerror::TaskNotFound(_))));
+    db.delete_user(&heiko).await.unwrap();
+    assert!(db.get_user(&heiko).await.is_err());
+}
@@ -0,0 +1,58 @@
+use surrealdb::{Surreal, engine::any::Any};
+use crate::backend::database::{connection::{connect, DatabaseEngine}, db_error::DBerror, db_requests::{add_user, get_user, verify_password}, migrations::{migrate, schema_version, LATEST_VERSION}};
+
+async fn setup() -> Surreal<Any> {
+    connect(&DatabaseEngine::Memory).await.unwrap()
+}
+
+#[tokio::test]
+async fn test_migrate_fresh() {
+    let db = setup().await;
+    assert_eq!(schema_version(&db).await.unwrap(), 0);
+    assert_eq!(migrate(&db).await.unwrap(), LATEST_VERSION);
+    assert_eq!(schema_version(&db).await.unwrap(), LATEST_VERSION);
+    // running again is a no-op
+    assert_eq!(migrate(&db).await.unwrap(), LATEST_VERSION);
+}
+
+#[tokio::test]
+async fn test_refuse_newer_schema() {
+    let db = setup().await;
+    db.query("UPDATE migrations:current SET version = $version")
+        .bind(("version", LATEST_VERSION + 1))
+        .await.unwrap();
+    assert!(matches!(migrate(&db).await, Err(DBerror::SchemaTooNew(found, supported)) if found == LATEST_VERSION + 1 && supported == LATEST_VERSION));
+}
+
+#[tokio::test]
+async fn test_keep_unhashed_passwords() {
+    let db = setup().await;
+    // a user as the schema before migrations stored them, with the password as raw bytes
+    let user = surrealdb::sql::Uuid::new();
+    db.query("CREATE type::thing('users', $id) SET name = 'micha', hashed_password = [112, 97, 115, 115], joined_at = time::now(), categories = [], uuid = $uuid")
+        .bind(("id", user.to_raw()))
+        .bind(("uuid", user.clone()))
+        .await.unwrap()
+        .check().unwrap();
+    migrate(&db).await.unwrap();
+
+    assert_eq!(get_user(&db, &user).await.unwrap().hashed_password, "legacy:[112, 97, 115, 115]");
+    assert!(!verify_password(&db, &user, "wrong").await.unwrap());
+    assert!(verify_password(&db, &user, "pass").await.unwrap());
+    // the first login replaces it with a hash
+    let hashed = get_user(&db, &user).await.unwrap().hashed_password;
+    assert!(hashed.starts_with("$argon2id$"));
+    assert!(verify_password(&db, &user, "pass").await.unwrap());
+}
+
+#[tokio::test]
+async fn test_unique_username() {
+    let db = setup().await;
+    migrate(&db).await.unwrap();
+    add_user(&db, "micha", "pass").await.unwrap();
+    // bypass the check in add_user, the unique index must still reject the duplicate
+    let duplicate = db.query("CREATE users SET name = 'micha', hashed_password = '', joined_at = time::now(), categories = [], uuid = rand::uuid()")
+        .await.unwrap()
+        .check();
+    assert!(duplicate.is_err());
+}
@@ -1,10 +1,51 @@
+#[cfg(test)]
+mod memory_store;
+#[cfg(test)]
+mod range_queries;
+#[cfg(test)]
+mod recurrence;
+#[cfg(test)]
+mod ical;
+#[cfg(test)]
+mod ical_import;
+#[cfg(test)]
+mod password;
+#[cfg(test)]
+mod day_layout;
+#[cfg(test)]
+mod week;
+#[cfg(test)]
+mod navigation;
+#[cfg(test)]
+mod year;
+#[cfg(test)]
+mod agenda;
+#[cfg(test)]
+mod categories;
+#[cfg(all(test, feature = "ssr"))]
+mod auth;
+#[cfg(all(test, feature = "ssr"))]
+mod api;
+#[cfg(all(test, feature = "ssr"))]
+mod account;
+#[cfg(all(test, feature = "ssr"))]
+mod reminders;
+#[cfg(all(test, feature = "ssr"))]
+mod jobs;
+#[cfg(all(test, feature = "kv-mem"))]
+mod migrations;
+#[cfg(test)]
+mod time_zones;
+#[cfg(test)]
+mod timespan;
+
 #[cfg(test)]
 mod tests {
     use std::env;
 
     use chrono::Utc;
     use surrealdb::{Surreal, engine::remote::ws::{Client, Ws}, opt::auth::Root, sql::Uuid};
-    use crate::{backend::database::db_requests::{add_event, add_user, user_id_from_name, add_task, get_tasks, get_events, change_username, delete_user, add_category, get_user, get_category, get_task, get_event}, common::model::{Category, Event, User, Task, Timespan}};
+    use crate::{backend::database::db_requests::{add_event, add_user, user_id_from_name, add_task, get_tasks, get_events, change_username, delete_user, add_category, get_user, get_category, get_task, get_event}, common::model::{Category, Event, User, Task, Timespan, WeekStart, LocalZone}};
 
     async fn setup() -> Surreal<Client> {
         let db = Surreal::new::<Ws>("127.0.0.1:8000").await.unwrap();
@@ -25,22 +66,27 @@ mod tests {
     async fn test_create() {
         let db = setup().await;
         let time = Utc::now();
-        let user = add_user(&db, "micha", &"pass".as_bytes().to_vec()).await.unwrap().uuid;
+        let user = add_user(&db, "micha", "pass").await.unwrap().uuid;
         let category = add_category(&db, "category1", 0, &user).await.unwrap().uuid;
-        let task = add_task(&db, "test_task", "task_description", &time, &time, &category, &user).await.unwrap().uuid;
-        let event = add_event(&db, "test_event", "event_description", &time, &time, &category, &user).await.unwrap().uuid;
+        let task = add_task(&db, "test_task", "task_description", &Timespan::new(&time, &time).unwrap(), &category, None, &user).await.unwrap().uuid;
+        let event = add_event(&db, "test_event", "event_description", &Timespan::new(&time, &time).unwrap(), &category, None, &user).await.unwrap().uuid;
 
         let user_test = User {
             name: "micha".to_string(),
-            hashed_password: "pass".as_bytes().to_vec(),
+            hashed_password: get_user(&db, &user).await.unwrap().hashed_password,
             joined_at: get_user(&db, &user).await.unwrap().joined_at,
             categories: vec![category.clone()],
+            feed_token: get_user(&db, &user).await.unwrap().feed_token,
+            week_start: WeekStart::default(),
+            email: None,
+            time_zone: LocalZone::default(),
             uuid: user.clone(),
         };
 
         let category_test = Category {
             name: "category1".to_string(),
             color: 0,
+            hidden: false,
             user: user.clone(),
             uuid: category.clone(),
         };
@@ -48,9 +94,12 @@ mod tests {
         let task_test = Task {
             name: "test_task".to_string(),
             description: "task_description".to_string(),
-            timespan: Timespan::new(&time, &time),
+            timespan: Timespan::new(&time, &time).unwrap(),
             category: category.clone(),
             completed: false,
+            recurrence: None,
+            ical_uid: None,
+            reminders: Vec::new(),
             user: user.clone(),
             uuid: task.clone(),
         };
@@ -58,8 +107,11 @@ mod tests {
         let event_test = Event {
             name: "test_event".to_string(),
             description: "event_description".to_string(),
-            timespan: Timespan::new(&time, &time),
+            timespan: Timespan::new(&time, &time).unwrap(),
             category: category.clone(),
+            recurrence: None,
+            ical_uid: None,
+            reminders: Vec::new(),
             user: user.clone(),
             uuid: event.clone(),
         };
@@ -0,0 +1,62 @@
+use time::{Date, Month};
+use crate::frontend::navigation::{date_from_parts, CalendarRoute, CalendarView};
+
+fn date(year: i32, month: Month, day: u8) -> Date {
+    Date::from_calendar_date(year, month, day).unwrap()
+}
+
+#[test]
+fn test_paths() {
+    let today = date(2023, Month::June, 2);
+    let route = CalendarRoute::from_path("/day/2023/5/14", today).unwrap();
+    assert_eq!(route, CalendarRoute { view: CalendarView::Day, date: date(2023, Month::May, 14) });
+    assert_eq!(route.path(), "/day/2023/5/14");
+    // months always start at their first day
+    let route = CalendarRoute::from_path("/month/2023/5/", today).unwrap();
+    assert_eq!((route.date, route.path().as_str()), (date(2023, Month::May, 1), "/month/2023/5"));
+    assert_eq!(CalendarRoute::from_path("/week", today).unwrap().date, today);
+    let route = CalendarRoute::from_path("/year/2024", today).unwrap();
+    assert_eq!((route.date, route.path().as_str()), (date(2024, Month::January, 1), "/year/2024"));
+    // dates that do not exist show today rather than an error
+    assert_eq!(CalendarRoute::from_path("/day/2023/2/30", today).unwrap().date, today);
+    assert_eq!(CalendarRoute::from_path("/user", today), None);
+    assert_eq!(date_from_parts(Some("2024"), Some("2"), Some("29")), Some(date(2024, Month::February, 29)));
+    assert_eq!(date_from_parts(Some("2024"), Some("13"), Some("1")), None);
+}
+
+#[test]
+fn test_steps() {
+    let today = date(2023, Month::June, 2);
+    let month = CalendarRoute::new(CalendarView::Month, date(2023, Month::January, 20));
+    assert_eq!(month.previous().unwrap().path(), "/month/2022/12");
+    assert_eq!(month.next().unwrap().path(), "/month/2023/2");
+    assert_eq!(month.next().unwrap().next().unwrap().path(), "/month/2023/3");
+    let week = CalendarRoute::new(CalendarView::Week, date(2023, Month::December, 28));
+    assert_eq!(week.next().unwrap().path(), "/week/2024/1/4");
+    assert_eq!(week.title(), "Week 52, 2023");
+    let day = CalendarRoute::new(CalendarView::Day, date(2024, Month::March, 1));
+    assert_eq!(day.previous().unwrap().path(), "/day/2024/2/29");
+    assert_eq!(day.title(), "Friday 1 March 2024");
+    assert_eq!(day.with_view(CalendarView::Month, today).path(), "/month/2024/3");
+    assert_eq!(month.with_view(CalendarView::Day, today).path(), "/day/2023/1/1");
+    assert_eq!(day.today(today).date, today);
+    let year = CalendarRoute::new(CalendarView::Year, date(2024, Month::February, 29));
+    assert_eq!((year.previous().unwrap().path(), year.next().unwrap().path(), year.title()), ("/year/2023".to_string(), "/year/2025".to_string(), "2024".to_string()));
+    assert_eq!(year.with_view(CalendarView::Week, today).path(), "/week/2024/1/1");
+    // unless today lies in it
+    assert_eq!(CalendarRoute::new(CalendarView::Year, today).with_view(CalendarView::Day, today).path(), "/day/2023/6/2");
+}
+
+#[test]
+fn test_steps_at_the_ends() {
+    let today = date(2023, Month::June, 2);
+    let route = |path: &str| CalendarRoute::from_path(path, today).unwrap();
+    assert_eq!(route("/year/9999").next(), None);
+    assert_eq!(route("/year/9999").previous().unwrap().path(), "/year/9998");
+    assert_eq!(route("/month/9999/12").next(), None);
+    assert_eq!(route("/week/9999/12/28").next(), None);
+    assert_eq!(route("/day/9999/12/31").next(), None);
+    assert_eq!(route("/day/-9999/1/1").previous(), None);
+    assert_eq!(route("/month/-9999/1").previous(), None);
+    assert_eq!(route("/day/-9999/1/1").next().unwrap().path(), "/day/-9999/1/2");
+}
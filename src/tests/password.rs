@@ -0,0 +1,68 @@
+use argon2::password_hash::{PasswordHasher, SaltString};
+use argon2::{Algorithm, Argon2, Params, Version};
+use rand_core::OsRng;
+use crate::backend::{database::{memory::MemoryStore, store::Store}, password::{hash_password, needs_rehash, verify_password, DUMMY_HASH}};
+
+#[test]
+fn test_hash_and_verify() {
+    let hash = hash_password("pass").unwrap();
+    assert!(hash.starts_with("$argon2id$v=19$"));
+    assert_ne!(hash, hash_password("pass").unwrap());
+    assert!(verify_password("pass", &hash));
+    assert!(!verify_password("Pass", &hash));
+    assert!(!verify_password("pass", ""));
+    assert!(!verify_password("pass", "pass"));
+    assert!(!needs_rehash(&hash));
+
+    // passwords kept from before hashing match their bytes, and are always rehashed
+    assert!(verify_password("pass", "legacy:[112, 97, 115, 115]"));
+    assert!(!verify_password("Pass", "legacy:[112, 97, 115, 115]"));
+    assert!(!verify_password("", "legacy:[]"));
+    assert!(!verify_password("pass", "legacy:[112, 97, 115, 999]"));
+    assert!(needs_rehash("legacy:[112, 97, 115, 115]"));
+
+    // the hash verified for unknown usernames costs as much as a current one
+    assert!(!needs_rehash(DUMMY_HASH));
+    assert!(!verify_password("", DUMMY_HASH));
+}
+
+#[tokio::test]
+async fn test_store_verify_and_rehash() {
+    let db = MemoryStore::new();
+    let user = db.add_user("micha", "pass").await.unwrap().uuid;
+    assert_ne!(db.get_user(&user).await.unwrap().hashed_password, "pass");
+    assert!(db.verify_password(&user, "pass").await.unwrap());
+    assert!(!db.verify_password(&user, "wrong").await.unwrap());
+
+    db.change_password(&user, "new_pass").await.unwrap();
+    assert!(!db.verify_password(&user, "pass").await.unwrap());
+    assert!(db.verify_password(&user, "new_pass").await.unwrap());
+}
+
+#[cfg(feature = "kv-mem")]
+#[tokio::test]
+async fn test_rehash_outdated_parameters() {
+    use crate::backend::database::{connection::{connect, DatabaseEngine}, migrations::migrate};
+
+    let db = connect(&DatabaseEngine::Memory).await.unwrap();
+    migrate(&db).await.unwrap();
+    let user = db.add_user("micha", "pass").await.unwrap().uuid;
+
+    // a hash made with older, weaker parameters is replaced on the next successful verification
+    let weak = Argon2::new(Algorithm::Argon2i, Version::V0x13, Params::new(8 * 1024, 1, 1, None).unwrap());
+    let old_hash = weak.hash_password(b"pass", &SaltString::generate(&mut OsRng)).unwrap().to_string();
+    assert!(needs_rehash(&old_hash));
+    db.query("UPDATE type::thing('users', $id) SET hashed_password = $hash")
+        .bind(("id", user.to_raw()))
+        .bind(("hash", &old_hash))
+        .await.unwrap()
+        .check().unwrap();
+
+    assert!(!db.verify_password(&user, "wrong").await.unwrap());
+    assert_eq!(db.get_user(&user).await.unwrap().hashed_password, old_hash);
+    assert!(db.verify_password(&user, "pass").await.unwrap());
+    let rehashed = db.get_user(&user).await.unwrap().hashed_password;
+    assert_ne!(rehashed, old_hash);
+    assert!(!needs_rehash(&rehashed));
+    assert!(db.verify_password(&user, "pass").await.unwrap());
+}
@@ -0,0 +1,77 @@
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use surrealdb::sql::Uuid;
+use crate::{backend::database::{memory::MemoryStore, store::Store}, common::{model::Timespan, recurrence::{Frequency, Recurrence}}};
+
+fn at(hour: u32) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2023, 4, 10, hour, 0, 0).unwrap()
+}
+
+/// Fills `db` with items around 10:00-12:00 and checks which ones the range queries return.
+async fn check_range_queries(db: &dyn Store) {
+    let user = db.add_user("micha", "pass").await.unwrap().uuid;
+    let other = db.add_user("heiko", "pass").await.unwrap().uuid;
+    let category = Uuid::new();
+
+    db.add_event("before", "", &Timespan::new(&at(8), &at(10)).unwrap(), &category, None, &user).await.unwrap();
+    db.add_event("overlapping start", "", &Timespan::new(&at(9), &at(11)).unwrap(), &category, None, &user).await.unwrap();
+    db.add_event("inside", "", &Timespan::new(&at(10), &at(10)).unwrap(), &category, None, &user).await.unwrap();
+    db.add_event("overlapping end", "", &Timespan::new(&at(11), &at(13)).unwrap(), &category, None, &user).await.unwrap();
+    db.add_event("after", "", &Timespan::new(&at(12), &at(13)).unwrap(), &category, None, &user).await.unwrap();
+    db.add_event("spanning", "", &Timespan::new(&(at(0) - Duration::days(1)), &(at(23) + Duration::days(1))).unwrap(), &category, None, &user).await.unwrap();
+    db.add_event("other user", "", &Timespan::new(&at(10), &at(11)).unwrap(), &category, None, &other).await.unwrap();
+    db.add_task("due", "", &Timespan::new(&at(11), &at(11)).unwrap(), &category, None, &user).await.unwrap();
+    db.add_task("later", "", &Timespan::new(&at(15), &at(15)).unwrap(), &category, None, &user).await.unwrap();
+
+    let range = Timespan::new(&at(10), &at(12)).unwrap();
+    let events: Vec<String> = db.get_events_in_range(&user, &range).await.unwrap().into_iter().map(|x| x.name).collect();
+    assert_eq!(events, vec!["spanning", "overlapping start", "inside", "overlapping end"]);
+    let tasks: Vec<String> = db.get_tasks_in_range(&user, &range).await.unwrap().into_iter().map(|x| x.name).collect();
+    assert_eq!(tasks, vec!["due"]);
+    assert_eq!(db.get_events(&user).await.unwrap().len(), 6);
+
+    // open tasks come soonest due first, whatever order they were added in
+    db.add_task("earlier", "", &Timespan::new(&at(9), &at(9)).unwrap(), &category, None, &user).await.unwrap();
+    let done = db.add_task("done", "", &Timespan::new(&at(8), &at(8)).unwrap(), &category, None, &user).await.unwrap().uuid;
+    db.task_set_completion(&done, true).await.unwrap();
+    let open: Vec<String> = db.get_open_tasks(&user).await.unwrap().into_iter().map(|x| x.name).collect();
+    assert_eq!(open, vec!["earlier", "due", "later"]);
+
+    // a daily event that started a week earlier occurs in the range, a finished one does not
+    db.add_event("daily", "", &Timespan::new(&(at(11) - Duration::weeks(1)), &(at(11) - Duration::weeks(1))).unwrap(), &category, Some(Recurrence::new(Frequency::Daily)), &other).await.unwrap();
+    let mut finished = Recurrence::new(Frequency::Daily);
+    finished.count = Some(2);
+    db.add_event("short", "", &Timespan::new(&(at(11) - Duration::weeks(1)), &(at(11) - Duration::weeks(1))).unwrap(), &category, Some(finished), &other).await.unwrap();
+    let mut until = Recurrence::new(Frequency::Daily);
+    until.until = Some(at(10));
+    db.add_event("ending", "", &Timespan::new(&(at(10) - Duration::weeks(1)), &(at(11) - Duration::weeks(1))).unwrap(), &category, Some(until), &other).await.unwrap();
+    // recurring items whose last occurrence ends before the range are left out, the occurrences of
+    // the others decide whether they take place in it
+    let candidates: Vec<String> = db.get_events_in_range(&other, &range).await.unwrap().into_iter().map(|x| x.name).collect();
+    assert_eq!(candidates.len(), 3);
+    assert!(!candidates.contains(&"short".to_string()));
+    assert!(candidates.contains(&"ending".to_string()));
+    let occurrences: Vec<_> = db.get_events_in_range(&other, &range).await.unwrap()
+        .into_iter()
+        .flat_map(|x| x.occurrences(&range).into_iter().map(move |o| (x.name.clone(), o.timespan.start)))
+        .collect();
+    assert_eq!(occurrences, vec![
+        ("ending".to_string(), at(10)),
+        ("daily".to_string(), at(11)),
+        ("other user".to_string(), at(10)),
+    ]);
+}
+
+#[tokio::test]
+async fn test_range_memory_store() {
+    check_range_queries(&MemoryStore::new()).await;
+}
+
+#[cfg(feature = "kv-mem")]
+#[tokio::test]
+async fn test_range_embedded_surreal() {
+    use crate::backend::database::{connection::{connect, DatabaseEngine}, migrations::migrate};
+
+    let db = connect(&DatabaseEngine::Memory).await.unwrap();
+    migrate(&db).await.unwrap();
+    check_range_queries(&db).await;
+}
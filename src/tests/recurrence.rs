@@ -0,0 +1,122 @@
+use chrono::{DateTime, Duration, TimeZone, Utc, Weekday};
+use crate::common::{model::Timespan, recurrence::{ByDay, Frequency, OccurrenceOverride, Recurrence, RecurrenceError}};
+
+fn at(year: i32, month: u32, day: u32, hour: u32) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(year, month, day, hour, 0, 0).unwrap()
+}
+
+fn starts(recurrence: &Recurrence, first: &Timespan, range: &Timespan) -> Vec<DateTime<Utc>> {
+    recurrence.expand(first, range).into_iter().map(|x| x.timespan.start).collect()
+}
+
+/// 2023-04-03 is a Monday; every first occurrence lasts an hour.
+fn first() -> Timespan {
+    Timespan::new(&at(2023, 4, 3, 9), &at(2023, 4, 3, 10)).unwrap()
+}
+
+fn year_2023() -> Timespan {
+    Timespan::new(&at(2023, 1, 1, 0), &at(2024, 1, 1, 0)).unwrap()
+}
+
+#[test]
+fn test_daily_interval_count() {
+    let mut rule = Recurrence::new(Frequency::Daily);
+    rule.interval = 2;
+    rule.count = Some(3);
+    assert_eq!(starts(&rule, &first(), &year_2023()), vec![at(2023, 4, 3, 9), at(2023, 4, 5, 9), at(2023, 4, 7, 9)]);
+
+    let occurrence = &rule.expand(&first(), &year_2023())[1];
+    assert_eq!(occurrence.timespan.end, at(2023, 4, 5, 10));
+    assert_eq!(occurrence.recurrence_id, Some(at(2023, 4, 5, 9)));
+}
+
+#[test]
+fn test_weekly_by_day_until() {
+    let mut rule = Recurrence::new(Frequency::Weekly);
+    rule.by_day = vec![ByDay { ordinal: None, weekday: Weekday::Fri }, ByDay { ordinal: None, weekday: Weekday::Mon }];
+    rule.until = Some(at(2023, 4, 14, 9));
+    assert_eq!(starts(&rule, &first(), &year_2023()), vec![at(2023, 4, 3, 9), at(2023, 4, 7, 9), at(2023, 4, 10, 9), at(2023, 4, 14, 9)]);
+}
+
+#[test]
+fn test_only_occurrences_in_range() {
+    let rule = Recurrence::new(Frequency::Weekly);
+    let range = Timespan::new(&at(2023, 5, 1, 0), &at(2023, 5, 15, 0)).unwrap();
+    assert_eq!(starts(&rule, &first(), &range), vec![at(2023, 5, 1, 9), at(2023, 5, 8, 9)]);
+    // an occurrence still running at the start of the range is included
+    let range = Timespan::new(&(at(2023, 5, 1, 9) + Duration::minutes(30)), &at(2023, 5, 2, 0)).unwrap();
+    assert_eq!(starts(&rule, &first(), &range), vec![at(2023, 5, 1, 9)]);
+}
+
+#[test]
+fn test_monthly_skips_short_months() {
+    let first = Timespan::new(&at(2023, 1, 31, 9), &at(2023, 1, 31, 10)).unwrap();
+    let mut rule = Recurrence::new(Frequency::Monthly);
+    rule.count = Some(3);
+    assert_eq!(starts(&rule, &first, &year_2023()), vec![at(2023, 1, 31, 9), at(2023, 3, 31, 9), at(2023, 5, 31, 9)]);
+}
+
+#[test]
+fn test_monthly_ordinal_by_day() {
+    let mut rule = Recurrence::new(Frequency::Monthly);
+    rule.by_day = vec![ByDay { ordinal: Some(-1), weekday: Weekday::Fri }];
+    rule.count = Some(3);
+    assert_eq!(starts(&rule, &first(), &year_2023()), vec![at(2023, 4, 3, 9), at(2023, 4, 28, 9), at(2023, 5, 26, 9)]);
+
+    rule.by_day = vec![ByDay { ordinal: Some(2), weekday: Weekday::Tue }];
+    assert_eq!(starts(&rule, &first(), &year_2023()), vec![at(2023, 4, 3, 9), at(2023, 4, 11, 9), at(2023, 5, 9, 9)]);
+}
+
+#[test]
+fn test_yearly_leap_day() {
+    let first = Timespan::new(&at(2024, 2, 29, 9), &at(2024, 2, 29, 10)).unwrap();
+    let rule = Recurrence::new(Frequency::Yearly);
+    let range = Timespan::new(&at(2024, 1, 1, 0), &at(2033, 1, 1, 0)).unwrap();
+    assert_eq!(starts(&rule, &first, &range), vec![at(2024, 2, 29, 9), at(2028, 2, 29, 9), at(2032, 2, 29, 9)]);
+}
+
+#[test]
+fn test_exdates_and_overrides() {
+    let mut rule = Recurrence::new(Frequency::Daily);
+    rule.count = Some(4);
+    rule.exdates = vec![at(2023, 4, 4, 9)];
+    rule.overrides = vec![OccurrenceOverride {
+        recurrence_id: at(2023, 4, 5, 9),
+        timespan: Timespan::new(&at(2023, 4, 5, 14), &at(2023, 4, 5, 16)).unwrap(),
+        name: Some("moved".to_string()),
+        description: None,
+    }];
+    let occurrences = rule.expand(&first(), &year_2023());
+    // the excluded occurrence still counts towards COUNT
+    assert_eq!(occurrences.iter().map(|x| x.timespan.start).collect::<Vec<_>>(), vec![at(2023, 4, 3, 9), at(2023, 4, 5, 14), at(2023, 4, 6, 9)]);
+    assert_eq!(occurrences[1].name.as_deref(), Some("moved"));
+    assert_eq!(occurrences[1].timespan.end, at(2023, 4, 5, 16));
+
+    // an override can move an occurrence into a range its original start lies outside of
+    rule.overrides[0].timespan = Timespan::new(&at(2023, 4, 3, 12), &at(2023, 4, 3, 13)).unwrap();
+    let range = Timespan::new(&at(2023, 4, 3, 0), &at(2023, 4, 4, 0)).unwrap();
+    assert_eq!(starts(&rule, &first(), &range), vec![at(2023, 4, 3, 9), at(2023, 4, 3, 12)]);
+}
+
+#[test]
+fn test_rrule_round_trip() {
+    let rule: Recurrence = "FREQ=MONTHLY;INTERVAL=2;BYDAY=-1FR,2TU;COUNT=5".parse().unwrap();
+    assert_eq!(rule.frequency, Frequency::Monthly);
+    assert_eq!(rule.interval, 2);
+    assert_eq!(rule.by_day, vec![ByDay { ordinal: Some(-1), weekday: Weekday::Fri }, ByDay { ordinal: Some(2), weekday: Weekday::Tue }]);
+    assert_eq!(rule.to_string(), "FREQ=MONTHLY;INTERVAL=2;BYDAY=-1FR,2TU;COUNT=5");
+
+    let rule: Recurrence = "RRULE:UNTIL=20230501T090000Z;FREQ=WEEKLY;WKST=MO".parse().unwrap();
+    assert_eq!(rule.until, Some(at(2023, 5, 1, 9)));
+    assert_eq!(rule.to_string(), "FREQ=WEEKLY;UNTIL=20230501T090000Z");
+
+    assert!(matches!("FREQ=HOURLY".parse::<Recurrence>(), Err(RecurrenceError::Unsupported(_))));
+    assert!(matches!("FREQ=DAILY;BYMONTHDAY=1".parse::<Recurrence>(), Err(RecurrenceError::Unsupported(_))));
+    assert!(matches!("INTERVAL=2".parse::<Recurrence>(), Err(RecurrenceError::InvalidRule(_))));
+    // ordinals a month or year cannot have, or a daily or weekly rule cannot take, never match
+    for rule in ["FREQ=YEARLY;BYDAY=60MO", "FREQ=YEARLY;BYDAY=-54MO", "FREQ=MONTHLY;BYDAY=6MO", "FREQ=MONTHLY;BYDAY=-6FR", "FREQ=WEEKLY;BYDAY=1MO", "FREQ=MONTHLY;BYDAY=0MO"] {
+        assert!(matches!(rule.parse::<Recurrence>(), Err(RecurrenceError::InvalidRule(_))), "{rule}");
+    }
+    assert!("FREQ=YEARLY;BYDAY=53MO,-53SU".parse::<Recurrence>().is_ok());
+    assert!("FREQ=MONTHLY;BYDAY=5MO,-5SU".parse::<Recurrence>().is_ok());
+}
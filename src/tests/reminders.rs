@@ -0,0 +1,268 @@
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use chrono::{DateTime, Duration, NaiveDate, TimeZone, Utc};
+use surrealdb::sql::Uuid;
+use crate::{backend::{database::{db_error::DBerror, memory::MemoryStore, store::Store}, reminders::{event_reminders, send_due_reminders, task_reminders, Mailer, SmtpConfig, SmtpSecurity}}, common::model::{Event, LocalZone, ReminderDelivery, Task, Timespan}};
+
+/// A local SMTP server keeping every mail it receives, or refusing every recipient.
+struct Sink {
+    port: u16,
+    mails: Arc<Mutex<Vec<String>>>,
+}
+
+fn start_sink(refuse: bool) -> Sink {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let mails = Arc::new(Mutex::new(Vec::new()));
+    let received = mails.clone();
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            _ = serve(stream, refuse, &received);
+        }
+    });
+    Sink { port, mails }
+}
+
+///answers one SMTP connection
+fn serve(stream: TcpStream, refuse: bool, mails: &Mutex<Vec<String>>) -> std::io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut writer = stream;
+    writer.write_all(b"220 sink ESMTP\r\n")?;
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            return Ok(());
+        }
+        let command = line.to_ascii_uppercase();
+        if command.starts_with("RCPT") && refuse {
+            writer.write_all(b"550 no such user\r\n")?;
+        } else if command.starts_with("DATA") {
+            writer.write_all(b"354 go ahead\r\n")?;
+            let mut mail = String::new();
+            loop {
+                line.clear();
+                if reader.read_line(&mut line)? == 0 || line == ".\r\n" {
+                    break;
+                }
+                mail.push_str(&line);
+            }
+            mails.lock().unwrap().push(mail);
+            writer.write_all(b"250 queued\r\n")?;
+        } else if command.starts_with("QUIT") {
+            writer.write_all(b"221 bye\r\n")?;
+            return Ok(());
+        } else {
+            writer.write_all(b"250 ok\r\n")?;
+        }
+    }
+}
+
+fn mailer(port: u16) -> Mailer {
+    Mailer::new(&SmtpConfig {
+        host: "127.0.0.1".to_string(),
+        port,
+        security: SmtpSecurity::None,
+        credentials: None,
+        from: "gressus@localhost".to_string(),
+    }).unwrap()
+}
+
+fn at(hour: u32, minute: u32) -> DateTime::<Utc> {
+    Utc.with_ymd_and_hms(2023, 5, 1, hour, minute, 0).unwrap()
+}
+
+fn event(start: DateTime::<Utc>, recurrence: Option<&str>, reminders: Vec<u32>) -> Event {
+    Event {
+        name: "meet".to_string(),
+        description: String::new(),
+        timespan: Timespan::new(&start, &(start + Duration::hours(1))).unwrap(),
+        category: Uuid::new(),
+        recurrence: recurrence.map(|x| x.parse().unwrap()),
+        ical_uid: None,
+        reminders,
+        user: Uuid::new(),
+        uuid: Uuid::new(),
+    }
+}
+
+#[test]
+fn test_due_reminders() {
+    let meeting = event(at(9, 30), None, vec![10, 60]);
+    let offsets = |now| event_reminders(&meeting, &LocalZone::default(), &now).into_iter().map(|x| x.minutes_before).collect::<Vec<_>>();
+    assert_eq!(offsets(at(8, 0)), Vec::<u32>::new());
+    assert_eq!(offsets(at(9, 0)), vec![60]);
+    // the earlier reminder stays due until the event starts, it is the store that remembers it was sent
+    assert_eq!(offsets(at(9, 25)), vec![10, 60]);
+    assert_eq!(offsets(at(9, 30)), Vec::<u32>::new());
+
+    let daily = event(at(9, 30) - Duration::days(30), Some("FREQ=DAILY"), vec![60]);
+    let due = event_reminders(&daily, &LocalZone::default(), &at(9, 0));
+    assert_eq!(due.len(), 1);
+    assert_eq!(due[0].occurs_at, at(9, 30));
+    assert!(!due[0].task);
+
+    // tasks are due at their end
+    let task = Task {
+        name: "write".to_string(),
+        description: String::new(),
+        timespan: Timespan::new(&at(8, 0), &at(9, 5)).unwrap(),
+        category: Uuid::new(),
+        completed: false,
+        recurrence: None,
+        ical_uid: None,
+        reminders: vec![10],
+        user: Uuid::new(),
+        uuid: Uuid::new(),
+    };
+    let due = task_reminders(&task, &LocalZone::default(), &at(9, 0));
+    assert_eq!(due.len(), 1);
+    assert_eq!(due[0].occurs_at, at(9, 5));
+    assert!(due[0].message(&LocalZone::default()).1.starts_with("write is due at Monday May 1, 2023 09:05"));
+}
+
+#[test]
+fn test_all_day_reminders() {
+    let may_2nd = NaiveDate::from_ymd_opt(2023, 5, 2).unwrap();
+    let mut holiday = event(at(0, 0), None, vec![60]);
+    holiday.timespan = Timespan::all_day(&may_2nd, &may_2nd).unwrap();
+    let berlin = LocalZone(chrono_tz::Europe::Berlin);
+    let new_york = LocalZone(chrono_tz::America::New_York);
+
+    // the day starts at 22:00 UTC in Berlin and at 04:00 UTC in New York
+    let due = event_reminders(&holiday, &berlin, &at(21, 0));
+    assert_eq!(due.iter().map(|x| x.occurs_at).collect::<Vec<_>>(), vec![at(22, 0)]);
+    assert_eq!(due[0].message(&berlin).1, "meet starts on Tuesday May 2, 2023.\n");
+    assert!(event_reminders(&holiday, &LocalZone::default(), &at(21, 0)).is_empty());
+    let due = event_reminders(&holiday, &new_york, &(at(3, 0) + Duration::days(1)));
+    assert_eq!(due.iter().map(|x| x.occurs_at).collect::<Vec<_>>(), vec![at(4, 0) + Duration::days(1)]);
+
+    let task = Task {
+        name: "taxes".to_string(),
+        description: String::new(),
+        timespan: Timespan::all_day(&may_2nd, &may_2nd).unwrap(),
+        category: Uuid::new(),
+        completed: false,
+        recurrence: None,
+        ical_uid: None,
+        reminders: vec![60],
+        user: Uuid::new(),
+        uuid: Uuid::new(),
+    };
+    let due = task_reminders(&task, &berlin, &(at(21, 0) + Duration::days(1)));
+    assert_eq!(due.iter().map(|x| x.occurs_at).collect::<Vec<_>>(), vec![at(22, 0) + Duration::days(1)]);
+    assert_eq!(due[0].message(&berlin).1, "taxes is due by the end of Tuesday May 2, 2023.\n");
+}
+
+/// Claims, finishes and releases reminders in `db`, checking each is only handed out once.
+async fn check_reminder_claims(db: &dyn Store) {
+    let now = at(9, 0);
+    let user = db.add_user("micha", "long enough").await.unwrap().uuid;
+    let category = db.add_category("work", 0, &user).await.unwrap().uuid;
+    let event = db.add_event("meet", "", &Timespan::new(&at(9, 30), &at(10, 0)).unwrap(), &category, None, &user).await.unwrap().uuid;
+    db.event_set_reminders(&event, vec![60]).await.unwrap();
+    db.add_event("quiet", "", &Timespan::new(&at(9, 30), &at(10, 0)).unwrap(), &category, None, &user).await.unwrap();
+    let task = db.add_task("write", "", &Timespan::new(&at(8, 0), &at(9, 5)).unwrap(), &category, None, &user).await.unwrap().uuid;
+    db.task_set_reminders(&task, vec![10]).await.unwrap();
+    let done = db.add_task("done", "", &Timespan::new(&at(8, 0), &at(9, 5)).unwrap(), &category, None, &user).await.unwrap().uuid;
+    db.task_set_reminders(&done, vec![10]).await.unwrap();
+    db.task_set_completion(&done, true).await.unwrap();
+
+    let tasks = db.get_tasks_with_reminders(&now).await.unwrap();
+    assert_eq!(tasks.iter().map(|x| x.uuid.clone()).collect::<Vec<_>>(), vec![task.clone()]);
+    let events = db.get_events_with_reminders(&now).await.unwrap();
+    assert_eq!(events.iter().map(|x| x.uuid.clone()).collect::<Vec<_>>(), vec![event.clone()]);
+    assert!(db.get_events_with_reminders(&at(9, 30)).await.unwrap().is_empty());
+    // an all-day event may still lie ahead in a zone behind UTC
+    let may_day = NaiveDate::from_ymd_opt(2023, 5, 1).unwrap();
+    let holiday = db.add_event("holiday", "", &Timespan::new(&at(0, 0), &at(0, 0)).unwrap(), &category, None, &user).await.unwrap().uuid;
+    db.event_edit_timespan(&holiday, &Timespan::all_day(&may_day, &may_day).unwrap()).await.unwrap();
+    db.event_set_reminders(&holiday, vec![60]).await.unwrap();
+    let holidays = |now| async move { db.get_events_with_reminders(&now).await.unwrap().into_iter().filter(|x| x.name == "holiday").count() };
+    assert_eq!(holidays(at(9, 30)).await, 1);
+    assert_eq!(holidays(at(0, 0) + Duration::days(1)).await, 0);
+
+    let delivery = |claimed_at| ReminderDelivery {
+        key: ReminderDelivery::key_for(&event, &at(9, 30), 60),
+        item: event.clone(),
+        user: user.clone(),
+        occurs_at: at(9, 30),
+        minutes_before: 60,
+        claimed_at,
+        sent_at: None,
+    };
+    let stale_before = |time: DateTime::<Utc>| time - Duration::minutes(10);
+    assert!(db.claim_reminder(&delivery(now), &stale_before(now)).await.unwrap());
+    assert!(!db.claim_reminder(&delivery(now), &stale_before(now)).await.unwrap());
+    // nobody finished the claim, so after a while it is handed out again
+    let later = now + Duration::minutes(15);
+    assert!(db.claim_reminder(&delivery(later), &stale_before(later)).await.unwrap());
+    assert!(!db.claim_reminder(&delivery(later), &stale_before(later)).await.unwrap());
+
+    let key = delivery(now).key;
+    assert_eq!(db.finish_reminder(&key, &later).await.unwrap().sent_at, Some(later));
+    let much_later = now + Duration::days(1);
+    assert!(!db.claim_reminder(&delivery(much_later), &stale_before(much_later)).await.unwrap());
+
+    assert_eq!(db.release_reminder(&key).await.unwrap().key, key);
+    assert!(matches!(db.release_reminder(&key).await, Err(DBerror::ReminderNotFound(_))));
+    assert!(matches!(db.finish_reminder(&key, &now).await, Err(DBerror::ReminderNotFound(_))));
+    assert!(db.claim_reminder(&delivery(now), &stale_before(now)).await.unwrap());
+
+    // the user's sent reminders go with their account
+    db.delete_user(&user).await.unwrap();
+    assert!(matches!(db.release_reminder(&key).await, Err(DBerror::ReminderNotFound(_))));
+}
+
+#[tokio::test]
+async fn test_reminder_claims_memory_store() {
+    check_reminder_claims(&MemoryStore::new()).await;
+}
+
+#[cfg(feature = "kv-mem")]
+#[tokio::test]
+async fn test_reminder_claims_embedded_surreal() {
+    use crate::backend::database::{connection::{connect, DatabaseEngine}, migrations::migrate};
+
+    let db = connect(&DatabaseEngine::Memory).await.unwrap();
+    migrate(&db).await.unwrap();
+    check_reminder_claims(&db).await;
+}
+
+#[tokio::test]
+async fn test_send_due_reminders() {
+    let db = MemoryStore::new();
+    let now = at(9, 0);
+    let micha = db.add_user("micha", "long enough").await.unwrap().uuid;
+    db.set_email(&micha, Some("micha@example.com".to_string())).await.unwrap();
+    let heiko = db.add_user("heiko", "long enough").await.unwrap().uuid;
+    for user in [&micha, &heiko] {
+        let event = db.add_event("meet", "", &Timespan::new(&at(9, 30), &at(10, 0)).unwrap(), &Uuid::new(), None, user).await.unwrap().uuid;
+        db.event_set_reminders(&event, vec![60]).await.unwrap();
+    }
+
+    // heiko has no address, so only micha is reminded
+    let sink = start_sink(false);
+    assert_eq!(send_due_reminders(&db, &mailer(sink.port), &now).await.unwrap(), 1);
+    {
+        let mails = sink.mails.lock().unwrap();
+        assert_eq!(mails.len(), 1);
+        assert!(mails[0].contains("To: micha@example.com"));
+        assert!(mails[0].contains("Subject: Reminder: meet"));
+        assert!(mails[0].contains("meet starts at Monday May 1, 2023 09:30 UTC."));
+    }
+    // sent reminders are recorded in the store, so a restarted scheduler does not send them again
+    assert_eq!(send_due_reminders(&db, &mailer(sink.port), &(now + Duration::minutes(1))).await.unwrap(), 0);
+
+    // a reminder that could not be delivered is tried again on the next run
+    let task = db.add_task("write", "", &Timespan::new(&at(8, 0), &at(9, 5)).unwrap(), &Uuid::new(), None, &micha).await.unwrap().uuid;
+    db.task_set_reminders(&task, vec![10]).await.unwrap();
+    let refusing = start_sink(true);
+    assert_eq!(send_due_reminders(&db, &mailer(refusing.port), &now).await.unwrap(), 0);
+    assert!(refusing.mails.lock().unwrap().is_empty());
+    assert_eq!(send_due_reminders(&db, &mailer(sink.port), &now).await.unwrap(), 1);
+    assert_eq!(sink.mails.lock().unwrap().len(), 2);
+}
@@ -0,0 +1,96 @@
+use chrono::{DateTime, Duration, NaiveDate, TimeZone, Utc};
+use chrono_tz::Europe::Berlin;
+use surrealdb::sql::Uuid;
+use crate::common::{api::calendar::{day_activity, CalendarItem, DayActivity, ItemKind}, model::{LocalZone, Timespan}, recurrence::{Frequency, Recurrence}};
+
+fn utc(month: u32, day: u32, hour: u32, minute: u32) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2023, month, day, hour, minute, 0).unwrap()
+}
+
+fn date(month: u32, day: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(2023, month, day).unwrap()
+}
+
+// in 2023 the clocks in Berlin go forward on March 26th and back on October 29th
+
+#[test]
+fn test_local_zone() {
+    let zone = LocalZone(Berlin);
+    assert_eq!(zone.date_of(&utc(3, 25, 22, 59)), date(3, 25));
+    assert_eq!(zone.date_of(&utc(3, 25, 23, 0)), date(3, 26));
+    assert_eq!(zone.day_span(&date(3, 26)), Timespan::new(&utc(3, 25, 23, 0), &utc(3, 26, 22, 0)).unwrap());
+    assert_eq!(zone.day_span(&date(10, 29)), Timespan::new(&utc(10, 28, 22, 0), &utc(10, 29, 23, 0)).unwrap());
+    assert_eq!(LocalZone::default().day_span(&date(3, 26)), Timespan::new(&utc(3, 26, 0, 0), &utc(3, 27, 0, 0)).unwrap());
+
+    // 02:30 does not exist on March 26th and means 03:30 summer time
+    assert_eq!(zone.instant(&date(3, 26).and_hms_opt(2, 30, 0).unwrap()), utc(3, 26, 1, 30));
+    // 02:30 happens twice on October 29th, the summer time one comes first
+    assert_eq!(zone.instant(&date(10, 29).and_hms_opt(2, 30, 0).unwrap()), utc(10, 29, 0, 30));
+
+    // 09:00 stays 09:00 across the change
+    assert_eq!(zone.add_days(&utc(3, 25, 8, 0), 1), Some(utc(3, 26, 7, 0)));
+    assert_eq!(zone.add_days(&utc(3, 26, 7, 0), -1), Some(utc(3, 25, 8, 0)));
+    assert_eq!(LocalZone::default().add_days(&utc(3, 25, 8, 0), 1), Some(utc(3, 26, 8, 0)));
+    assert_eq!(zone.add_days(&utc(3, 25, 8, 0), i64::MAX), None);
+}
+
+#[test]
+fn test_recurrence_keeps_local_time() {
+    // Friday March 24th 09:00 in Berlin
+    let first = Timespan::new(&utc(3, 24, 8, 0), &utc(3, 24, 9, 0)).unwrap();
+    let range = Timespan::new(&utc(3, 1, 0, 0), &utc(4, 1, 0, 0)).unwrap();
+    let mut rule = Recurrence::new(Frequency::Daily);
+    rule.count = Some(4);
+    let starts = |rule: &Recurrence| rule.expand(&first, &range).into_iter().map(|x| x.timespan).collect::<Vec<_>>();
+    assert_eq!(starts(&rule)[2], Timespan::new(&utc(3, 26, 8, 0), &utc(3, 26, 9, 0)).unwrap());
+
+    rule.time_zone = LocalZone(Berlin);
+    assert_eq!(starts(&rule), vec![
+        Timespan::new(&utc(3, 24, 8, 0), &utc(3, 24, 9, 0)).unwrap(),
+        Timespan::new(&utc(3, 25, 8, 0), &utc(3, 25, 9, 0)).unwrap(),
+        Timespan::new(&utc(3, 26, 7, 0), &utc(3, 26, 8, 0)).unwrap(),
+        Timespan::new(&utc(3, 27, 7, 0), &utc(3, 27, 8, 0)).unwrap(),
+    ]);
+}
+
+#[test]
+fn test_day_activity_in_zone() {
+    let item = |start: DateTime<Utc>| CalendarItem {
+        kind: ItemKind::Event,
+        uuid: Uuid::new(),
+        recurrence_id: None,
+        name: String::new(),
+        timespan: Timespan::new(&start, &(start + Duration::minutes(30))).unwrap(),
+        all_day: false,
+        category: Uuid::new(),
+        color: 0xFF0000,
+    };
+    // 23:30 UTC is already the next day in Berlin
+    let items = [item(utc(3, 25, 23, 30)), item(utc(3, 26, 21, 30))];
+    let counts = |zone| day_activity(&items, &date(3, 25), 3, &zone).into_iter().map(|x: DayActivity| x.count).collect::<Vec<_>>();
+    assert_eq!(counts(LocalZone(Berlin)), vec![0, 2, 0]);
+    assert_eq!(counts(LocalZone::default()), vec![1, 1, 0]);
+}
+
+#[test]
+fn test_all_day_spans() {
+    let holiday = Timespan::all_day(&date(3, 25), &date(3, 26)).unwrap();
+    assert_eq!((holiday.start, holiday.end, holiday.all_day), (utc(3, 25, 0, 0), utc(3, 27, 0, 0), true));
+    assert_eq!(holiday.dates(), Some((date(3, 25), date(3, 26))));
+    assert_eq!(Timespan::new(&utc(3, 25, 0, 0), &utc(3, 27, 0, 0)).unwrap().dates(), None);
+
+    // the same dates in every zone, one of them an hour short in Berlin
+    assert_eq!(LocalZone(Berlin).resolve(&holiday), Timespan::new(&utc(3, 24, 23, 0), &utc(3, 26, 22, 0)).unwrap());
+    assert_eq!(LocalZone::default().resolve(&holiday), Timespan::new(&utc(3, 25, 0, 0), &utc(3, 27, 0, 0)).unwrap());
+    let timed = Timespan::new(&utc(3, 25, 9, 0), &utc(3, 25, 10, 0)).unwrap();
+    assert_eq!(LocalZone(Berlin).resolve(&timed), timed);
+
+    // a rule kept in Berlin still repeats an all-day item on dates
+    let mut rule = Recurrence::new(Frequency::Weekly);
+    rule.count = Some(2);
+    rule.time_zone = LocalZone(Berlin);
+    let first = Timespan::all_day(&date(3, 20), &date(3, 20)).unwrap();
+    let range = Timespan::new(&utc(3, 1, 0, 0), &utc(4, 1, 0, 0)).unwrap();
+    let spans = rule.expand(&first, &range).into_iter().map(|x| x.timespan).collect::<Vec<_>>();
+    assert_eq!(spans, vec![first.clone(), Timespan::all_day(&date(3, 27), &date(3, 27)).unwrap()]);
+}
@@ -0,0 +1,98 @@
+use chrono::{DateTime, Duration, NaiveDate, TimeZone, Utc};
+use chrono_tz::Europe::Berlin;
+use crate::common::model::{LocalZone, Timespan, TimespanError};
+
+fn utc(month: u32, day: u32, hour: u32, minute: u32) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2023, month, day, hour, minute, 0).unwrap()
+}
+
+fn date(month: u32, day: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(2023, month, day).unwrap()
+}
+
+fn span(start: DateTime<Utc>, end: DateTime<Utc>) -> Timespan {
+    Timespan::new(&start, &end).unwrap()
+}
+
+#[test]
+fn test_validation() {
+    assert_eq!(Timespan::new(&utc(5, 1, 10, 0), &utc(5, 1, 9, 0)), Err(TimespanError::EndBeforeStart));
+    assert!(Timespan::new(&utc(5, 1, 10, 0), &utc(5, 1, 10, 0)).is_ok());
+    assert_eq!(Timespan::all_day(&date(5, 2), &date(5, 1)), Err(TimespanError::EndBeforeStart));
+    assert_eq!(Timespan::all_day(&date(5, 1), &date(5, 1)).unwrap(), Timespan { start: utc(5, 1, 0, 0), end: utc(5, 2, 0, 0), all_day: true });
+
+    // spans from a request or a record are not built through the constructors
+    assert_eq!(Timespan { start: utc(5, 1, 10, 0), end: utc(5, 1, 9, 0), all_day: false }.validate(), Err(TimespanError::EndBeforeStart));
+    assert_eq!(Timespan { start: utc(5, 1, 0, 0), end: utc(5, 1, 12, 0), all_day: true }.validate(), Err(TimespanError::NotWholeDates));
+    assert_eq!(Timespan { start: utc(5, 1, 0, 0), end: utc(5, 1, 0, 0), all_day: true }.validate(), Err(TimespanError::NotWholeDates));
+}
+
+#[test]
+fn test_arithmetic() {
+    let morning = span(utc(5, 1, 9, 0), utc(5, 1, 12, 0));
+    let lunch = span(utc(5, 1, 11, 30), utc(5, 1, 13, 0));
+    let afternoon = span(utc(5, 1, 12, 0), utc(5, 1, 17, 0));
+    let noon = span(utc(5, 1, 12, 0), utc(5, 1, 12, 0));
+
+    assert_eq!(morning.duration(), Duration::hours(3));
+    assert_eq!(morning.intersection(&lunch), Some(span(utc(5, 1, 11, 30), utc(5, 1, 12, 0))));
+    assert_eq!(lunch.intersection(&morning), morning.intersection(&lunch));
+    assert_eq!(morning.intersection(&afternoon), None);
+    // a zero-length span belongs to the span starting at its instant, not the one ending there
+    assert_eq!(noon.intersection(&afternoon), Some(noon.clone()));
+    assert_eq!(noon.intersection(&morning), None);
+
+    assert!(morning.contains(&utc(5, 1, 9, 0)));
+    assert!(!morning.contains(&utc(5, 1, 12, 0)));
+    assert!(morning.covers(&span(utc(5, 1, 10, 0), utc(5, 1, 12, 0))));
+    assert!(!morning.covers(&lunch));
+
+    assert_eq!(morning.shift(Duration::hours(-2)), Some(span(utc(5, 1, 7, 0), utc(5, 1, 10, 0))));
+    let holiday = Timespan::all_day(&date(5, 1), &date(5, 2)).unwrap();
+    assert_eq!(holiday.shift(Duration::days(7)), Some(Timespan::all_day(&date(5, 8), &date(5, 9)).unwrap()));
+    assert_eq!(holiday.shift(Duration::days(1 << 30)), None);
+}
+
+#[test]
+fn test_shift_days() {
+    let zone = LocalZone(Berlin);
+    // 09:00 to 10:00 the day before the clocks go forward stays 09:00 to 10:00
+    let meeting = span(utc(3, 25, 8, 0), utc(3, 25, 9, 0));
+    assert_eq!(zone.shift_days(&meeting, 1), Some(span(utc(3, 26, 7, 0), utc(3, 26, 8, 0))));
+    assert_eq!(LocalZone::default().shift_days(&meeting, 1), Some(span(utc(3, 26, 8, 0), utc(3, 26, 9, 0))));
+    // all-day spans keep being whole dates in UTC
+    let holiday = Timespan::all_day(&date(3, 25), &date(3, 25)).unwrap();
+    assert_eq!(zone.shift_days(&holiday, -3), Some(Timespan::all_day(&date(3, 22), &date(3, 22)).unwrap()));
+    // too far for chrono
+    assert_eq!(zone.shift_days(&holiday, i64::MIN), None);
+    assert_eq!(zone.shift_days(&meeting, 1 << 40), None);
+}
+
+#[test]
+fn test_split_days() {
+    let zone = LocalZone(Berlin);
+    // Saturday 22:00 to Monday 02:00 in Berlin, across the clocks going forward on Sunday
+    let night = span(utc(3, 25, 21, 0), utc(3, 27, 0, 0));
+    assert_eq!(night.split_days(&zone), vec![
+        (date(3, 25), span(utc(3, 25, 21, 0), utc(3, 25, 23, 0))),
+        (date(3, 26), span(utc(3, 25, 23, 0), utc(3, 26, 22, 0))),
+        (date(3, 27), span(utc(3, 26, 22, 0), utc(3, 27, 0, 0))),
+    ]);
+    // ending at midnight does not reach into the next day, and a zero-length span has one part
+    let evening = span(utc(3, 24, 19, 0), utc(3, 24, 23, 0));
+    assert_eq!(evening.split_days(&zone), vec![(date(3, 24), evening.clone())]);
+    let midnight = span(utc(3, 24, 23, 0), utc(3, 24, 23, 0));
+    assert_eq!(midnight.split_days(&zone), vec![(date(3, 25), midnight.clone())]);
+
+    // an all-day span is split once it is resolved in the zone
+    let weekend = zone.resolve(&Timespan::all_day(&date(3, 25), &date(3, 26)).unwrap());
+    assert_eq!(weekend.split_days(&zone).into_iter().map(|x| x.0).collect::<Vec<_>>(), vec![date(3, 25), date(3, 26)]);
+    assert_eq!(zone.dates_span(&date(3, 25), &date(3, 26)), weekend);
+
+    // splitting stops at the last date there is
+    let end = DateTime::<Utc>::MAX_UTC;
+    let last = span(end - Duration::hours(36), end);
+    let parts = last.split_days(&LocalZone::default());
+    assert_eq!(parts.iter().map(|x| x.0).collect::<Vec<_>>(), vec![NaiveDate::MAX.pred_opt().unwrap(), NaiveDate::MAX]);
+    assert_eq!(parts[1].1.end, end);
+}
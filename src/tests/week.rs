@@ -0,0 +1,61 @@
+use chrono::{TimeZone, Utc, Weekday};
+use surrealdb::sql::Uuid;
+use time::{Date, Month};
+use crate::{common::{api::calendar::{CalendarItem, ItemKind}, model::{LocalZone, Timespan, WeekStart}}, frontend::calendar::{dates_from, day_span, first_of_week, is_all_day, weekday_names}};
+
+fn date(day: u8) -> Date {
+    Date::from_calendar_date(2023, Month::May, day).unwrap()
+}
+
+#[test]
+fn test_first_of_week() {
+    // May 3rd 2023 is a Wednesday
+    assert_eq!(first_of_week(date(3), WeekStart::default()), date(1));
+    assert_eq!(first_of_week(date(3), WeekStart(Weekday::Sun)), Date::from_calendar_date(2023, Month::April, 30).unwrap());
+    assert_eq!(first_of_week(date(3), WeekStart(Weekday::Wed)), date(3));
+    // a week starting before the first date there is starts on it
+    let mut week_start = Weekday::Mon;
+    for _ in 0..7 {
+        assert_eq!(first_of_week(Date::MIN, WeekStart(week_start)), Date::MIN);
+        week_start = week_start.succ();
+    }
+    assert_eq!(dates_from(date(30), 3), vec![date(30), date(31), Date::from_calendar_date(2023, Month::June, 1).unwrap()]);
+    assert_eq!(dates_from(Date::MAX, 7), vec![Date::MAX]);
+    assert_eq!(weekday_names(WeekStart(Weekday::Sat)), vec!["Sat", "Sun", "Mon", "Tue", "Wed", "Thu", "Fri"]);
+}
+
+#[test]
+fn test_all_day() {
+    let item = |start, end| CalendarItem {
+        kind: ItemKind::Event,
+        uuid: Uuid::new(),
+        recurrence_id: None,
+        name: String::new(),
+        timespan: Timespan::new(&Utc.with_ymd_and_hms(2023, 5, start, 0, 0, 0).unwrap(), &Utc.with_ymd_and_hms(2023, 5, end, 0, 0, 0).unwrap()).unwrap(),
+        all_day: false,
+        category: Uuid::new(),
+        color: 0,
+    };
+    assert!(is_all_day(&item(1, 2), &day_span(date(1), LocalZone::default())));
+    // a holiday spanning several days is all-day on each of them
+    assert!(is_all_day(&item(1, 4), &day_span(date(3), LocalZone::default())));
+    assert!(!is_all_day(&item(1, 4), &day_span(date(4), LocalZone::default())));
+    assert!(!is_all_day(&item(1, 1), &day_span(date(1), LocalZone::default())));
+    // the flag alone puts an item above the timeline
+    let mut short = item(1, 1);
+    short.all_day = true;
+    assert!(is_all_day(&short, &day_span(date(1), LocalZone::default())));
+}
+
+#[cfg(feature = "kv-mem")]
+#[tokio::test]
+async fn test_week_start_embedded_surreal() {
+    use crate::backend::database::{connection::{connect, DatabaseEngine}, migrations::migrate, store::Store};
+
+    let db = connect(&DatabaseEngine::Memory).await.unwrap();
+    migrate(&db).await.unwrap();
+    let user = db.add_user("micha", "long enough").await.unwrap();
+    assert_eq!(user.week_start, WeekStart(Weekday::Mon));
+    db.set_week_start(&user.uuid, WeekStart(Weekday::Sun)).await.unwrap();
+    assert_eq!(db.get_user(&user.uuid).await.unwrap().week_start, WeekStart(Weekday::Sun));
+}
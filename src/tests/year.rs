@@ -0,0 +1,40 @@
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use surrealdb::sql::Uuid;
+use crate::common::{api::calendar::{day_activity, CalendarItem, DayActivity, ItemKind}, model::{LocalZone, Timespan}};
+
+fn item(category: &Uuid, color: u32, start: DateTime<Utc>, hours: i64) -> CalendarItem {
+    CalendarItem {
+        kind: ItemKind::Event,
+        uuid: Uuid::new(),
+        recurrence_id: None,
+        name: String::new(),
+        timespan: Timespan::new(&start, &(start + Duration::hours(hours))).unwrap(),
+        all_day: false,
+        category: category.clone(),
+        color,
+    }
+}
+
+#[test]
+fn test_day_activity() {
+    let first = Utc.with_ymd_and_hms(2023, 1, 1, 0, 0, 0).unwrap();
+    let (work, home) = (Uuid::new(), Uuid::new());
+    let items = [
+        item(&home, 0x00FF00, first + Duration::hours(9), 1),
+        item(&work, 0xFF0000, first + Duration::hours(10), 1),
+        item(&work, 0xFF0000, first + Duration::hours(11), 1),
+        // a tie is decided by the category that comes first
+        item(&home, 0x00FF00, first + Duration::days(1), 1),
+        item(&work, 0xFF0000, first + Duration::days(1) + Duration::hours(1), 1),
+        // a holiday counts on every day it covers
+        item(&home, 0x00FF00, first + Duration::days(3), 48),
+    ];
+    assert_eq!(day_activity(&items, &first.date_naive(), 6, &LocalZone::default()), vec![
+        DayActivity { count: 3, color: Some(0xFF0000) },
+        DayActivity { count: 2, color: Some(0x00FF00) },
+        DayActivity { count: 0, color: None },
+        DayActivity { count: 1, color: Some(0x00FF00) },
+        DayActivity { count: 1, color: Some(0x00FF00) },
+        DayActivity { count: 0, color: None },
+    ]);
+}